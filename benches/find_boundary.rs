@@ -0,0 +1,104 @@
+//! Times the boundary search, both on its own and while reading a large field through
+//! `Multipart`.
+//!
+//! Run with `cargo bench --bench find_boundary`.
+use std::convert::Infallible;
+use std::time::Instant;
+
+use futures::executor::block_on;
+use futures::{stream, TryStreamExt};
+
+use multipart_async::server::Multipart;
+
+const ITERS: u32 = 20;
+const CHUNK_SIZE: usize = 64 * 1024;
+const FIELD_LEN: usize = 16 * 1024 * 1024;
+
+/// A boundary as generated by browsers
+const BOUNDARY: &str = "----WebKitFormBoundary7MA4YWxkTrZu0gW";
+
+/// Field data with frequent near-misses of the boundary, i.e. newlines and dashes
+fn make_field() -> Vec<u8> {
+    let mut state = 0x2545_F491_4F6C_DD1Du64;
+
+    (0..FIELD_LEN)
+        .map(|_| {
+            // xorshift
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+
+            match state % 64 {
+                0 => b'\n',
+                1 => b'-',
+                n => b'a' + (n % 26) as u8,
+            }
+        })
+        .collect()
+}
+
+fn report(name: &str, len: usize, start: Instant) {
+    let per_iter = start.elapsed() / ITERS;
+    let mib_per_sec = (len as f64 / (1024. * 1024.)) / per_iter.as_secs_f64();
+
+    println!(
+        "{:<16} {:>10.2?}/iter, {:>8.1} MiB/s",
+        name, per_iter, mib_per_sec
+    );
+}
+
+fn bench_search(name: &str, field: &[u8], mut search: impl FnMut(&[u8]) -> Option<usize>) {
+    let start = Instant::now();
+
+    for _ in 0..ITERS {
+        for chunk in field.chunks(CHUNK_SIZE) {
+            assert_eq!(search(chunk), None);
+        }
+    }
+
+    report(name, field.len(), start);
+}
+
+fn bench_multipart(field: &[u8]) {
+    let mut body = format!(
+        "--{}\r\nContent-Disposition: form-data; name=\"file\"\r\n\r\n",
+        BOUNDARY
+    )
+    .into_bytes();
+    body.extend_from_slice(field);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", BOUNDARY).as_bytes());
+
+    let start = Instant::now();
+
+    for _ in 0..ITERS {
+        let chunks = body.chunks(CHUNK_SIZE).map(Ok::<_, Infallible>);
+        let mut multipart = Multipart::with_body(stream::iter(chunks), BOUNDARY);
+
+        let read = block_on(async {
+            let field = multipart.next_field().await.unwrap().unwrap();
+            field
+                .data
+                .try_fold(0, |read, chunk| async move { Ok(read + chunk.len()) })
+                .await
+                .unwrap()
+        });
+
+        assert_eq!(read, field.len());
+    }
+
+    report("multipart", field.len(), start);
+}
+
+fn main() {
+    let field = make_field();
+    let needle = format!("--{}", BOUNDARY).into_bytes();
+
+    bench_search("memmem::find", &field, |chunk| {
+        memchr::memmem::find(chunk, &needle)
+    });
+
+    let finder = memchr::memmem::Finder::new(&needle);
+    bench_search("memmem::Finder", &field, |chunk| finder.find(chunk));
+
+    bench_multipart(&field);
+}
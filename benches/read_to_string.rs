@@ -0,0 +1,67 @@
+//! Times `FieldData::read_to_string()` on ASCII-only and mixed ASCII/non-ASCII text fields.
+//!
+//! Run with `cargo bench --bench read_to_string`.
+use std::convert::Infallible;
+use std::time::Instant;
+
+use futures::executor::block_on;
+use futures::stream;
+
+use multipart_async::server::Multipart;
+
+const ITERS: u32 = 200;
+const CHUNK_SIZE: usize = 8 * 1024;
+
+fn make_body(text: &str) -> Vec<u8> {
+    format!(
+        "--boundary\r\n\
+         Content-Disposition: form-data; name=\"text\"\r\n\r\n\
+         {}\r\n\
+         --boundary--\r\n",
+        text
+    )
+    .into_bytes()
+}
+
+fn read_field(body: &[u8]) -> String {
+    let chunks = body.chunks(CHUNK_SIZE).map(Ok::<_, Infallible>);
+    let mut multipart = Multipart::with_body(stream::iter(chunks), "boundary");
+
+    block_on(async {
+        let field = multipart.next_field().await.unwrap().unwrap();
+        field.data.read_to_string().await.unwrap()
+    })
+}
+
+fn bench(name: &str, text: &str) {
+    let body = make_body(text);
+    assert_eq!(read_field(&body), text);
+
+    let start = Instant::now();
+
+    for _ in 0..ITERS {
+        read_field(&body);
+    }
+
+    let elapsed = start.elapsed();
+    let per_iter = elapsed / ITERS;
+    let mib_per_sec = (text.len() as f64 / (1024. * 1024.)) / per_iter.as_secs_f64();
+
+    println!(
+        "{:<8} {:>10} bytes: {:>10.2?}/iter, {:>8.1} MiB/s",
+        name,
+        text.len(),
+        per_iter,
+        mib_per_sec
+    );
+}
+
+fn main() {
+    let ascii = include_str!("../lorem_ipsum.txt").repeat(1024);
+    // mostly ASCII with a multi-byte character every few hundred bytes, which will
+    // regularly straddle chunk boundaries
+    let mixed = ascii.replace(". ", ". \u{00B6} ");
+
+    bench("ascii", &ascii);
+    bench("mixed", &mixed);
+}
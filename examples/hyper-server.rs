@@ -3,7 +3,7 @@ use hyper::rt;
 use hyper::server::conn::AddrStream;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Request, Response, Server, StatusCode};
-use multipart_async::server::Multipart;
+use multipart_async::server::prelude::*;
 use std::net::TcpStream;
 
 use futures::{Future, FutureExt, TryStreamExt};
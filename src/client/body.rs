@@ -0,0 +1,342 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! A `multipart/form-data` request body which is produced lazily as a `Stream`, for HTTP
+//! clients which pull the body instead of having it written to them, e.g. with
+//! `hyper::Body::wrap_stream()`.
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::{StreamExt, TryStreamExt};
+use mime::Mime;
+use tokio::io::AsyncRead;
+
+use crate::BodyChunk;
+
+use super::content_type_str;
+use super::writer::{check_field_name, check_no_collision, field_header};
+
+/// The size of the chunks read from an `AsyncRead` field
+const READ_BUF_SIZE: usize = 8 * 1024;
+
+/// A `multipart/form-data` request body as a `Stream` of `Bytes`, returned by
+/// [`MultipartRequest::into_stream()`](../struct.MultipartRequest.html#method.into_stream).
+///
+/// Fields are added up front with the builder methods, but nothing is read from them until
+/// the stream is polled, and only one chunk is held at a time. The boundary, the headers and
+/// the data of each field are yielded as separate chunks.
+///
+/// Errors from a field's source are yielded as-is and end the body; the request should be
+/// aborted. As with [`MultipartWriter`](../writer/struct.MultipartWriter.html), a field name
+/// containing CR or LF yields an error of kind `InvalidInput` when that field is reached.
+pub struct StreamingBody {
+    boundary: String,
+    fields: VecDeque<PendingField>,
+    current: Option<Source>,
+    read_buf: Vec<u8>,
+    data_written: bool,
+    finished: bool,
+}
+
+struct PendingField {
+    header: io::Result<String>,
+    source: Source,
+}
+
+enum Source {
+    Bytes(Bytes),
+    Read(Box<dyn AsyncRead + Send + Unpin>),
+    Stream(Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>),
+}
+
+impl StreamingBody {
+    pub(crate) fn new(boundary: String) -> Self {
+        StreamingBody {
+            boundary,
+            fields: VecDeque::new(),
+            current: None,
+            read_buf: Vec::new(),
+            data_written: false,
+            finished: false,
+        }
+    }
+
+    fn push_field(
+        mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        source: Source,
+    ) -> Self {
+        let header = check_field_name(name)
+            .map(|_| field_header(&self.boundary, Some(name), filename, content_type, false));
+
+        let header = match source {
+            Source::Bytes(ref data) => {
+                header.and_then(|header| check_no_collision(&self.boundary, data).map(|_| header))
+            }
+            _ => header,
+        };
+
+        self.fields.push_back(PendingField { header, source });
+        self
+    }
+
+    /// Add a plain text field.
+    ///
+    /// See [`MultipartWriter::write_text()`](../writer/struct.MultipartWriter.html#method.write_text)
+    /// for details; an error of kind `InvalidInput` is yielded if `text` contains the boundary.
+    pub fn text<T: Into<String>>(self, name: &str, text: T) -> Self {
+        self.bytes(name, None, None, text.into())
+    }
+
+    /// Add a field with its data held in memory.
+    ///
+    /// See [`MultipartWriter::write_field()`](../writer/struct.MultipartWriter.html#method.write_field)
+    /// for details on these parameters; an error of kind `InvalidInput` is yielded if `data`
+    /// contains the boundary.
+    pub fn bytes<B: Into<Bytes>>(
+        self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        data: B,
+    ) -> Self {
+        self.push_field(name, filename, content_type, Source::Bytes(data.into()))
+    }
+
+    /// Add a field which is read from `contents` when the stream reaches it.
+    ///
+    /// See [`MultipartWriter::write_field()`](../writer/struct.MultipartWriter.html#method.write_field)
+    /// for details on these parameters.
+    pub fn reader<R>(
+        self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        contents: R,
+    ) -> Self
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        self.push_field(
+            name,
+            filename,
+            content_type,
+            Source::Read(Box::new(contents)),
+        )
+    }
+
+    /// Add a field whose chunks are taken from `contents` when the stream reaches it.
+    ///
+    /// Errors from the stream will be wrapped as `io::ErrorKind::Other`.
+    pub fn stream<B, E, S>(
+        self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        contents: S,
+    ) -> Self
+    where
+        B: Into<Bytes> + 'static,
+        E: Into<Box<dyn Error + Send + Sync>> + 'static,
+        S: Stream<Item = Result<B, E>> + Send + 'static,
+    {
+        let contents = contents.map_ok(Into::into).map_err(io::Error::other);
+
+        self.push_field(
+            name,
+            filename,
+            content_type,
+            Source::Stream(contents.boxed()),
+        )
+    }
+
+    /// The value of the `Content-Type` header to be sent with this body.
+    pub fn content_type(&self) -> String {
+        content_type_str(&self.boundary)
+    }
+
+    fn poll_source(&mut self, cx: &mut Context) -> Poll<Option<io::Result<Bytes>>> {
+        let source = match self.current {
+            Some(ref mut source) => source,
+            None => return Poll::Ready(None),
+        };
+
+        match source {
+            Source::Bytes(data) => {
+                let data = std::mem::take(data);
+                Poll::Ready(if data.is_empty() {
+                    None
+                } else {
+                    Some(Ok(data))
+                })
+            }
+            Source::Read(read) => {
+                self.read_buf.resize(READ_BUF_SIZE, 0);
+
+                match ready!(Pin::new(read).poll_read(cx, &mut self.read_buf)) {
+                    Ok(0) => Poll::Ready(None),
+                    Ok(read) => {
+                        Poll::Ready(Some(Ok(Bytes::copy_from_slice(&self.read_buf[..read]))))
+                    }
+                    Err(e) => Poll::Ready(Some(Err(e))),
+                }
+            }
+            Source::Stream(stream) => stream.as_mut().poll_next(cx),
+        }
+    }
+}
+
+impl Stream for StreamingBody {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        while this.current.is_some() {
+            match ready!(this.poll_source(cx)) {
+                // an empty chunk would look like the end of the body to some clients
+                Some(Ok(chunk)) if chunk.is_empty() => continue,
+                Some(Ok(chunk)) => return Poll::Ready(Some(Ok(chunk))),
+                Some(Err(e)) => {
+                    this.finished = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                None => {
+                    this.current = None;
+                    return Poll::Ready(Some(Ok(Bytes::from_static(b"\r\n"))));
+                }
+            }
+        }
+
+        match this.fields.pop_front() {
+            Some(PendingField {
+                header: Ok(header),
+                source,
+            }) => {
+                this.current = Some(source);
+                this.data_written = true;
+                Poll::Ready(Some(Ok(header.into())))
+            }
+            Some(PendingField { header: Err(e), .. }) => {
+                this.finished = true;
+                Poll::Ready(Some(Err(e)))
+            }
+            None => {
+                this.finished = true;
+
+                if this.data_written {
+                    // trailing newline isn't necessary per the spec but some clients are expecting it
+                    // https://github.com/actix/actix-web/issues/598
+                    let trailer = format!("--{}--\r\n", this.boundary);
+                    Poll::Ready(Some(Ok(trailer.into())))
+                } else {
+                    Poll::Ready(None)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for StreamingBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StreamingBody")
+            .field("boundary", &self.boundary)
+            .field("fields_pending", &self.fields.len())
+            .field("finished", &self.finished)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_streaming_body() {
+    use futures_util::stream;
+
+    let chunks: Vec<Result<&'static [u8], io::Error>> = vec![Ok(b"chunk one, "), Ok(b"chunk two")];
+
+    let body = StreamingBody::new("boundary".to_string())
+        .text("hello", "world!")
+        .reader(
+            "file",
+            Some("file.txt"),
+            Some(&mime::TEXT_PLAIN),
+            &b"file contents"[..],
+        )
+        .stream("stream", None, None, stream::iter(chunks));
+
+    assert_eq!(
+        body.content_type(),
+        "multipart/form-data; boundary=boundary"
+    );
+
+    let chunks: Vec<Bytes> = body.try_collect().await.unwrap();
+
+    assert_eq!(
+        chunks.concat(),
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"hello\"\r\n\r\n\
+          world!\r\n\
+          --boundary\r\n\
+          Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+          Content-Type: text/plain\r\n\r\n\
+          file contents\r\n\
+          --boundary\r\n\
+          Content-Disposition: form-data; name=\"stream\"\r\n\r\n\
+          chunk one, chunk two\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    // the body can be handed straight to hyper
+    let _ = hyper::Body::wrap_stream(StreamingBody::new("boundary".to_string()).text("a", "b"));
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_streaming_body_errors() {
+    let mut body = StreamingBody::new("boundary".to_string())
+        .text("first", "ok")
+        .text("bad\r\nname", "text");
+
+    let mut chunks = Vec::new();
+
+    let err = loop {
+        match body.next().await.expect("body ended without an error") {
+            Ok(chunk) => chunks.push(chunk),
+            Err(e) => break e,
+        }
+    };
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    // the first field was written in full
+    assert_eq!(
+        chunks.concat(),
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"first\"\r\n\r\n\
+          ok\r\n"[..]
+    );
+    assert!(body.next().await.is_none());
+
+    let mut body = StreamingBody::new("boundary".to_string()).text("text", "--boundary--");
+    let err = body.next().await.unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    // an empty body has no trailing boundary
+    let body = StreamingBody::new("boundary".to_string());
+    let chunks: Vec<Bytes> = body.try_collect().await.unwrap();
+    assert!(chunks.is_empty());
+}
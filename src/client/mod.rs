@@ -9,44 +9,144 @@
 //!
 //! Use this when sending POST requests with files to a server.
 
+use std::io;
+
+use bytes::Bytes;
+use futures_util::sink::Sink;
 use http::HeaderValue;
 use rand::distributions::{Alphanumeric, Distribution};
+use rand::rngs::OsRng;
+use rand::Rng;
 use tokio::io::AsyncWrite;
 
+use crate::client::body::StreamingBody;
+use crate::client::sink::MultipartSink;
 use crate::client::writer::MultipartWriter;
 
+pub mod body;
+pub mod prelude;
+pub mod sink;
 pub mod writer;
 
 const BOUNDARY_LEN: usize = 32;
 
+/// The maximum length of a boundary per
+/// [IETF RFC 2046 section 5.1.1](https://tools.ietf.org/html/rfc2046#section-5.1.1)
+const MAX_BOUNDARY_LEN: usize = 70;
+
+fn random_boundary<R: Rng>(rng: R) -> String {
+    let mut boundary = String::with_capacity(BOUNDARY_LEN);
+    boundary.extend(Alphanumeric.sample_iter(rng).take(BOUNDARY_LEN));
+    boundary
+}
+
+/// Characters allowed in a boundary by RFC 2046 which may also appear unquoted in the
+/// `Content-Type` header
+fn is_boundary_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "'+_-.".contains(c)
+}
+
+/// The value of the `Content-Type` header for a request with the given boundary
+pub(crate) fn content_type_str(boundary: &str) -> String {
+    format!("multipart/form-data; boundary={}", boundary)
+}
+
 pub struct MultipartRequest {
     boundary: String,
 }
 
 impl MultipartRequest {
     /// Start building a new `multipart/form-data` request.
+    ///
+    /// The boundary is sampled from `rand::thread_rng()`, a CSPRNG seeded from the OS and
+    /// periodically reseeded, which is fast enough to use for every request.
     pub fn new() -> Self {
-        let mut boundary = String::with_capacity(BOUNDARY_LEN);
-        boundary.extend(
-            Alphanumeric
-                .sample_iter(rand::thread_rng())
-                .take(BOUNDARY_LEN),
-        );
+        MultipartRequest {
+            boundary: random_boundary(rand::thread_rng()),
+        }
+    }
 
-        MultipartRequest { boundary }
+    /// Start building a new `multipart/form-data` request with a boundary sampled directly from
+    /// the operating system's RNG (`rand::rngs::OsRng`), instead of a userspace RNG seeded
+    /// from it as with [`::new()`](#method.new).
+    ///
+    /// This is slower, as each boundary needs a syscall, but leaves no RNG state in the process
+    /// to recover the boundary from, e.g. where a guessed boundary would allow a field to be
+    /// smuggled into a signed request.
+    ///
+    /// ### Panics
+    /// If the OS RNG fails, which should only happen if it cannot be accessed at all.
+    pub fn new_secure() -> Self {
+        MultipartRequest {
+            boundary: random_boundary(OsRng),
+        }
+    }
+
+    /// Start building a new `multipart/form-data` request with the given boundary instead of a
+    /// random one, e.g. for reproducible output in tests.
+    ///
+    /// The boundary must be 1 to 70 characters long and consist of ASCII letters and digits or
+    /// `'`, `+`, `_`, `-` and `.`; this is the set allowed by
+    /// [IETF RFC 2046 section 5.1.1](https://tools.ietf.org/html/rfc2046#section-5.1.1)
+    /// minus the characters which would need the `boundary` parameter of `Content-Type` to be
+    /// quoted. Returns an error with a kind of `InvalidInput` otherwise.
+    ///
+    /// The boundary must not appear in any field data; the writers check for this where they
+    /// can, but a random boundary is the safer choice for data that isn't known in advance.
+    pub fn with_boundary<B: Into<String>>(boundary: B) -> io::Result<Self> {
+        let boundary = boundary.into();
+
+        let invalid = |msg| {
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid multipart boundary {:?}: {}", boundary, msg),
+            ))
+        };
+
+        if boundary.is_empty() {
+            return invalid("empty".into());
+        }
+
+        if boundary.len() > MAX_BOUNDARY_LEN {
+            return invalid(format!("longer than {} characters", MAX_BOUNDARY_LEN));
+        }
+
+        if let Some(c) = boundary.chars().find(|&c| !is_boundary_char(c)) {
+            return invalid(format!("contains disallowed character {:?}", c));
+        }
+
+        Ok(MultipartRequest { boundary })
     }
 
     /// Get the value of the `Content-Type` header to be sent to the server.
     pub fn get_content_type(&self) -> HeaderValue {
-        format!("multipart/form-data; boundary={}", self.boundary)
+        self.get_content_type_str()
             .parse()
             .expect("this should be a valid header value")
     }
 
+    /// Get the value of the `Content-Type` header to be sent to the server as a `String`.
+    ///
+    /// Useful for HTTP clients that don't use the types from the `http` crate.
+    pub fn get_content_type_str(&self) -> String {
+        content_type_str(&self.boundary)
+    }
+
     /// Wrap a `AsyncWrite` impl.
     pub fn wrap_writer<W: AsyncWrite + Unpin>(self, writer: W) -> MultipartWriter<W> {
         MultipartWriter::new(writer, self.boundary)
     }
+
+    /// Wrap a `Sink<Bytes>` impl.
+    pub fn wrap_sink<S: Sink<Bytes> + Unpin>(self, sink: S) -> MultipartSink<S> {
+        MultipartSink::new(sink, self.boundary)
+    }
+
+    /// Build a body which is produced lazily as a `Stream` of `Bytes`, e.g. to pass to
+    /// `hyper::Body::wrap_stream()`.
+    pub fn into_stream(self) -> StreamingBody {
+        StreamingBody::new(self.boundary)
+    }
 }
 
 #[test]
@@ -60,3 +160,62 @@ fn test_multipart_get_content_type() {
         "multipart/form-data; boundary=boundary"
     );
 }
+
+#[test]
+fn test_multipart_request_with_boundary() {
+    let request = MultipartRequest::with_boundary("my-Boundary_1.0+'").unwrap();
+    assert_eq!(
+        request.get_content_type_str(),
+        "multipart/form-data; boundary=my-Boundary_1.0+'"
+    );
+
+    let max_len = "a".repeat(MAX_BOUNDARY_LEN);
+    assert!(MultipartRequest::with_boundary(max_len).is_ok());
+
+    for invalid in &[
+        "",
+        &"a".repeat(MAX_BOUNDARY_LEN + 1)[..],
+        "with space",
+        "semi;colon",
+        "quote\"",
+        "param=value",
+        "new\r\nline",
+        "caf\u{E9}",
+    ] {
+        let err = MultipartRequest::with_boundary(*invalid)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{:?}", invalid);
+    }
+
+    // a random boundary passes the same checks
+    let random = MultipartRequest::new();
+    assert!(MultipartRequest::with_boundary(random.boundary).is_ok());
+}
+
+#[test]
+fn test_multipart_request_new_secure() {
+    let first = MultipartRequest::new_secure().boundary;
+    assert_eq!(first.len(), BOUNDARY_LEN);
+    assert!(
+        first.chars().all(|c| c.is_ascii_alphanumeric()),
+        "{}",
+        first
+    );
+    assert!(MultipartRequest::with_boundary(&first[..]).is_ok());
+
+    assert_ne!(first, MultipartRequest::new_secure().boundary);
+}
+
+#[test]
+fn test_multipart_get_content_type_str() {
+    let request = MultipartRequest {
+        boundary: "boundary".to_string(),
+    };
+
+    assert_eq!(
+        request.get_content_type_str(),
+        "multipart/form-data; boundary=boundary"
+    );
+    assert_eq!(request.get_content_type(), &*request.get_content_type_str());
+}
@@ -0,0 +1,35 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! The types needed to write most requests, for glob importing.
+//!
+//! ```rust
+//! use multipart_async::client::prelude::*;
+//!
+//! async fn write_form() -> std::io::Result<Vec<u8>> {
+//!     let mut writer = MultipartRequest::new().wrap_writer(Vec::new());
+//!
+//!     writer
+//!         .write_text("hello", "world!")
+//!         .await?
+//!         .write_part_with_disposition(
+//!             &ContentDisposition::new("form-data")
+//!                 .name("file")
+//!                 .filename("hello.txt"),
+//!             None,
+//!             &b"file contents"[..],
+//!         )
+//!         .await?;
+//!
+//!     writer.finish_into_inner().await
+//! }
+//! ```
+pub use super::body::StreamingBody;
+pub use super::sink::{MultipartSink, SinkError};
+pub use super::writer::{ContentDisposition, MultipartWriter};
+#[cfg(feature = "compression")]
+pub use super::writer::Compression;
+pub use super::MultipartRequest;
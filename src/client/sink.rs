@@ -0,0 +1,278 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! A `multipart/form-data` writer for HTTP clients which take the request body as a
+//! `Sink<Bytes>`.
+use std::{error, fmt, io};
+
+use bytes::Bytes;
+use futures_core::Stream;
+use futures_util::sink::{Sink, SinkExt};
+use futures_util::TryStreamExt;
+use mime::Mime;
+
+use super::writer::{check_field_name, check_no_collision, field_header};
+
+/// The error type returned by [`MultipartSink`](struct.MultipartSink.html).
+#[derive(Debug)]
+pub enum SinkError<E> {
+    /// An argument was rejected before anything was sent, with a kind of `InvalidInput`,
+    /// e.g. a field name containing CR or LF.
+    InvalidInput(io::Error),
+    /// The underlying `Sink` (or the `Stream` passed to `write_stream()`) returned an error.
+    Sink(E),
+}
+
+impl<E: fmt::Display> fmt::Display for SinkError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SinkError::InvalidInput(e) => e.fmt(f),
+            SinkError::Sink(e) => e.fmt(f),
+        }
+    }
+}
+
+impl<E: error::Error + 'static> error::Error for SinkError<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SinkError::InvalidInput(e) => Some(e),
+            SinkError::Sink(e) => Some(e),
+        }
+    }
+}
+
+/// Like [`MultipartWriter`](../writer/struct.MultipartWriter.html) but writes to a
+/// `Sink<Bytes>` instead of `AsyncWrite`.
+///
+/// Boundaries, headers and field contents are sent as separate `Bytes` items.
+pub struct MultipartSink<S> {
+    inner: S,
+    boundary: String,
+    data_written: bool,
+}
+
+impl<S> MultipartSink<S> {
+    pub(crate) fn new(inner: S, boundary: String) -> Self {
+        MultipartSink {
+            inner,
+            boundary,
+            data_written: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: Sink<Bytes> + Unpin> MultipartSink<S> {
+    async fn feed(&mut self, item: Bytes) -> Result<(), SinkError<S::Error>> {
+        self.inner.feed(item).await.map_err(SinkError::Sink)
+    }
+
+    async fn write_field_header(
+        &mut self,
+        name: Option<&str>,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+    ) -> Result<(), SinkError<S::Error>> {
+        if let Some(name) = name {
+            check_field_name(name).map_err(SinkError::InvalidInput)?;
+        }
+
+        let header = field_header(&self.boundary, name, filename, content_type, false);
+        self.feed(header.into()).await?;
+        self.data_written = true;
+        Ok(())
+    }
+
+    /// Write a field of any type to the output.
+    ///
+    /// See [`MultipartWriter::write_field()`](../writer/struct.MultipartWriter.html#method.write_field)
+    /// for details on these parameters.
+    ///
+    /// Returns `SinkError::InvalidInput` before sending anything if `name` contains CR or LF,
+    /// or if `contents` contains the boundary.
+    pub async fn write_field(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        contents: impl Into<Bytes>,
+    ) -> Result<&mut Self, SinkError<S::Error>> {
+        self.write_part(Some(name), filename, content_type, contents)
+            .await
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but `name` may be omitted.
+    ///
+    /// See [`MultipartWriter::write_part()`](../writer/struct.MultipartWriter.html#method.write_part)
+    /// for details.
+    pub async fn write_part(
+        &mut self,
+        name: Option<&str>,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        contents: impl Into<Bytes>,
+    ) -> Result<&mut Self, SinkError<S::Error>> {
+        let contents = contents.into();
+        check_no_collision(&self.boundary, &contents).map_err(SinkError::InvalidInput)?;
+
+        self.write_field_header(name, filename, content_type)
+            .await?;
+        self.feed(contents).await?;
+        self.feed(Bytes::from_static(b"\r\n")).await?;
+        Ok(self)
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but takes a `Stream`, sending each chunk
+    /// as it is yielded.
+    pub async fn write_stream<B, St>(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        mut contents: St,
+    ) -> Result<&mut Self, SinkError<S::Error>>
+    where
+        B: Into<Bytes>,
+        St: Stream<Item = Result<B, S::Error>> + Unpin,
+    {
+        self.write_field_header(Some(name), filename, content_type)
+            .await?;
+
+        while let Some(buf) = contents.try_next().await.map_err(SinkError::Sink)? {
+            self.feed(buf.into()).await?;
+        }
+
+        self.feed(Bytes::from_static(b"\r\n")).await?;
+        Ok(self)
+    }
+
+    /// Write a plain text field to the output.
+    ///
+    /// See [`MultipartWriter::write_text()`](../writer/struct.MultipartWriter.html#method.write_text)
+    /// for details.
+    pub async fn write_text(
+        &mut self,
+        name: &str,
+        text: &str,
+    ) -> Result<&mut Self, SinkError<S::Error>> {
+        self.write_field(name, None, None, text.to_string()).await
+    }
+
+    /// Complete the `multipart/form-data` request.
+    ///
+    /// Sends the trailing boundary and flushes the sink.
+    pub async fn finish(&mut self) -> Result<(), SinkError<S::Error>> {
+        if self.data_written {
+            // trailing newline isn't necessary per the spec but some clients are expecting it
+            // https://github.com/actix/actix-web/issues/598
+            let trailer = format!("--{}--\r\n", self.boundary);
+            self.feed(trailer.into()).await?;
+        }
+
+        self.inner.flush().await.map_err(SinkError::Sink)
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_sink_matches_writer() {
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    use super::writer::MultipartWriter;
+
+    let (tx, rx) = mpsc::unbounded::<Bytes>();
+
+    let mut sink = MultipartSink::new(tx, "boundary".to_string());
+    sink.write_text("hello", "world!")
+        .await
+        .unwrap()
+        .write_part(None, Some("file1.txt"), Some(&mime::TEXT_PLAIN), "contents")
+        .await
+        .unwrap()
+        .finish()
+        .await
+        .unwrap();
+
+    // dropping the sender ends the stream
+    drop(sink);
+    let sent: Vec<Bytes> = rx.collect().await;
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    writer
+        .write_text("hello", "world!")
+        .await
+        .unwrap()
+        .write_part(
+            None,
+            Some("file1.txt"),
+            Some(&mime::TEXT_PLAIN),
+            &b"contents"[..],
+        )
+        .await
+        .unwrap()
+        .finish()
+        .await
+        .unwrap();
+
+    assert_eq!(sent.concat(), writer.into_inner());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_sink_header_injection() {
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    let (tx, rx) = mpsc::unbounded::<Bytes>();
+
+    let mut sink = MultipartSink::new(tx, "boundary".to_string());
+
+    for name in &["foo\r\nEvil: header", "foo\nbar", "foo\r"] {
+        match sink.write_text(name, "bar").await {
+            Err(SinkError::InvalidInput(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+            Err(e) => panic!("unexpected error for name {:?}: {:?}", name, e),
+            Ok(_) => panic!("name {:?} should have been rejected", name),
+        }
+    }
+
+    // nothing was sent, so there's not even a trailing boundary
+    sink.finish().await.unwrap();
+    drop(sink);
+    assert!(rx.collect::<Vec<Bytes>>().await.is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_sink_boundary_collision() {
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    let (tx, rx) = mpsc::unbounded::<Bytes>();
+
+    let mut sink = MultipartSink::new(tx, "boundary".to_string());
+
+    match sink.write_text("foo", "bar\r\n--boundary--").await {
+        Err(SinkError::InvalidInput(e)) => assert_eq!(e.kind(), io::ErrorKind::InvalidInput),
+        Err(e) => panic!("unexpected error: {:?}", e),
+        Ok(_) => panic!("data containing the boundary should have been rejected"),
+    }
+
+    sink.finish().await.unwrap();
+    drop(sink);
+    assert!(rx.collect::<Vec<Bytes>>().await.is_empty());
+}
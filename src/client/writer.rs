@@ -5,6 +5,7 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 use std::error::Error;
+use std::fmt::{self, Write as _};
 use std::future::Future;
 use std::io::{Cursor};
 use std::path::Path;
@@ -13,14 +14,17 @@ use std::task::{Context, Poll};
 
 use futures_core::Stream;
 use futures_util::TryStreamExt;
-use http::header::HeaderName;
+use http::header::{HeaderMap, HeaderName, HeaderValue};
 use mime::Mime;
 use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
+use crate::BodyChunk;
+
 pub struct MultipartWriter<W> {
     inner: W,
     boundary: String,
     data_written: bool,
+    filename_first: bool,
 }
 
 impl<W> MultipartWriter<W> {
@@ -29,33 +33,36 @@ impl<W> MultipartWriter<W> {
             inner,
             boundary,
             data_written: false,
+            filename_first: false,
         }
     }
 
+    /// If `true`, emit the `filename` parameter of `Content-Disposition` before `name`.
+    ///
+    /// `name` comes first by default, which is what browsers send; this is intended for testing
+    /// interoperability with servers that are sensitive to the order.
+    pub fn filename_before_name(mut self, filename_first: bool) -> Self {
+        self.filename_first = filename_first;
+        self
+    }
+
     fn get_field_header(
         &self,
-        name: &str,
+        name: Option<&str>,
         filename: Option<&str>,
         content_type: Option<&Mime>,
-    ) -> String {
-        use std::fmt::Write;
-
-        let mut header = format!(
-            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
-            self.boundary, name
-        );
-
-        if let Some(filename) = filename {
-            write!(header, "; filename=\"{}\"", filename).unwrap();
-        }
-
-        if let Some(content_type) = content_type {
-            write!(header, "\r\nContent-Type: {}", content_type);
+    ) -> io::Result<String> {
+        if let Some(name) = name {
+            check_field_name(name)?;
         }
 
-        header.push_str("\r\n\r\n");
-
-        header
+        Ok(field_header(
+            &self.boundary,
+            name,
+            filename,
+            content_type,
+            self.filename_first,
+        ))
     }
 
     pub fn get_ref(&self) -> &W {
@@ -71,19 +78,323 @@ impl<W> MultipartWriter<W> {
     }
 }
 
+/// The `Content-Disposition` header of a part, for full control over its disposition type
+/// and parameters, e.g. when re-encoding fields received by a server.
+///
+/// Parameter values are emitted as quoted strings, with `"` and `\` escaped. CR and LF are
+/// percent-encoded as `%0D` and `%0A` anywhere in the header, which is what browsers do.
+///
+/// A filename containing non-ASCII characters is also emitted as an extended `filename*`
+/// parameter ([RFC 6266, Section 4.3](https://tools.ietf.org/html/rfc6266#section-4.3)),
+/// percent-encoded as UTF-8, with the non-ASCII characters replaced by `_` in `filename` for
+/// servers which don't understand it.
+///
+/// ```rust
+/// # use multipart_async::client::writer::ContentDisposition;
+/// let disposition = ContentDisposition::new("attachment")
+///     .name("file")
+///     .filename("report.pdf")
+///     .param("creation-date", "Wed, 12 Feb 1997 16:29:51 -0500");
+///
+/// assert_eq!(
+///     disposition.to_string(),
+///     "attachment; name=\"file\"; filename=\"report.pdf\"; \
+///      creation-date=\"Wed, 12 Feb 1997 16:29:51 -0500\""
+/// );
+///
+/// assert_eq!(
+///     ContentDisposition::new("form-data").filename("r\u{E9}sum\u{E9}.pdf").to_string(),
+///     "form-data; filename=\"r_sum_.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9.pdf"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct ContentDisposition {
+    disposition_type: String,
+    params: Vec<Param>,
+}
+
+#[derive(Clone, Debug)]
+struct Param {
+    key: String,
+    value: String,
+    /// Emit as an RFC 5987 extended value instead of a quoted string
+    extended: bool,
+}
+
+impl ContentDisposition {
+    /// Start a header with the given disposition type, e.g. `form-data` or `attachment`.
+    pub fn new<T: Into<String>>(disposition_type: T) -> Self {
+        ContentDisposition {
+            disposition_type: disposition_type.into(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Add the `name` parameter.
+    pub fn name<N: Into<String>>(self, name: N) -> Self {
+        self.param("name", name)
+    }
+
+    /// Add the `filename` parameter, and `filename*` if it contains non-ASCII characters.
+    pub fn filename<F: Into<String>>(self, filename: F) -> Self {
+        let filename = filename.into();
+
+        if filename.is_ascii() {
+            return self.param("filename", filename);
+        }
+
+        let fallback: String = filename
+            .chars()
+            .map(|c| if c.is_ascii() { c } else { '_' })
+            .collect();
+
+        let mut this = self.param("filename", fallback);
+        this.params.push(Param {
+            key: "filename*".into(),
+            value: filename,
+            extended: true,
+        });
+        this
+    }
+
+    /// Add an arbitrary parameter. Parameters are emitted in the order they were added.
+    pub fn param<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.params.push(Param {
+            key: key.into(),
+            value: value.into(),
+            extended: false,
+        });
+        self
+    }
+}
+
+impl fmt::Display for ContentDisposition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_escaped(f, &self.disposition_type, false)?;
+
+        for param in &self.params {
+            f.write_str("; ")?;
+            write_escaped(f, &param.key, false)?;
+
+            if param.extended {
+                f.write_str("=UTF-8''")?;
+                write_ext_value(f, &param.value)?;
+            } else {
+                f.write_str("=\"")?;
+                write_escaped(f, &param.value, true)?;
+                f.write_char('"')?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Percent-encode CR and LF like browsers do so they can't end the header line, and if
+/// `quoted`, escape `"` and `\` so they can't end the quoted string.
+fn write_escaped(f: &mut fmt::Formatter, s: &str, quoted: bool) -> fmt::Result {
+    for c in s.chars() {
+        match c {
+            '\r' => f.write_str("%0D")?,
+            '\n' => f.write_str("%0A")?,
+            '"' | '\\' if quoted => {
+                f.write_char('\\')?;
+                f.write_char(c)?;
+            }
+            c => f.write_char(c)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Percent-encode the UTF-8 bytes of `s` except for `attr-char`
+/// ([RFC 5987, Section 3.2.1](https://tools.ietf.org/html/rfc5987#section-3.2.1)).
+fn write_ext_value(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    for &b in s.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&b) {
+            f.write_char(b as char)?;
+        } else {
+            write!(f, "%{:02X}", b)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Return an error if a field name contains CR or LF.
+///
+/// These would be escaped in the header anyway, but the escaped name is unlikely to match
+/// what the server expects, so it's better to fail early.
+pub(crate) fn check_field_name(name: &str) -> io::Result<()> {
+    if name.contains(&['\r', '\n'][..]) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("field name contains CR or LF: {:?}", name),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Return an error if `data` contains `boundary` (without the leading `--`), which would end
+/// the field early when the request is parsed.
+pub(crate) fn check_no_collision(boundary: &str, data: &[u8]) -> io::Result<()> {
+    let boundary = format!("--{}", boundary);
+
+    if data.is_boundary_safe(boundary.as_bytes()) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("field data contains the boundary {:?}", boundary),
+        ))
+    }
+}
+
+/// If `name` is `None`, `Content-Disposition: file` is emitted instead of `form-data`,
+/// or no `Content-Disposition` at all if `filename` is also `None`.
+pub(crate) fn field_header(
+    boundary: &str,
+    name: Option<&str>,
+    filename: Option<&str>,
+    content_type: Option<&Mime>,
+    filename_first: bool,
+) -> String {
+    let disposition = field_disposition(name, filename, filename_first);
+    part_header(boundary, disposition.as_ref(), content_type, &[])
+}
+
+fn field_disposition(
+    name: Option<&str>,
+    filename: Option<&str>,
+    filename_first: bool,
+) -> Option<ContentDisposition> {
+    match (name, filename) {
+        (Some(name), Some(filename)) if filename_first => Some(
+            ContentDisposition::new("form-data")
+                .filename(filename)
+                .name(name),
+        ),
+        (Some(name), filename) => {
+            let disposition = ContentDisposition::new("form-data").name(name);
+            Some(match filename {
+                Some(filename) => disposition.filename(filename),
+                None => disposition,
+            })
+        }
+        (None, Some(filename)) => Some(ContentDisposition::new("file").filename(filename)),
+        (None, None) => None,
+    }
+}
+
+/// Enough for the signatures of the common types `infer` recognizes
+#[cfg(feature = "sniff")]
+const SNIFF_LEN: usize = 512;
+
+#[cfg(feature = "sniff")]
+fn sniff_content_type(peeked: &[u8], filename: Option<&str>) -> Mime {
+    infer::get(peeked)
+        .and_then(|kind| kind.mime_type().parse().ok())
+        .or_else(|| filename.and_then(|filename| mime_guess::from_path(filename).first()))
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+}
+
+/// Check the value of an extra part header, as a `HeaderValue` is not guaranteed to be free
+/// of CR and LF if it was created unchecked.
+fn extra_header_value<'a>(name: &HeaderName, value: &'a HeaderValue) -> io::Result<&'a str> {
+    let invalid = |msg| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("value of part header {:?} {}", name.as_str(), msg),
+        )
+    };
+
+    // `HeaderName` only allows token characters so can't contain either
+    if value.as_bytes().iter().any(|&b| b == b'\r' || b == b'\n') {
+        return Err(invalid("contains CR or LF"));
+    }
+
+    value.to_str().map_err(|_| invalid("is not visible ASCII"))
+}
+
+/// A compression algorithm for
+/// [`MultipartWriter::write_compressed_field()`](struct.MultipartWriter.html#method.write_compressed_field).
+/// Enabled with the `compression` feature.
+#[cfg(feature = "compression")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// `Content-Encoding: gzip`
+    Gzip,
+    /// `Content-Encoding: zstd`
+    Zstd,
+}
+
+#[cfg(feature = "compression")]
+impl Compression {
+    /// The value of the `Content-Encoding` header for this algorithm.
+    pub fn content_encoding(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// `extra` headers are emitted after `Content-Type` in the order given.
+fn part_header(
+    boundary: &str,
+    disposition: Option<&ContentDisposition>,
+    content_type: Option<&Mime>,
+    extra: &[(&str, &str)],
+) -> String {
+    let mut header = format!("--{}", boundary);
+
+    if let Some(disposition) = disposition {
+        write!(header, "\r\nContent-Disposition: {}", disposition).unwrap();
+    }
+
+    if let Some(content_type) = content_type {
+        write!(header, "\r\nContent-Type: {}", content_type).unwrap();
+    }
+
+    for (name, value) in extra {
+        write!(header, "\r\n{}: {}", name, value).unwrap();
+    }
+
+    header.push_str("\r\n\r\n");
+
+    header
+}
+
 impl<W: AsyncWrite + Unpin> MultipartWriter<W> {
     async fn write_field_header(
         &mut self,
-        name: &str,
+        name: Option<&str>,
         filename: Option<&str>,
         content_type: Option<&Mime>,
     ) -> io::Result<()> {
-        let mut header = Cursor::new(self.get_field_header(name, filename, content_type));
+        let mut header = Cursor::new(self.get_field_header(name, filename, content_type)?);
         io::copy(&mut header, &mut self.inner).await?;
         self.data_written = true;
         Ok(())
     }
 
+    /// Write a complete part: `header`, then `contents`, then the line ending before the next
+    /// boundary.
+    async fn write_part_contents<R: AsyncRead + Unpin>(
+        &mut self,
+        header: &str,
+        mut contents: R,
+    ) -> io::Result<&mut Self> {
+        self.inner.write_all(header.as_bytes()).await?;
+        self.data_written = true;
+
+        io::copy(&mut contents, &mut self.inner).await?;
+        self.inner.write_all(b"\r\n").await?;
+        Ok(self)
+    }
+
     /// Write a field of any type to the output. (Method for taking `AsyncRead`).
     ///
     /// If `content_type` is not set, the server assumes `Content-Type: text/plain`
@@ -99,21 +410,211 @@ impl<W: AsyncWrite + Unpin> MultipartWriter<W> {
     /// If you want the server to interpret a field as a file regardless of type or filename,
     /// pass a `content_type` of `mime::APPLICATION_OCTET_STREAM`.
     ///
+    /// Returns an error with a kind of `InvalidInput` before writing anything if `name` contains
+    /// CR or LF. CR and LF in `filename` are percent-encoded, and `"` and `\` escaped.
+    ///
     /// [7578-4.4]: https://tools.ietf.org/html/rfc7578#section-4.4
     pub async fn write_field<R: AsyncRead + Unpin>(
         &mut self,
         name: &str,
         filename: Option<&str>,
         content_type: Option<&Mime>,
+        contents: R,
+    ) -> io::Result<&mut Self> {
+        self.write_part(Some(name), filename, content_type, contents)
+            .await
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but takes the content type by value, so it
+    /// doesn't have to be kept alive by the caller, e.g. when it was parsed or guessed just to
+    /// write this field.
+    ///
+    /// `content_type` may be a `Mime`, `Some(Mime)` or `None`.
+    pub async fn write_typed_field<M, R>(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: M,
+        contents: R,
+    ) -> io::Result<&mut Self>
+    where
+        M: Into<Option<Mime>>,
+        R: AsyncRead + Unpin,
+    {
+        let content_type = content_type.into();
+        self.write_field(name, filename, content_type.as_ref(), contents)
+            .await
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but `name` may be omitted.
+    ///
+    /// This is intended for the sub-parts of a `multipart/mixed` body, which are identified
+    /// by `Content-Disposition: file; filename="..."` or carry no `Content-Disposition` at all
+    /// ([RFC 7578 Section 4.3][7578-4.3]). If `name` is provided, this is equivalent to
+    /// `.write_field()`.
+    ///
+    /// [7578-4.3]: https://tools.ietf.org/html/rfc7578#section-4.3
+    pub async fn write_part<R: AsyncRead + Unpin>(
+        &mut self,
+        name: Option<&str>,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        contents: R,
+    ) -> io::Result<&mut Self> {
+        let header = self.get_field_header(name, filename, content_type)?;
+        self.write_part_contents(&header, contents).await
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but also writes each header in `extra`
+    /// after `Content-Type`, e.g. `Content-Transfer-Encoding` or a custom `X-` header.
+    ///
+    /// Returns an error with a kind of `InvalidInput` before writing anything if a header name
+    /// or value contains CR or LF, which would allow injecting headers or ending the field
+    /// header early, or if a value isn't visible ASCII.
+    pub async fn write_field_with_headers<R: AsyncRead + Unpin>(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        extra: &HeaderMap,
+        contents: R,
+    ) -> io::Result<&mut Self> {
+        check_field_name(name)?;
+
+        let extra = extra
+            .iter()
+            .map(|(name, value)| Ok((name.as_str(), extra_header_value(name, value)?)))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let disposition = field_disposition(Some(name), filename, self.filename_first);
+        let header = part_header(&self.boundary, disposition.as_ref(), content_type, &extra);
+        self.write_part_contents(&header, contents).await
+    }
+
+    /// Like [`.write_part()`](#method.write_part) but with full control over the
+    /// `Content-Disposition` header.
+    pub async fn write_part_with_disposition<R: AsyncRead + Unpin>(
+        &mut self,
+        disposition: &ContentDisposition,
+        content_type: Option<&Mime>,
+        contents: R,
+    ) -> io::Result<&mut Self> {
+        let header = part_header(&self.boundary, Some(disposition), content_type, &[]);
+        self.write_part_contents(&header, contents).await
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but the content type is guessed from the
+    /// first bytes of `contents`, such as the signature of a PNG or PDF file.
+    ///
+    /// Up to 512 bytes are read before the field header is written, then written as the start of
+    /// the field. If the type isn't recognized it is guessed from the extension of `filename`,
+    /// and if that fails `application/octet-stream` is used.
+    /// Enabled with the `sniff` feature.
+    #[cfg(feature = "sniff")]
+    pub async fn write_field_sniffed<R: AsyncRead + Unpin>(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
         mut contents: R,
     ) -> io::Result<&mut Self> {
-        self.write_field_header(name, filename, content_type)
+        let mut peeked = Vec::with_capacity(SNIFF_LEN);
+        (&mut contents)
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut peeked)
             .await?;
-        io::copy(&mut contents, &mut self.inner).await?;
+
+        let content_type = sniff_content_type(&peeked, filename);
+
+        self.write_field(
+            name,
+            filename,
+            Some(&content_type),
+            (&peeked[..]).chain(contents),
+        )
+        .await
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but `contents` is compressed with `algorithm`
+    /// as it is written, and the part is sent with a matching `Content-Encoding` header.
+    /// Enabled with the `compression` feature.
+    ///
+    /// `content_type` should be the type of the uncompressed data; the server is expected to
+    /// decode the field according to `Content-Encoding`.
+    #[cfg(feature = "compression")]
+    pub async fn write_compressed_field<R: AsyncRead + Unpin>(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        contents: R,
+        algorithm: Compression,
+    ) -> io::Result<&mut Self> {
+        use async_compression::tokio_02::bufread::{GzipEncoder, ZstdEncoder};
+
+        check_field_name(name)?;
+
+        let disposition = field_disposition(Some(name), filename, self.filename_first);
+
+        let header = part_header(
+            &self.boundary,
+            disposition.as_ref(),
+            content_type,
+            &[("Content-Encoding", algorithm.content_encoding())],
+        );
+
+        let contents = io::BufReader::new(contents);
+
+        match algorithm {
+            Compression::Gzip => {
+                self.write_part_contents(&header, GzipEncoder::new(contents)).await
+            }
+            Compression::Zstd => {
+                self.write_part_contents(&header, ZstdEncoder::new(contents)).await
+            }
+        }
+    }
+
+    /// Like [`.write_field()`](#method.write_field) but takes an in-memory buffer, which is
+    /// written directly instead of being copied through `AsyncRead`.
+    ///
+    /// Returns an error with a kind of `InvalidInput` before writing anything if `data`
+    /// contains the boundary.
+    pub async fn write_bytes_field(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        data: &[u8],
+    ) -> io::Result<&mut Self> {
+        check_no_collision(&self.boundary, data)?;
+        self.write_field_header(Some(name), filename, content_type)
+            .await?;
+        self.inner.write_all(data).await?;
         self.inner.write_all(b"\r\n").await?;
         Ok(self)
     }
 
+    /// Like [`.write_field()`](#method.write_field) but the reader is created on demand by
+    /// calling `make`, for contents that are expensive to prepare.
+    ///
+    /// If `make` returns an error, it is returned before anything is written for this field.
+    pub async fn write_field_with<F, Fut, R>(
+        &mut self,
+        name: &str,
+        filename: Option<&str>,
+        content_type: Option<&Mime>,
+        make: F,
+    ) -> io::Result<&mut Self>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = io::Result<R>>,
+        R: AsyncRead + Unpin,
+    {
+        let contents = make().await?;
+        self.write_field(name, filename, content_type, contents)
+            .await
+    }
+
     /// Like [`.write_field()`](#method.write_field) but takes a `Stream`.
     /// See that method for details on these parameters.
     ///
@@ -132,6 +633,9 @@ impl<W: AsyncWrite + Unpin> MultipartWriter<W> {
     {
         let mut contents = contents.map_err(|e| io::Error::new(io::ErrorKind::Other, e));
 
+        self.write_field_header(Some(name), filename, content_type)
+            .await?;
+
         while let Some(buf) = contents.try_next().await? {
             self.inner.write_all(buf.as_ref()).await?;
         }
@@ -171,8 +675,12 @@ impl<W: AsyncWrite + Unpin> MultipartWriter<W> {
     /// If you want to pass a string but still set the filename and/or content type,
     /// convert it to bytes with `.as_bytes()` and pass it to [`.write_field()`](#method.write_field)
     /// instead, as byte slices implement `AsyncRead`.
+    ///
+    /// Returns an error with a kind of `InvalidInput` before writing anything if `text`
+    /// contains the boundary.
     pub async fn write_text(&mut self, name: &str, text: &str) -> io::Result<&mut Self> {
-        self.write_field(name, None, None, text.as_bytes()).await
+        self.write_bytes_field(name, None, None, text.as_bytes())
+            .await
     }
 
     /// Complete the `multipart/form-data` request.
@@ -194,16 +702,19 @@ impl<W: AsyncWrite + Unpin> MultipartWriter<W> {
 
         Ok(())
     }
+
+    /// Like [`.finish()`](#method.finish) but returns the wrapped writer afterwards,
+    /// e.g. to take the buffer when writing to a `Vec<u8>`.
+    pub async fn finish_into_inner(mut self) -> io::Result<W> {
+        self.finish().await?;
+        Ok(self.inner)
+    }
 }
 
 #[cfg(test)]
 #[tokio::test]
 async fn test_multipart_writer_one_text_field() -> io::Result<()> {
-    let mut writer = MultipartWriter {
-        inner: Vec::<u8>::new(),
-        boundary: "boundary".to_string(),
-        data_written: false,
-    };
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
 
     writer.write_text("hello", "world!").await?.finish().await?;
 
@@ -217,3 +728,456 @@ async fn test_multipart_writer_one_text_field() -> io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_finish_into_inner() -> io::Result<()> {
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    writer.write_text("hello", "world!").await?;
+
+    let buf: Vec<u8> = writer.finish_into_inner().await?;
+
+    assert_eq!(
+        buf,
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"hello\"\r\n\r\n\
+          world!\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_typed_field() -> io::Result<()> {
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    let csv: Mime = "text/csv; charset=utf-8".parse().unwrap();
+
+    writer
+        .write_typed_field("table", Some("table.csv"), csv, &b"a,b"[..])
+        .await?
+        .write_typed_field("plain", None, None, &b"text"[..])
+        .await?
+        .finish()
+        .await?;
+
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"table\"; filename=\"table.csv\"\r\n\
+          Content-Type: text/csv; charset=utf-8\r\n\r\n\
+          a,b\r\n\
+          --boundary\r\n\
+          Content-Disposition: form-data; name=\"plain\"\r\n\r\n\
+          text\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_part_with_disposition() -> io::Result<()> {
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    let disposition = ContentDisposition::new("attachment")
+        .name("upload")
+        .filename("say \"hi\".txt")
+        .param("size", "2");
+
+    writer
+        .write_part_with_disposition(&disposition, Some(&mime::TEXT_PLAIN), &b"hi"[..])
+        .await?
+        .finish()
+        .await?;
+
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: attachment; name=\"upload\"; filename=\"say \\\"hi\\\".txt\"; \
+          size=\"2\"\r\n\
+          Content-Type: text/plain\r\n\r\n\
+          hi\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_nameless_part() -> io::Result<()> {
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    writer
+        .write_part(
+            None,
+            Some("file1.txt"),
+            Some(&mime::TEXT_PLAIN),
+            &b"contents"[..],
+        )
+        .await?
+        .finish()
+        .await?;
+
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: file; filename=\"file1.txt\"\r\n\
+          Content-Type: text/plain\r\n\r\n\
+          contents\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_field_with() -> io::Result<()> {
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    let made = std::cell::Cell::new(false);
+
+    let make = || {
+        made.set(true);
+        async { Ok(&b"lazy contents"[..]) }
+    };
+
+    // `make` is not invoked until the field is actually written
+    let write = writer.write_field_with("lazy", None, None, make);
+    assert!(!made.get());
+    write.await?.finish().await?;
+
+    assert!(made.get());
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"lazy\"\r\n\r\n\
+          lazy contents\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "sniff"))]
+#[tokio::test]
+async fn test_multipart_writer_field_sniffed() -> io::Result<()> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0DIHDR";
+
+    // longer than what is peeked, to check the rest follows on
+    let mut png = PNG_SIGNATURE.to_vec();
+    png.extend((0..1000).map(|i| i as u8));
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    writer
+        .write_field_sniffed("image", Some("image"), &png[..])
+        .await?
+        .write_field_sniffed("notes", Some("notes.txt"), &b"plain text"[..])
+        .await?
+        .write_field_sniffed("unknown", None, &b"\x00\x01"[..])
+        .await?
+        .finish()
+        .await?;
+
+    let expected = [
+        &b"--boundary\r\n\
+           Content-Disposition: form-data; name=\"image\"; filename=\"image\"\r\n\
+           Content-Type: image/png\r\n\r\n"[..],
+        &png,
+        b"\r\n--boundary\r\n\
+          Content-Disposition: form-data; name=\"notes\"; filename=\"notes.txt\"\r\n\
+          Content-Type: text/plain\r\n\r\n\
+          plain text\r\n\
+          --boundary\r\n\
+          Content-Disposition: form-data; name=\"unknown\"\r\n\
+          Content-Type: application/octet-stream\r\n\r\n\
+          \x00\x01\r\n\
+          --boundary--\r\n",
+    ]
+    .concat();
+
+    assert_eq!(writer.inner, expected);
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_bytes_field() -> io::Result<()> {
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    let data: Vec<u8> = vec![0x00, 0xFF, b'\r', b'\n', 0x7F];
+
+    writer
+        .write_bytes_field(
+            "binary",
+            Some("data.bin"),
+            Some(&mime::APPLICATION_OCTET_STREAM),
+            &data,
+        )
+        .await?
+        .finish()
+        .await?;
+
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"binary\"; filename=\"data.bin\"\r\n\
+          Content-Type: application/octet-stream\r\n\r\n\
+          \x00\xFF\r\n\x7F\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "compression"))]
+#[tokio::test]
+async fn test_multipart_writer_compressed_field() -> io::Result<()> {
+    use async_compression::tokio_02::bufread::GzipDecoder;
+
+    let contents = b"compressible ".repeat(100);
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    writer
+        .write_compressed_field(
+            "log",
+            Some("log.txt"),
+            Some(&mime::TEXT_PLAIN),
+            &contents[..],
+            Compression::Gzip,
+        )
+        .await?
+        .finish()
+        .await?;
+
+    let header: &[u8] = b"--boundary\r\n\
+        Content-Disposition: form-data; name=\"log\"; filename=\"log.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        Content-Encoding: gzip\r\n\r\n";
+    let trailer: &[u8] = b"\r\n--boundary--\r\n";
+
+    let body = writer.into_inner();
+    assert!(body.starts_with(header));
+    assert!(body.ends_with(trailer));
+
+    let compressed = &body[header.len()..body.len() - trailer.len()];
+    assert!(compressed.len() < contents.len());
+
+    let mut decompressed = Vec::new();
+    GzipDecoder::new(compressed)
+        .read_to_end(&mut decompressed)
+        .await?;
+    assert_eq!(decompressed, contents);
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_field_with_headers() -> io::Result<()> {
+    let mut extra = HeaderMap::new();
+    extra.insert(
+        "content-transfer-encoding",
+        HeaderValue::from_static("8bit"),
+    );
+    extra.insert("x-checksum", HeaderValue::from_static("abc123"));
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    writer
+        .write_field_with_headers(
+            "file",
+            Some("file.txt"),
+            Some(&mime::TEXT_PLAIN),
+            &extra,
+            &b"contents"[..],
+        )
+        .await?
+        .finish()
+        .await?;
+
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n\
+          Content-Type: text/plain\r\n\
+          content-transfer-encoding: 8bit\r\n\
+          x-checksum: abc123\r\n\r\n\
+          contents\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    // `HeaderValue` itself rejects CR and LF unless created unchecked
+    assert!(HeaderValue::from_str("1\r\nContent-Type: text/html").is_err());
+
+    let mut extra = HeaderMap::new();
+    extra.insert("x-latin1", HeaderValue::from_bytes(b"caf\xE9").unwrap());
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    let err = writer
+        .write_field_with_headers("file", None, None, &extra, &b"contents"[..])
+        .await
+        .err()
+        .expect("non-ASCII header value not rejected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(writer.get_ref().is_empty());
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_header_injection() -> io::Result<()> {
+    const EVIL: &str = "x\"\r\nEvil: header";
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    let err = writer
+        .write_field(EVIL, None, None, &b"contents"[..])
+        .await
+        .err()
+        .expect("CRLF in field name not rejected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = writer
+        .write_text(EVIL, "text")
+        .await
+        .err()
+        .expect("CRLF in field name not rejected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    assert!(writer.get_ref().is_empty());
+
+    writer
+        .write_field("file", Some(EVIL), None, &b"contents"[..])
+        .await?
+        .finish()
+        .await?;
+
+    assert_eq!(
+        writer.inner,
+        &b"--boundary\r\n\
+          Content-Disposition: form-data; name=\"file\"; filename=\"x\\\"%0D%0AEvil: header\"\r\n\r\n\
+          contents\r\n\
+          --boundary--\r\n"[..]
+    );
+
+    let disposition = ContentDisposition::new("attachment\r\nEvil: header").name(EVIL);
+    assert_eq!(
+        disposition.to_string(),
+        "attachment%0D%0AEvil: header; name=\"x\\\"%0D%0AEvil: header\""
+    );
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "server"))]
+#[tokio::test]
+async fn test_multipart_writer_non_ascii_filename_round_trip() -> io::Result<()> {
+    use crate::server::parse_all;
+
+    const FILENAME: &str = "r\u{E9}sum\u{E9} final.pdf";
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+    writer
+        .write_field("file", Some(FILENAME), None, &b"contents"[..])
+        .await?
+        .write_field("plain", Some("plain.txt"), None, &b"text"[..])
+        .await?
+        .finish()
+        .await?;
+
+    let expected_disp: &[u8] = b"Content-Disposition: form-data; name=\"file\"; \
+        filename=\"r_sum_ final.pdf\"; \
+        filename*=UTF-8''r%C3%A9sum%C3%A9%20final.pdf\r\n";
+
+    assert!(writer
+        .inner
+        .windows(expected_disp.len())
+        .any(|window| window == expected_disp));
+
+    let fields = parse_all(&writer.inner, "boundary").unwrap();
+    assert_eq!(fields.len(), 2);
+    assert_eq!(fields[0].headers.filename.as_deref(), Some(FILENAME));
+    assert_eq!(fields[1].headers.filename.as_deref(), Some("plain.txt"));
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_multipart_writer_boundary_collision() -> io::Result<()> {
+    let data = b"some data\r\n--boundary\r\nmore data";
+    assert!(!(&data[..]).is_boundary_safe(b"--boundary"));
+    assert!((&data[..]).is_boundary_safe(b"--other-boundary"));
+
+    let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string());
+
+    let err = writer
+        .write_bytes_field("data", None, None, data)
+        .await
+        .err()
+        .expect("boundary collision not detected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let err = writer
+        .write_text("text", "--boundary--")
+        .await
+        .err()
+        .expect("boundary collision not detected");
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    // nothing was written for the rejected fields
+    assert!(writer.get_ref().is_empty());
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "server"))]
+#[tokio::test]
+async fn test_multipart_writer_filename_order_round_trip() -> io::Result<()> {
+    use crate::server::Multipart;
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    for &filename_first in &[false, true] {
+        let mut writer = MultipartWriter::new(Vec::<u8>::new(), "boundary".to_string())
+            .filename_before_name(filename_first);
+
+        writer
+            .write_field("file", Some("file.txt"), None, &b"contents"[..])
+            .await?
+            .finish()
+            .await?;
+
+        let expected_disp: &[u8] = if filename_first {
+            b"Content-Disposition: form-data; filename=\"file.txt\"; name=\"file\"\r\n"
+        } else {
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"file.txt\"\r\n"
+        };
+
+        assert!(writer
+            .inner
+            .windows(expected_disp.len())
+            .any(|window| window == expected_disp));
+
+        let body = writer.into_inner();
+        let chunks = [&body[..]];
+        let mut multipart = Multipart::with_body(mock_stream(&chunks), "boundary");
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "file");
+        assert_eq!(field.headers.filename.as_deref(), Some("file.txt"));
+        assert_eq!(
+            run_future_hot(field.data.read_to_string()).unwrap(),
+            "contents"
+        );
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+    }
+
+    Ok(())
+}
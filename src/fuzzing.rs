@@ -12,6 +12,7 @@ use futures_test::stream::StreamTestExt;
 use futures_test::task::noop_context;
 use futures_util::future::FutureExt;
 use futures_util::stream::{self, StreamExt};
+use memchr::memmem;
 
 use std::task::Poll::*;
 
@@ -70,8 +71,8 @@ pub fn fuzz_boundary_finder(fuzz_data: &[u8]) {
         loop {
             match finder.as_mut().body_chunk(cx) {
                 Ready(Some(Ok(chunk))) => {
-                    assert_ne!(chunk, &[]);
-                    assert_eq!(twoway::find_bytes(chunk, BOUNDARY.as_bytes()), None)
+                    assert!(!chunk.is_empty());
+                    assert_eq!(memmem::find(chunk, BOUNDARY.as_bytes()), None)
                 }
                 Pending => (),
                 Ready(None) | Ready(Some(Err(_))) => return,
@@ -83,7 +84,7 @@ pub fn fuzz_boundary_finder(fuzz_data: &[u8]) {
 /// Fuzz BoundaryFinder taking the input as the data of a field
 pub fn fuzz_boundary_finder_field(fuzz_data: &[u8]) {
     // ensure the boundary doesn't appear in the input data
-    if twoway::find_bytes(fuzz_data, BOUNDARY.as_bytes()).is_some() {
+    if memmem::find(fuzz_data, BOUNDARY.as_bytes()).is_some() {
         return;
     }
 
@@ -113,7 +114,7 @@ pub fn fuzz_boundary_finder_field(fuzz_data: &[u8]) {
     loop {
         match finder.as_mut().body_chunk(cx) {
             Ready(Some(Ok(chunk))) => {
-                assert_ne!(chunk, &[]);
+                assert!(!chunk.is_empty());
                 assert!(
                     remaining.starts_with(chunk),
                     "expected chunk \"{}\" to be a prefix of remaining data \"{}\"",
@@ -124,7 +125,11 @@ pub fn fuzz_boundary_finder_field(fuzz_data: &[u8]) {
             }
             Ready(Some(Err(_))) => return,
             Ready(None) => {
-                assert_eq!(remaining, &[]);
+                assert!(
+                    remaining.is_empty(),
+                    "unread data \"{}\"",
+                    show_bytes(remaining)
+                );
                 break;
             }
             Pending => (),
@@ -142,7 +147,7 @@ pub fn fuzz_boundary_finder_field(fuzz_data: &[u8]) {
 }
 
 pub fn fuzz_read_headers(fuzz_data: &[u8]) {
-    if twoway::find_bytes(fuzz_data, BOUNDARY.as_bytes()).is_some() {
+    if memmem::find(fuzz_data, BOUNDARY.as_bytes()).is_some() {
         return;
     }
 
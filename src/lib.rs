@@ -12,8 +12,33 @@
 //! * `client` (default): Enable the client-side abstractions for multipart requests. If the
 //! `hyper` feature is also set, enables integration with the Hyper HTTP client API.
 //!
-//! * `server` (default): Enable the server-side abstractions for multipart requests. If the
-//! `hyper` feature is also set, enables integration with the Hyper HTTP server API.
+//! * `server` (default): Enable the server-side abstractions for multipart requests.
+//!
+//! * `hyper_014`: Enable integration with the Hyper 0.14 HTTP server API.
+//!
+//! * `charset`: Enable `FieldData::read_to_string_with_charset()` for non-UTF-8 text fields.
+//!
+//! * `sniff`: Enable `MultipartWriter::write_field_sniffed()` to guess content types from data.
+//!
+//! * `compression`: Enable `MultipartWriter::write_compressed_field()`.
+//!
+//! * `httpdate`: Parse the `creation-date` and `modification-date` parameters into `FieldHeaders`.
+//!
+//! * `spill`: Enable `Field::spill_to_temp()` for buffering large fields to a temporary file.
+//!
+//! * `concurrency`: Enable `server::ConcurrencyLimiter` for bounding requests read at once.
+//!
+//! * `save`: Enable `Multipart::save_files_concurrently()`.
+//!
+//! * `drain`: Enable `Multipart::drain_on_drop()` to read the rest of a body on a spawned task.
+//!
+//! * `tide`: Enable integration with the Tide HTTP server API.
+//!
+//! * `axum`: Enable `server::axum::Multipart` as an extractor for Axum handlers.
+//!
+//! * `actix`: Enable `server::actix::Multipart` as an extractor for actix-web handlers.
+//!
+//! * `test-util`: Expose `test_util` for replaying captured requests (not part of the public API).
 #![allow(unused_imports, deprecated)]
 // FIXME: hiding irrelevant warnings during prototyping
 // #![deny(missing_docs)]
@@ -36,7 +61,7 @@ pub extern crate http;
 #[macro_use]
 extern crate lazy_static;
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures_core::{Future, Stream};
 use std::borrow::Cow;
 use std::process::Output;
@@ -46,7 +71,7 @@ use std::{fmt, io, ops};
 
 mod helpers;
 
-#[cfg(any(test, feature = "fuzzing"))]
+#[cfg(any(test, feature = "fuzzing", feature = "test-util"))]
 #[macro_use]
 pub mod test_util;
 
@@ -71,6 +96,20 @@ pub trait BodyChunk: Sized {
     /// Split the chunk at `idx`, returning `(self[..idx], self[idx..])`.
     fn split_into(self, idx: usize) -> (Self, Self);
 
+    /// Split the chunk at `idx` like `split_into()`, but return `None` if `idx > self.len()`
+    /// instead of panicking.
+    ///
+    /// The default implementation checks the length, then calls `split_into()`; chunk types
+    /// which need special handling to split, such as some ref-counted buffers, may override it.
+    #[inline]
+    fn split_at_checked(self, idx: usize) -> Option<(Self, Self)> {
+        if idx <= self.len() {
+            Some(self.split_into(idx))
+        } else {
+            None
+        }
+    }
+
     /// Get the slice representing the data of this chunk.
     fn as_slice(&self) -> &[u8];
 
@@ -95,6 +134,23 @@ pub trait BodyChunk: Sized {
         self.as_slice().is_empty()
     }
 
+    /// Equivalent to `self.as_slice().starts_with(prefix)`
+    ///
+    /// Implementors are welcome to override this if they can answer without getting the slice.
+    #[inline(always)]
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        self.as_slice().starts_with(prefix)
+    }
+
+    /// Returns `true` if `boundary` does not appear anywhere in this chunk.
+    ///
+    /// This cannot detect a boundary split between this chunk and the next.
+    #[cfg(any(feature = "client", feature = "server"))]
+    #[inline]
+    fn is_boundary_safe(&self, boundary: &[u8]) -> bool {
+        memchr::memmem::find(self.as_slice(), boundary).is_none()
+    }
+
     /// Equivalent to `self.as_slice().to_owned()`
     ///
     /// Implementors are welcome to override this if they can provide a cheaper conversion.
@@ -162,3 +218,177 @@ impl BodyChunk for Bytes {
         self.as_ref()
     }
 }
+
+impl BodyChunk for Box<[u8]> {
+    fn split_into(self, idx: usize) -> (Self, Self) {
+        let (left, right) = self.into_vec().split_into(idx);
+        (left.into_boxed_slice(), right.into_boxed_slice())
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self
+    }
+
+    fn into_vec(self) -> Vec<u8> {
+        // the inherent method, which reuses the allocation
+        <[u8]>::into_vec(self)
+    }
+}
+
+impl BodyChunk for BytesMut {
+    fn split_into(mut self, idx: usize) -> (Self, Self) {
+        let left = self.split_to(idx);
+        (left, self)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self.as_ref()
+    }
+
+    // `bytes` 0.5 has no conversion to `Vec<u8>` which reuses the allocation,
+    // so `into_vec()` keeps the default copy
+}
+
+/// `Bytes` from `bytes` 1.x, as used by web frameworks built on Tokio 1.
+#[cfg(feature = "bytes_1")]
+impl BodyChunk for bytes_1::Bytes {
+    fn split_into(mut self, idx: usize) -> (Self, Self) {
+        let right = self.split_off(idx);
+        (self, right)
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        self.as_ref()
+    }
+
+    fn into_vec(self) -> Vec<u8> {
+        // reuses the allocation if this is the only handle to it
+        self.into()
+    }
+}
+
+#[test]
+fn test_bytes_mut_chunk() {
+    let chunk = BytesMut::from(&b"field data"[..]);
+
+    let (left, right) = chunk.split_into(5);
+    assert_eq!(left.as_slice(), b"field");
+    assert_eq!(right.as_slice(), b" data");
+
+    let (empty, all) = right.split_into(0);
+    assert!(empty.is_empty());
+    assert_eq!(all.into_vec(), b" data");
+}
+
+#[test]
+fn test_vec_and_boxed_slice_chunks() {
+    fn check<C: BodyChunk + From<&'static [u8]>>() {
+        let (left, right) = C::from(b"field data").split_into(5);
+        assert_eq!(left.as_slice(), b"field");
+        assert_eq!(right.as_slice(), b" data");
+
+        let (empty, all) = C::from(b"field data").split_into(0);
+        assert!(empty.is_empty());
+        assert_eq!(all.into_vec(), b"field data");
+
+        let (all, empty) = C::from(b"field data").split_into(10);
+        assert_eq!(all.len(), 10);
+        assert!(empty.is_empty());
+        assert_eq!(empty.into_vec(), b"");
+    }
+
+    check::<Vec<u8>>();
+    check::<Box<[u8]>>();
+}
+
+#[test]
+fn test_split_at_checked() {
+    let chunk: &[u8] = b"field data";
+
+    // `[u8]` has an inherent method of the same name
+    let (left, right) = BodyChunk::split_at_checked(chunk, 5).unwrap();
+    assert_eq!(left, b"field");
+    assert_eq!(right, b" data");
+
+    assert_eq!(
+        BodyChunk::split_at_checked(chunk, 10),
+        Some((chunk, &b""[..]))
+    );
+    assert_eq!(BodyChunk::split_at_checked(chunk, 11), None);
+    assert_eq!(Vec::from(chunk).split_at_checked(11), None);
+}
+
+#[test]
+fn test_starts_with() {
+    let chunk = Vec::from(&b"--boundary\r\n"[..]);
+    assert!(BodyChunk::starts_with(&chunk, b"--boundary"));
+    assert!(BodyChunk::starts_with(&chunk, b""));
+    assert!(!BodyChunk::starts_with(&chunk, b"\r\n--boundary"));
+    assert!(!BodyChunk::starts_with(&chunk, b"--boundary\r\n--"));
+}
+
+#[test]
+fn test_starts_with_override() {
+    use std::cell::Cell;
+
+    /// A chunk which keeps a copy of the first bytes of its data inline
+    struct Inline {
+        head: [u8; 2],
+        data: Vec<u8>,
+        slices: Cell<usize>,
+    }
+
+    impl Inline {
+        fn new(data: Vec<u8>) -> Self {
+            let mut head = [0; 2];
+            let len = data.len().min(head.len());
+            head[..len].copy_from_slice(&data[..len]);
+
+            Inline {
+                head,
+                data,
+                slices: Cell::new(0),
+            }
+        }
+    }
+
+    impl BodyChunk for Inline {
+        fn split_into(mut self, idx: usize) -> (Self, Self) {
+            let rem = self.data.split_off(idx);
+            (Inline::new(self.data), Inline::new(rem))
+        }
+
+        fn as_slice(&self) -> &[u8] {
+            self.slices.set(self.slices.get() + 1);
+            &self.data
+        }
+
+        fn starts_with(&self, prefix: &[u8]) -> bool {
+            if prefix.len() <= self.head.len() && self.data.len() >= self.head.len() {
+                self.head.starts_with(prefix)
+            } else {
+                self.as_slice().starts_with(prefix)
+            }
+        }
+    }
+
+    let chunk = Inline::new(b"--boundary".to_vec());
+
+    assert!(chunk.starts_with(b"--"));
+    assert!(!chunk.starts_with(b"\r\n"));
+    assert_eq!(chunk.slices.get(), 0);
+
+    assert!(chunk.starts_with(b"--boundary"));
+    assert_eq!(chunk.slices.get(), 1);
+
+    let (dashes, rest) = chunk.split_into(2);
+    assert!(dashes.starts_with(b"--"));
+    assert!(rest.starts_with(b"bo"));
+    assert!(!rest.starts_with(b"--"));
+    assert_eq!(dashes.slices.get() + rest.slices.get(), 0);
+
+    // too short to fill the inline copy
+    let (empty, _) = rest.split_into(0);
+    assert!(empty.starts_with(b""));
+    assert!(!empty.starts_with(b"\0"));
+}
@@ -0,0 +1,103 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! Server-side integration with [actix-web](https://github.com/actix/actix-web).
+//! Enabled with the `actix` feature.
+use std::future::{ready, Ready};
+use std::ops::{Deref, DerefMut};
+
+use actix_web::dev::Payload;
+use actix_web::error::{ErrorBadRequest, PayloadError};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::{FromRequest, HttpRequest, ResponseError};
+use mime::Mime;
+
+use super::{boundary_param, Error, NoBoundary};
+
+/// An extractor which wraps the payload of a `multipart/form-data` request in a
+/// [`server::Multipart`](../struct.Multipart.html).
+///
+/// Requests which are not `multipart/form-data`, or have no `boundary` parameter, are
+/// rejected with `400 Bad Request`. The method of the request is not checked.
+///
+/// ```rust,no_run
+/// use actix_web::error::PayloadError;
+/// use multipart_async::server::{actix::Multipart, Error};
+///
+/// async fn upload(mut multipart: Multipart) -> Result<String, Error<PayloadError>> {
+///     let mut names = Vec::new();
+///
+///     while let Some(field) = multipart.next_field().await? {
+///         names.push(field.headers.name);
+///     }
+///
+///     Ok(names.join(", "))
+/// }
+///
+/// let app = actix_web::App::new().route("/upload", actix_web::web::post().to(upload));
+/// ```
+pub struct Multipart(pub super::Multipart<Payload>);
+
+impl Multipart {
+    /// Take the payload of a request, as for a handler which doesn't use the extractor.
+    pub fn from_parts(req: &HttpRequest, payload: &mut Payload) -> actix_web::Result<Self> {
+        let content_type = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Mime>().ok())
+            .filter(|mime| mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA)
+            .ok_or_else(|| ErrorBadRequest("expected a multipart/form-data request"))?;
+
+        let boundary = boundary_param(&content_type).ok_or_else(|| ErrorBadRequest(NoBoundary))?;
+
+        Ok(Multipart(super::Multipart::with_body(
+            payload.take(),
+            boundary,
+        )))
+    }
+
+    /// Unwrap the `server::Multipart`.
+    pub fn into_inner(self) -> super::Multipart<Payload> {
+        self.0
+    }
+}
+
+impl Deref for Multipart {
+    type Target = super::Multipart<Payload>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Multipart {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl FromRequest for Multipart {
+    type Error = actix_web::Error;
+    type Future = Ready<actix_web::Result<Self>>;
+
+    fn from_request(req: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        ready(Self::from_parts(req, payload))
+    }
+}
+
+/// Client errors, such as a malformed request or an exceeded limit, become `400 Bad Request`;
+/// errors reading the payload become `500 Internal Server Error`.
+impl ResponseError for Error<PayloadError> {
+    fn status_code(&self) -> StatusCode {
+        if self.is_client_error() {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! Server-side integration with [Axum](https://github.com/tokio-rs/axum).
+//! Enabled with the `axum` feature.
+use std::ops::{Deref, DerefMut};
+
+use axum::async_trait;
+use axum::body::{Bytes, HttpBody};
+use axum::extract::{BodyStream, FromRequest};
+use axum::response::{IntoResponse, Response};
+use axum::BoxError;
+use http::{header, Request, StatusCode};
+use mime::Mime;
+
+use super::{boundary_param, Error, NoBoundary};
+
+/// An extractor which wraps the body of a `multipart/form-data` request in a
+/// [`server::Multipart`](../struct.Multipart.html).
+///
+/// Requests which are not `multipart/form-data`, or have no `boundary` parameter, are
+/// rejected with `400 Bad Request`. The method of the request is not checked.
+///
+/// ```rust,no_run
+/// use multipart_async::server::{axum::Multipart, Error};
+///
+/// async fn upload(mut multipart: Multipart) -> Result<String, Error<axum::Error>> {
+///     let mut names = Vec::new();
+///
+///     while let Some(field) = multipart.next_field().await? {
+///         names.push(field.headers.name);
+///     }
+///
+///     Ok(names.join(", "))
+/// }
+///
+/// let app: axum::Router = axum::Router::new().route("/upload", axum::routing::post(upload));
+/// ```
+pub struct Multipart(pub super::Multipart<BodyStream>);
+
+impl Multipart {
+    /// Unwrap the `server::Multipart`.
+    pub fn into_inner(self) -> super::Multipart<BodyStream> {
+        self.0
+    }
+}
+
+impl Deref for Multipart {
+    type Target = super::Multipart<BodyStream>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Multipart {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for Multipart
+where
+    B: HttpBody + Send + 'static,
+    B::Data: Into<Bytes>,
+    B::Error: Into<BoxError>,
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, String);
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<Mime>().ok())
+            .filter(|mime| mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA)
+            .ok_or_else(|| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    "expected a multipart/form-data request".to_string(),
+                )
+            })?;
+
+        let boundary = boundary_param(&content_type)
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, NoBoundary.to_string()))?;
+
+        let body = match BodyStream::from_request(req, state).await {
+            Ok(body) => body,
+            Err(never) => match never {},
+        };
+
+        Ok(Multipart(super::Multipart::with_body(body, boundary)))
+    }
+}
+
+/// Client errors, such as a malformed request or an exceeded limit, become `400 Bad Request`;
+/// errors reading the body become `500 Internal Server Error`.
+impl IntoResponse for Error<axum::Error> {
+    fn into_response(self) -> Response {
+        let status = if self.is_client_error() {
+            StatusCode::BAD_REQUEST
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+
+        (status, self.to_string()).into_response()
+    }
+}
@@ -4,14 +4,13 @@
 // http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
-extern crate twoway;
-
-use futures_core::{Stream};
+use futures_core::Stream;
+use memchr::memmem::Finder;
 
+use std::collections::VecDeque;
 use std::{fmt, mem};
 
-
-use super::PushChunk;
+use super::{LineEnding, MapChunks, PushChunk};
 use crate::BodyChunk;
 
 use self::State::*;
@@ -30,14 +29,31 @@ pub struct BoundaryFinder<S: TryStream> {
     stream: S,
     state: State<S::Ok>,
     boundary: Box<[u8]>,
+    /// Searcher for `boundary`, built once as every chunk is searched
+    finder: Finder<'static>,
+    /// Chunks to be examined again before polling `stream`
+    pending: VecDeque<S::Ok>,
+    /// Total bytes received from `stream`
+    bytes_read: u64,
+    /// No boundary has been confirmed yet
+    in_preamble: bool,
+    /// The line ending after the first boundary
+    line_ending: Option<LineEnding>,
 }
 
 impl<S: TryStream> BoundaryFinder<S> {
     pub fn new<B: Into<Vec<u8>>>(stream: S, boundary: B) -> Self {
+        let boundary = boundary.into().into_boxed_slice();
+
         BoundaryFinder {
             stream,
             state: State::Watching,
-            boundary: boundary.into().into_boxed_slice(),
+            finder: Finder::new(&boundary).into_owned(),
+            boundary,
+            pending: VecDeque::new(),
+            bytes_read: 0,
+            in_preamble: true,
+            line_ending: None,
         }
     }
 }
@@ -56,6 +72,25 @@ where
 {
     unsafe_pinned!(stream: S);
     unsafe_unpinned!(state: State<S::Ok>);
+    unsafe_unpinned!(pending: VecDeque<S::Ok>);
+    unsafe_unpinned!(bytes_read: u64);
+    unsafe_unpinned!(in_preamble: bool);
+    unsafe_unpinned!(line_ending: Option<LineEnding>);
+
+    /// Take the next chunk to examine, from `pending` first and then from `stream`.
+    fn poll_chunk(mut self: Pin<&mut Self>, cx: &mut Context) -> PollOpt<S::Ok, S::Error> {
+        if let Some(chunk) = self.as_mut().pending().pop_front() {
+            return Ready(Some(Ok(chunk)));
+        }
+
+        let res = ready!(self.as_mut().stream().try_poll_next(cx));
+
+        if let Some(Ok(ref chunk)) = res {
+            *self.bytes_read() += chunk.len() as u64;
+        }
+
+        Ready(res)
+    }
 
     pub fn body_chunk(
         mut self: Pin<&mut Self>,
@@ -96,13 +131,13 @@ where
             trace!("body_chunk() loop state: {:?}", self.state,);
 
             match self.state {
-                Found(_) | Split(_, _) | End => return Ready(None),
+                Found(_) | Split(_) | End => return Ready(None),
                 _ => (),
             }
 
             match mem::replace(self.as_mut().state(), Watching) {
                 Watching => {
-                    let chunk = try_ready_opt!(self.as_mut().stream().try_poll_next(cx));
+                    let chunk = try_ready_opt!(self.as_mut().poll_chunk(cx));
 
                     // For sanity
                     if chunk.is_empty() {
@@ -118,80 +153,91 @@ where
                         return ready_ok(chunk);
                     }
                 }
-                Partial(partial, res) => {
-                    let chunk = match self.as_mut().stream().try_poll_next(cx)? {
+                Partial(partial, mut follow, res) => {
+                    let chunk = match self.as_mut().poll_chunk(cx)? {
                         Ready(Some(chunk)) => chunk,
                         Ready(None) => {
                             set_state!(self = End);
-                            return Ready(fmt_err!(
-                                "unable to verify multipart boundary; expected: \"{}\" found: \"{}\"",
+
+                            let found: Vec<u8> = follow.iter().fold(
+                                partial.as_slice()[res.idx..].to_vec(),
+                                |mut found, chunk| {
+                                    found.extend_from_slice(chunk.as_slice());
+                                    found
+                                },
+                            );
+
+                            return Ready(eof_err!(
+                                "unable to verify multipart boundary; expected: \"{}\" found: \"{}\" \
+                                 (at byte offset {})",
                                 show_bytes(&self.boundary),
-                                show_bytes(partial.as_slice())
+                                show_bytes(&found),
+                                self.offset(found.len())
                             ).into());
                         }
                         Pending => {
-                            set_state!(self = Partial(partial, res));
+                            set_state!(self = Partial(partial, follow, res));
                             return Pending;
                         }
                     };
 
-                    trace!("Partial got second chunk: {}", show_bytes(chunk.as_slice()));
+                    trace!("Partial got next chunk: {}", show_bytes(chunk.as_slice()));
 
-                    if !self.is_boundary_prefix(partial.as_slice(), chunk.as_slice(), res) {
-                        trace!("partial + chunk don't make a boundary prefix");
-                        set_state!(self = Remainder(chunk));
-                        return ready_ok(partial);
+                    if !chunk.is_empty() {
+                        follow.push(chunk);
                     }
 
-                    let needed_len =
-                        (self.boundary_size(res.incl_crlf)).saturating_sub(partial.len());
-
-                    if needed_len > chunk.len() {
-                        // hopefully rare; must be dealing with a poorly behaved stream impl
-                        return Ready(
-                            fmt_err!(
-                                "needed {} more bytes to verify boundary, got {}",
-                                needed_len,
-                                chunk.len()
-                            )
-                            .into(),
-                        );
-                    }
+                    if !self.is_boundary_prefix(&partial.as_slice()[res.idx..], &follow, res) {
+                        trace!("partial + following chunks don't make a boundary prefix");
+                        // a boundary may still start later in `partial` or in the following chunks
+                        let (ret, rem) = partial.split_into(res.idx + 1);
+                        let pending = self.as_mut().pending();
+
+                        for chunk in follow.into_iter().rev() {
+                            pending.push_front(chunk);
+                        }
+
+                        if !rem.is_empty() {
+                            pending.push_front(rem);
+                        }
 
-                    let bnd_start = res.boundary_start();
+                        return ready_ok(ret);
+                    }
 
-                    let is_boundary = (bnd_start > partial.len()
-                        // `partial` ended with a `<CR>` and `chunk` starts with `<LF>--<boundary>`
-                        && self.check_boundary(&chunk.as_slice()[bnd_start - partial.len()..]))
-                        || self.check_boundary_split(
-                            &partial.as_slice()[bnd_start..],
-                            chunk.as_slice(),
-                        );
+                    let have = follow
+                        .iter()
+                        .fold(partial.len() - res.idx, |have, chunk| have + chunk.len());
 
-                    if !is_boundary {
-                        trace!("partial + chunk don't make a whole boundary");
-                        *self.as_mut().state() = Remainder(chunk);
-                        return ready_ok(partial);
+                    if have < self.boundary_size(res.incl_crlf) {
+                        // the boundary may span any number of chunks
+                        trace!("need more bytes to verify boundary; have: {}", have);
+                        set_state!(self = Partial(partial, follow, res));
+                        continue;
                     }
 
-                    let ret = if res.incl_crlf {
-                        if partial.len() < bnd_start {
-                            // `partial` ended with a `<CR>` and `chunk` starts with `<LF>--<boundary>`
-                            *self.as_mut().state() =
-                                Found(chunk.split_into(bnd_start - partial.len()).1);
-                            partial.split_into(res.idx).0
+                    let (ret, candidate) = partial.split_into(res.idx);
+
+                    // cut off the preceding CRLF, which may itself be split across chunks
+                    let mut skip = res.boundary_start() - res.idx;
+                    let mut bnd_chunks = Vec::with_capacity(follow.len() + 1);
+
+                    for chunk in Some(candidate).into_iter().chain(follow) {
+                        if skip >= chunk.len() {
+                            skip -= chunk.len();
                         } else {
-                            let (ret, rem) = partial.split_into(res.idx);
-                            let (_, first) = rem.split_into(2);
-                            *self.as_mut().state() = Split(first, chunk);
-                            ret
+                            bnd_chunks.push(chunk.split_into(skip).1);
+                            skip = 0;
                         }
+                    }
+
+                    *self.as_mut().state() = if bnd_chunks.len() == 1 {
+                        Found(bnd_chunks.pop().expect("bnd_chunks.len() == 1"))
                     } else {
-                        let (ret, first) = partial.split_into(res.idx);
-                        *self.as_mut().state() = Split(first, chunk);
-                        ret
+                        Split(bnd_chunks)
                     };
 
+                    trace!("boundary located: {:?}", self.state);
+
                     if !ret.is_empty() {
                         return ready_ok(ret);
                     } else {
@@ -214,11 +260,25 @@ where
         if let Some(res) = self.find_boundary(&chunk) {
             debug!("boundary found: {:?}", res);
 
+            // the first boundary must be at the start of a line, the same as the rest,
+            // but the preamble may not end with a CRLF if it is empty
+            if self.in_preamble && !res.incl_crlf && self.offset(chunk.len()) + res.idx as u64 != 0
+            {
+                trace!("ignoring boundary in the middle of a line in the preamble");
+                let (ret, rem) = chunk.split_into(res.idx + 1);
+
+                if !rem.is_empty() {
+                    self.as_mut().pending().push_front(rem);
+                }
+
+                return Some(ret);
+            }
+
             let len = self.boundary_size(res.incl_crlf);
 
             if chunk.len() < res.idx + len {
                 // Either partial boundary, or boundary but not the two bytes after it
-                set_state!(self = Partial(chunk, res));
+                set_state!(self = Partial(chunk, Vec::new(), res));
                 trace!("partial boundary: {:?}", self.state);
                 None
             } else {
@@ -251,13 +311,16 @@ where
     }
 
     fn find_boundary(&self, chunk: &S::Ok) -> Option<SearchResult> {
-        twoway::find_bytes(chunk.as_slice(), &self.boundary)
+        self.finder
+            .find(chunk.as_slice())
             .map(|idx| check_crlf(chunk.as_slice(), idx))
             .or_else(|| self.partial_find_boundary(chunk))
     }
 
-    fn is_boundary_prefix(&self, first: &[u8], second: &[u8], res: SearchResult) -> bool {
-        let maybe_prefix = first.iter().chain(second);
+    fn is_boundary_prefix(&self, first: &[u8], rest: &[S::Ok], res: SearchResult) -> bool {
+        let maybe_prefix = first
+            .iter()
+            .chain(rest.iter().flat_map(|chunk| chunk.as_slice()));
 
         if res.incl_crlf {
             maybe_prefix
@@ -293,24 +356,97 @@ where
             )
     }
 
-    fn check_boundary(&self, bytes: &[u8]) -> bool {
-        (bytes.len() >= 2 && bytes[2..].starts_with(&self.boundary))
-            || bytes.starts_with(&self.boundary)
+    pub fn boundary(&self) -> &[u8] {
+        &self.boundary
     }
 
-    fn check_boundary_split(&self, first: &[u8], second: &[u8]) -> bool {
-        let check_len = self.boundary.len().saturating_sub(first.len());
+    pub fn detected_line_ending(&self) -> Option<LineEnding> {
+        self.line_ending
+    }
 
-        second.len() >= check_len
-            && first
-                .iter()
-                .chain(&second[..check_len])
-                .zip(self.boundary.iter())
-                .all(|(l, r)| l == r)
+    pub fn total_bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    pub fn debug_state(&self) -> String {
+        format!("{:?}, pending chunks: {}", self.state, self.pending.len())
+    }
+
+    /// Wrap the stream to convert its chunks with `f`, also converting any buffered chunks
+    pub fn map_ok<F, B>(self, mut f: F) -> BoundaryFinder<MapChunks<S, F>>
+    where
+        S: Unpin,
+        F: FnMut(S::Ok) -> B,
+    {
+        BoundaryFinder {
+            state: self.state.map(&mut f),
+            pending: self.pending.into_iter().map(&mut f).collect(),
+            stream: MapChunks::new(self.stream, f),
+            boundary: self.boundary,
+            finder: self.finder,
+            bytes_read: self.bytes_read,
+            in_preamble: self.in_preamble,
+            line_ending: self.line_ending,
+        }
+    }
+
+    /// Take apart the finder, returning the chunks it holds that haven't been yielded yet, in
+    /// order, along with the underlying stream
+    pub fn into_parts(self) -> (Vec<S::Ok>, S) {
+        let mut held = match self.state {
+            Watching | End => Vec::new(),
+            Partial(partial, mut follow, _) => {
+                follow.insert(0, partial);
+                follow
+            }
+            Found(bnd) | Remainder(bnd) => vec![bnd],
+            Split(chunks) => chunks,
+        };
+
+        held.extend(self.pending);
+
+        (held, self.stream)
+    }
+
+    /// The offset in the body of the next byte to be examined
+    pub fn position(&self) -> u64 {
+        let held = match self.state {
+            Watching | End => 0,
+            Partial(ref partial, ref follow, _) => {
+                partial.len() + follow.iter().map(|chunk| chunk.len()).sum::<usize>()
+            }
+            Found(ref bnd) | Remainder(ref bnd) => bnd.len(),
+            Split(ref chunks) => chunks.iter().map(|chunk| chunk.len()).sum(),
+        };
+
+        self.offset(held)
+    }
+
+    /// The offset in the body of the first of `held` bytes taken out of `state`
+    fn offset(&self, held: usize) -> u64 {
+        let pending: usize = self.pending.iter().map(|chunk| chunk.len()).sum();
+        self.bytes_read - (pending + held) as u64
+    }
+
+    /// Returns `true` if the end of the current field has been reached
+    pub fn at_boundary(&self) -> bool {
+        matches!(self.state, Found(_) | Split(_) | End)
+    }
+
+    /// Returns `true` if the underlying stream or the multipart body has ended
+    pub fn is_end(&self) -> bool {
+        matches!(self.state, End)
+    }
+
+    fn check_boundary<B: BodyChunk>(&self, bytes: &B) -> bool {
+        bytes.starts_with(&self.boundary)
+            || (bytes.len() >= 2 && bytes.as_slice()[2..].starts_with(&self.boundary))
     }
 
     /// Returns `true` if another field should follow this boundary, `false` if the stream
     /// is at a logical end
+    ///
+    /// Any data before the boundary is discarded, which includes the preamble for the first one.
     pub fn consume_boundary(
         mut self: Pin<&mut Self>,
         cx: &mut Context,
@@ -325,7 +461,7 @@ where
 
         match mem::replace(self.as_mut().state(), Watching) {
             Found(bnd) => self.confirm_boundary(bnd),
-            Split(first, second) => self.confirm_boundary_split(first, second),
+            Split(chunks) => self.confirm_boundary_split(chunks),
             End => {
                 *self.state() = End;
                 ready_ok(false)
@@ -338,30 +474,35 @@ where
         mut self: Pin<&mut Self>,
         boundary: S::Ok,
     ) -> Poll<super::Result<bool, S::Error>> {
+        // checked up front, as the chunk is needed for the message and splitting consumes it
         if boundary.len() < self.boundary_size(false) {
             ret_err!(
-                "boundary sequence too short: {}",
-                show_bytes(boundary.as_slice())
+                BoundaryMismatch: "boundary sequence too short: {} (at byte offset {})",
+                show_bytes(boundary.as_slice()),
+                self.offset(boundary.len())
             );
         }
 
         let (boundary, rem) = boundary.split_into(self.boundary_size(false));
-        let boundary = boundary.as_slice();
-
-        trace!("confirming boundary: {}", show_bytes(boundary));
 
         debug_assert!(
             !boundary.starts_with(b"\r\n"),
             "leading CRLF should have been trimmed from boundary: {}",
-            show_bytes(boundary)
+            show_bytes(boundary.as_slice())
         );
 
         debug_assert!(
-            self.check_boundary(boundary),
+            self.check_boundary(&boundary),
             "invalid boundary previous confirmed as valid: {}",
-            show_bytes(boundary)
+            show_bytes(boundary.as_slice())
         );
 
+        let boundary = boundary.as_slice();
+
+        trace!("confirming boundary: {}", show_bytes(boundary));
+
+        self.as_mut().detect_line_ending(boundary);
+
         set_state!(
             self = if !rem.is_empty() {
                 Remainder(rem)
@@ -385,39 +526,53 @@ where
 
     fn confirm_boundary_split(
         mut self: Pin<&mut Self>,
-        first: S::Ok,
-        second: S::Ok,
+        chunks: Vec<S::Ok>,
     ) -> Poll<super::Result<bool, S::Error>> {
-        let first = first.as_slice();
-        let check_len = self.boundary_size(false) - first.len();
+        let bnd_size = self.boundary_size(false);
+        let offset = self.offset(chunks.iter().map(|chunk| chunk.len()).sum());
+        let mut boundary = Vec::with_capacity(bnd_size);
+        let mut rem = None;
+
+        for chunk in chunks {
+            let needed = bnd_size - boundary.len();
+
+            if chunk.len() > needed {
+                let (chunk, chunk_rem) = chunk.split_into(needed);
+                boundary.extend_from_slice(chunk.as_slice());
+                rem = Some(chunk_rem);
+                break;
+            }
+
+            boundary.extend_from_slice(chunk.as_slice());
+        }
 
-        if second.len() < check_len {
+        if boundary.len() < bnd_size {
             ret_err!(
-                "split boundary sequence too short: ({}, {})",
-                show_bytes(first),
-                show_bytes(second.as_slice())
+                BoundaryMismatch: "split boundary sequence too short: {} (at byte offset {})",
+                show_bytes(&boundary),
+                offset
             );
         }
 
-        let (second, rem) = second.split_into(check_len);
-        let second = second.as_slice();
+        set_state!(self = rem.map_or(Watching, Remainder));
 
-        set_state!(self = Remainder(rem));
+        trace!("confirming split boundary: {}", show_bytes(&boundary));
+
+        self.as_mut().detect_line_ending(&boundary);
 
         debug_assert!(
-            !first.starts_with(b"\r\n"),
-            "leading CRLF should have been trimmed from first boundary section: {}",
-            show_bytes(first)
+            !boundary.starts_with(b"\r\n"),
+            "leading CRLF should have been trimmed from split boundary: {}",
+            show_bytes(&boundary)
         );
 
         debug_assert!(
-            self.check_boundary_split(first, second),
-            "invalid split boundary previous confirmed as valid: ({}, {})",
-            show_bytes(first),
-            show_bytes(second)
+            self.check_boundary(&boundary),
+            "invalid split boundary previous confirmed as valid: {}",
+            show_bytes(&boundary)
         );
 
-        let is_end = check_last_two(second);
+        let is_end = check_last_two(&boundary);
 
         if is_end {
             set_state!(self = End);
@@ -426,6 +581,23 @@ where
         ready_ok(!is_end)
     }
 
+    /// Leave the preamble, recording the line ending after the first boundary.
+    fn detect_line_ending(mut self: Pin<&mut Self>, boundary: &[u8]) {
+        if !mem::replace(self.as_mut().in_preamble(), false) {
+            return;
+        }
+
+        let after = &boundary[self.boundary.len()..];
+
+        *self.line_ending() = if after == b"\r\n" {
+            Some(LineEnding::Crlf)
+        } else if after.starts_with(b"\n") {
+            Some(LineEnding::Lf)
+        } else {
+            None
+        };
+    }
+
     /// The necessary size to verify a boundary, including the potential CRLF before, and the
     /// CRLF / "--" afterward
     fn boundary_size(&self, incl_crlf: bool) -> usize {
@@ -461,40 +633,67 @@ where
 enum State<B> {
     /// Watching for next boundary
     Watching,
-    /// Partial boundary
-    Partial(B, SearchResult),
+    /// Partial boundary, with any following chunks needed to verify it
+    Partial(B, Vec<B>, SearchResult),
+    /// A chunk starting with a boundary
     Found(B),
-    Split(B, B),
+    /// A boundary spanning multiple chunks
+    Split(Vec<B>),
     /// The remains of a chunk after processing
     Remainder(B),
     End,
 }
 
+impl<B> State<B> {
+    fn map<T>(self, mut f: impl FnMut(B) -> T) -> State<T> {
+        match self {
+            Watching => Watching,
+            Partial(partial, follow, res) => {
+                Partial(f(partial), follow.into_iter().map(&mut f).collect(), res)
+            }
+            Found(bnd) => Found(f(bnd)),
+            Split(chunks) => Split(chunks.into_iter().map(f).collect()),
+            Remainder(rem) => Remainder(f(rem)),
+            End => End,
+        }
+    }
+}
+
 impl<B: BodyChunk> fmt::Debug for State<B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use self::State::*;
 
         match *self {
             Watching => f.write_str("State::Watching"),
-            Partial(ref bnd, res) => write!(
-                f,
-                "State::Partial({}, {:?})",
-                show_bytes(bnd.as_slice()),
-                res
-            ),
+            Partial(ref bnd, ref follow, res) => {
+                write!(f, "State::Partial({}, [", show_bytes(bnd.as_slice()))?;
+                fmt_chunks(f, follow)?;
+                write!(f, "], {:?})", res)
+            }
             Found(ref bnd) => write!(f, "State::Found({})", show_bytes(bnd.as_slice())),
-            Split(ref first, ref second) => write!(
-                f,
-                "State::Split(\"{}\", \"{}\")",
-                show_bytes(first.as_slice()),
-                show_bytes(second.as_slice())
-            ),
+            Split(ref chunks) => {
+                f.write_str("State::Split(")?;
+                fmt_chunks(f, chunks)?;
+                f.write_str(")")
+            }
             Remainder(ref rem) => write!(f, "State::Remainder({})", show_bytes(rem.as_slice())),
             End => f.write_str("State::End"),
         }
     }
 }
 
+fn fmt_chunks<B: BodyChunk>(f: &mut fmt::Formatter, chunks: &[B]) -> fmt::Result {
+    for (i, chunk) in chunks.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+
+        write!(f, "\"{}\"", show_bytes(chunk.as_slice()))?;
+    }
+
+    Ok(())
+}
+
 #[derive(Copy, Clone, Debug)]
 struct SearchResult {
     idx: usize,
@@ -547,18 +746,23 @@ fn partial_rmatch(haystack: &[u8], needle: &[u8]) -> Option<usize> {
     }
 
     // If the haystack is smaller than the needle, we still need to test it
-    let trim_start = haystack.len().saturating_sub(needle.len() - 1);
+    let mut start = haystack.len().saturating_sub(needle.len() - 1);
 
-    let idx = try_opt!(twoway::find_bytes(&haystack[trim_start..], &needle[..1])) + trim_start;
+    // A candidate start that doesn't pan out doesn't rule out a later one
+    while let Some(found) = memchr::memchr(needle[0], &haystack[start..]) {
+        let idx = start + found;
 
-    trace!("partial_rmatch found start: {:?}", idx);
+        trace!("partial_rmatch found start: {:?}", idx);
 
-    // If the rest of `haystack` matches `needle`, then we have our partial match
-    if haystack[idx..].iter().zip(needle).all(|(l, r)| l == r) {
-        Some(idx)
-    } else {
-        None
+        // If the rest of `haystack` matches `needle`, then we have our partial match
+        if haystack[idx..].iter().zip(needle).all(|(l, r)| l == r) {
+            return Some(idx);
+        }
+
+        start = idx + 1;
     }
+
+    None
 }
 
 #[cfg(test)]
@@ -592,8 +796,9 @@ mod test {
         pin_mut!(finder);
         ready_assert_eq!(
             |cx| finder.as_mut().consume_boundary(cx),
-            Err(Error::Parsing(
-                "unable to verify multipart boundary; expected: \"--boundary\" found: \"--bound\""
+            Err(Error::UnexpectedEof(
+                "unable to verify multipart boundary; expected: \"--boundary\" found: \"--bound\" \
+                 (at byte offset 0)"
                     .into()
             ))
         );
@@ -656,4 +861,206 @@ mod test {
         ready_assert_eq!(|cx| finder.as_mut().body_chunk(cx), None);
         ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
     }
+
+    #[test]
+    fn test_boundary_one_byte_chunks() {
+        let _ = ::env_logger::try_init();
+
+        const BODY: &[u8] = b"--boundary\r\nfield data\r\n--boundary--";
+        let chunks: Vec<&[u8]> = BODY.chunks(1).collect();
+
+        let finder = BoundaryFinder::new(mock_stream(&chunks), BOUNDARY);
+        pin_mut!(finder);
+
+        ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(true));
+
+        let mut data = Vec::new();
+
+        while let Some(chunk) = until_ready!(|cx| finder.as_mut().body_chunk(cx)) {
+            data.extend_from_slice(chunk.unwrap());
+        }
+
+        assert_eq!(data, b"field data");
+        ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
+    }
+
+    #[test]
+    fn test_end_boundary_no_crlf_all_splits() {
+        let _ = ::env_logger::try_init();
+
+        const TAIL: &[u8] = b"field data\r\n--boundary--";
+        // offset of the end boundary including its leading CRLF
+        const END_START: usize = 10;
+
+        for first in END_START..TAIL.len() {
+            for second in first..=TAIL.len() {
+                let chunks = [
+                    &b"--boundary\r\n"[..],
+                    &TAIL[..first],
+                    &TAIL[first..second],
+                    &TAIL[second..],
+                ];
+
+                let finder = BoundaryFinder::new(mock_stream(&chunks), BOUNDARY);
+                pin_mut!(finder);
+
+                ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(true));
+
+                let mut data = Vec::new();
+
+                while let Some(chunk) = until_ready!(|cx| finder.as_mut().body_chunk(cx)) {
+                    data.extend_from_slice(chunk.unwrap());
+                }
+
+                assert_eq!(data, b"field data", "chunks: {:?}", chunks);
+                ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
+                ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
+            }
+        }
+    }
+
+    #[test]
+    fn test_near_boundary_at_end_of_last_field() {
+        let _ = ::env_logger::try_init();
+
+        // data ending with a prefix of the boundary, with and without a preceding CRLF
+        let datas: &[&[u8]] = &[
+            b"--bound",
+            b"field data--bound",
+            b"field data\r\n--bound",
+            b"field data\r\n--boundar",
+            b"field data\r\n-",
+            b"field data\r\n",
+        ];
+
+        for data in datas {
+            for end in &[&b"\r\n--boundary--"[..], b"\r\n--boundary--\r\n"] {
+                let tail = [*data, *end].concat();
+
+                for first in 0..tail.len() {
+                    for second in first..=tail.len() {
+                        let chunks = [
+                            &b"--boundary\r\n"[..],
+                            &tail[..first],
+                            &tail[first..second],
+                            &tail[second..],
+                        ];
+
+                        let finder = BoundaryFinder::new(mock_stream(&chunks), BOUNDARY);
+                        pin_mut!(finder);
+
+                        ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(true));
+
+                        let mut read = Vec::new();
+
+                        while let Some(chunk) = until_ready!(|cx| finder.as_mut().body_chunk(cx)) {
+                            read.extend_from_slice(chunk.unwrap());
+                        }
+
+                        assert_eq!(read, *data, "chunks: {:?}", chunks);
+                        ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bare_cr_at_chunk_end() {
+        let _ = ::env_logger::try_init();
+
+        // a lone `\r` at the end of a chunk that doesn't turn out to start the boundary
+        // must be returned as data
+        let cases: &[&[&[u8]]] = &[
+            &[b"field\r", b"data"],
+            &[b"field\r", b"\ndata"],
+            &[b"field\r", b"\n-data"],
+            &[b"field\r", b"\n--bound data"],
+            &[b"field\r", b"\r", b"\rdata"],
+            &[b"\r", b"data\r"],
+            &[b"field\r\n", b"\r"],
+            &[b"field\r"],
+        ];
+
+        for data in cases {
+            let mut chunks = vec![&b"--boundary\r\n"[..]];
+            chunks.extend_from_slice(data);
+            chunks.push(b"\r\n--boundary--");
+
+            let finder = BoundaryFinder::new(mock_stream(&chunks), BOUNDARY);
+            pin_mut!(finder);
+
+            ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(true));
+
+            let mut read = Vec::new();
+
+            while let Some(chunk) = until_ready!(|cx| finder.as_mut().body_chunk(cx)) {
+                read.extend_from_slice(chunk.unwrap());
+            }
+
+            assert_eq!(read, data.concat(), "chunks: {:?}", chunks);
+            ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
+        }
+    }
+
+    #[test]
+    fn test_partial_rmatch_repeated_bytes() {
+        use super::partial_rmatch;
+
+        // the first candidate in the window fails but a later one is the real prefix
+        assert_eq!(partial_rmatch(b"data aba", b"aaab"), Some(7));
+        assert_eq!(partial_rmatch(b"field-\r\n-", b"--aab"), Some(8));
+        assert_eq!(partial_rmatch(b"field-\r\n--a", b"--aab"), Some(8));
+        assert_eq!(partial_rmatch(b"field-\r\n", b"--aab"), None);
+    }
+
+    #[test]
+    fn test_boundary_repeated_bytes_split() {
+        let _ = ::env_logger::try_init();
+
+        // the boundary `aab` split so that a dash in the window before it doesn't start it
+        let cases: &[&[&[u8]]] = &[
+            &[b"field-\r\n-", b"-aab--"],
+            &[b"field-\r\n--a", b"ab--"],
+            &[b"field-a\r\n--aa", b"b--"],
+            &[b"-\r\n-", b"-aab--"],
+        ];
+
+        for chunks in cases {
+            let mut body = vec![&b"--aab\r\n"[..]];
+            body.extend_from_slice(chunks);
+
+            let finder = BoundaryFinder::new(mock_stream(&body), "--aab");
+            pin_mut!(finder);
+
+            ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(true));
+
+            let mut read = Vec::new();
+
+            while let Some(chunk) = until_ready!(|cx| finder.as_mut().body_chunk(cx)) {
+                read.extend_from_slice(chunk.unwrap());
+            }
+
+            let expected = chunks.concat();
+            let data_len = expected.len() - b"\r\n--aab--".len();
+            assert_eq!(read, &expected[..data_len], "chunks: {:?}", body);
+            ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(false));
+        }
+    }
+
+    #[test]
+    fn test_end_boundary_truncated() {
+        let _ = ::env_logger::try_init();
+
+        for tail in &[&b"\r\n--boundary-"[..], b"\r\n--boundary", b"\r\n--bou"] {
+            let chunks = [&b"--boundary\r\nfield data"[..], tail];
+
+            let finder = BoundaryFinder::new(mock_stream(&chunks), BOUNDARY);
+            pin_mut!(finder);
+
+            ready_assert_eq!(|cx| finder.as_mut().consume_boundary(cx), Ok(true));
+            let err = until_ready!(|cx| finder.as_mut().consume_boundary(cx)).unwrap_err();
+            assert!(err.is_truncation(), "{:?}: {}", tail, err);
+        }
+    }
 }
@@ -0,0 +1,184 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// A limit on the total number of fields and the depth of nesting, shared between a `Multipart`
+/// and the nested bodies read from its fields with
+/// [`FieldData::into_nested()`](struct.FieldData.html#method.into_nested).
+///
+/// [`Multipart::with_max_fields()`](struct.Multipart.html#method.with_max_fields) limits each
+/// body on its own, so a request nesting many small bodies could still contain any number of
+/// fields in total; with a budget, the fields at every level count against the same limit.
+///
+/// Set with [`Multipart::with_field_budget()`](struct.Multipart.html#method.with_field_budget).
+/// Cloning yields a handle to the same budget.
+#[derive(Clone, Debug)]
+pub struct FieldBudget {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    max_fields: usize,
+    max_depth: usize,
+    fields: AtomicUsize,
+}
+
+impl FieldBudget {
+    /// Allow at most `max_fields` fields in total and `max_depth` levels of bodies nested
+    /// below the top-level one.
+    pub fn new(max_fields: usize, max_depth: usize) -> Self {
+        FieldBudget {
+            inner: Arc::new(Inner {
+                max_fields,
+                max_depth,
+                fields: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    /// The total number of fields allowed across all levels.
+    pub fn max_fields(&self) -> usize {
+        self.inner.max_fields
+    }
+
+    /// The number of levels of nesting allowed below the top-level body.
+    pub fn max_depth(&self) -> usize {
+        self.inner.max_depth
+    }
+
+    /// The number of fields read so far across all levels.
+    pub fn fields_read(&self) -> usize {
+        self.inner.fields.load(Ordering::SeqCst)
+    }
+
+    /// Count another field, returning `false` if the budget is already spent.
+    pub(crate) fn take_field(&self) -> bool {
+        let max_fields = self.inner.max_fields;
+
+        self.inner
+            .fields
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |fields| {
+                if fields < max_fields {
+                    Some(fields + 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::TryStreamExt;
+
+    use super::FieldBudget;
+    use crate::server::Multipart;
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    /// An outer field containing a nested body of two fields, itself containing a nested body
+    /// in its second field
+    const BODY: &[u8] = b"--outer\r\n\
+        Content-Disposition: form-data; name=\"files\"\r\n\
+        Content-Type: multipart/form-data; boundary=inner\r\n\r\n\
+        --inner\r\n\
+        Content-Disposition: form-data; name=\"a\"; filename=\"a.txt\"\r\n\r\n\
+        file a\r\n\
+        --inner\r\n\
+        Content-Disposition: form-data; name=\"b\"\r\n\
+        Content-Type: multipart/form-data; boundary=deepest\r\n\r\n\
+        --deepest\r\n\
+        Content-Disposition: form-data; name=\"c\"; filename=\"c.txt\"\r\n\r\n\
+        file c\r\n\
+        --deepest--\r\n\
+        --inner--\r\n\
+        --outer--\r\n";
+
+    /// Read every field at every level, returning the names of those read before any error
+    fn read_all(budget: FieldBudget) -> (Vec<String>, Result<(), String>) {
+        let mut names = Vec::new();
+
+        let res = run_future_hot(async {
+            let mut outer =
+                Multipart::with_body(mock_stream(&[BODY]), "outer").with_field_budget(budget);
+
+            let files = outer
+                .next_field()
+                .await
+                .map_err(|e| e.to_string())?
+                .unwrap();
+            names.push(files.headers.name);
+
+            let mut inner = files.data.into_nested("inner").map_err(|e| e.to_string())?;
+
+            let a = inner
+                .next_field()
+                .await
+                .map_err(|e| e.to_string())?
+                .unwrap();
+            names.push(a.headers.name);
+            a.data
+                .try_for_each(|_| async { Ok(()) })
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let b = inner
+                .next_field()
+                .await
+                .map_err(|e| e.to_string())?
+                .unwrap();
+            names.push(b.headers.name);
+
+            let mut deepest = b.data.into_nested("deepest").map_err(|e| e.to_string())?;
+
+            while let Some(field) = deepest.next_field().await.map_err(|e| e.to_string())? {
+                names.push(field.headers.name);
+            }
+
+            Ok::<_, String>(())
+        });
+
+        (names, res)
+    }
+
+    #[test]
+    fn test_within_budget() {
+        let _ = ::env_logger::try_init();
+
+        let budget = FieldBudget::new(4, 2);
+        let (names, res) = read_all(budget.clone());
+
+        res.unwrap();
+        assert_eq!(names, ["files", "a", "b", "c"]);
+        assert_eq!(budget.fields_read(), 4);
+    }
+
+    #[test]
+    fn test_field_budget_exceeded() {
+        let _ = ::env_logger::try_init();
+
+        // each level has at most two fields, but four in total
+        let (names, res) = read_all(FieldBudget::new(3, 2));
+
+        assert_eq!(names, ["files", "a", "b"]);
+        let err = res.unwrap_err();
+        assert!(err.contains("limit of 3 fields across"), "{}", err);
+    }
+
+    #[test]
+    fn test_depth_exceeded() {
+        let _ = ::env_logger::try_init();
+
+        let (names, res) = read_all(FieldBudget::new(10, 1));
+
+        assert_eq!(names, ["files", "a", "b"]);
+        let err = res.unwrap_err();
+        assert!(err.contains("nesting depth limit of 1"), "{}", err);
+    }
+}
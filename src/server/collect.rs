@@ -0,0 +1,262 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{Future, TryStream};
+
+use crate::BodyChunk;
+
+use super::field::PollNextField;
+use super::{BufferedField, Error, FieldHeaders, Multipart};
+
+impl<S> Multipart<S>
+where
+    S: TryStream,
+    S::Ok: BodyChunk,
+{
+    /// Return a `Future` which reads the whole request into memory, grouping the fields by name.
+    ///
+    /// Fields which share a name, such as from `<input type="file" multiple>` or a group of
+    /// checkboxes, are kept in the order they were sent.
+    ///
+    /// Unbounded by default; call [`.field_limit()`](struct.CollectFields.html#method.field_limit)
+    /// and [`.total_limit()`](struct.CollectFields.html#method.total_limit) on the returned
+    /// future to error out instead of buffering an arbitrarily large request.
+    pub fn collect_fields(self) -> CollectFields<S> {
+        CollectFields {
+            multipart: Box::pin(self),
+            next: PollNextField::default(),
+            current: None,
+            fields: HashMap::new(),
+            field_limit: None,
+            total_limit: None,
+            total: 0,
+        }
+    }
+}
+
+/// The value of a field read by [`Multipart::collect_fields()`](struct.Multipart.html#method.collect_fields).
+#[derive(Clone, Debug)]
+pub enum FieldValue {
+    /// A [text field](struct.FieldHeaders.html#method.is_text_field), read to a string.
+    Text(FieldHeaders, String),
+    /// Any other field, with its data buffered.
+    File(BufferedField),
+}
+
+impl FieldValue {
+    /// The headers of this field.
+    pub fn headers(&self) -> &FieldHeaders {
+        match self {
+            FieldValue::Text(headers, _) => headers,
+            FieldValue::File(field) => &field.headers,
+        }
+    }
+
+    /// The data of this field, as UTF-8 for a text field.
+    pub fn data(&self) -> &[u8] {
+        match self {
+            FieldValue::Text(_, text) => text.as_bytes(),
+            FieldValue::File(field) => &field.data,
+        }
+    }
+}
+
+/// A `Future` which yields all the fields of a request grouped by name.
+///
+/// See [`Multipart::collect_fields()`](struct.Multipart.html#method.collect_fields).
+pub struct CollectFields<S: TryStream> {
+    multipart: Pin<Box<Multipart<S>>>,
+    next: PollNextField,
+    current: Option<BufferedField>,
+    fields: HashMap<String, Vec<FieldValue>>,
+    field_limit: Option<usize>,
+    total_limit: Option<usize>,
+    /// The data bytes buffered across all fields
+    total: usize,
+}
+
+impl<S: TryStream> CollectFields<S> {
+    /// Return an error instead of buffering more than `limit` bytes of any one field.
+    pub fn field_limit(mut self, limit: usize) -> Self {
+        self.field_limit = Some(limit);
+        self
+    }
+
+    /// Return an error instead of buffering more than `limit` bytes of field data in total.
+    pub fn total_limit(mut self, limit: usize) -> Self {
+        self.total_limit = Some(limit);
+        self
+    }
+}
+
+impl<S> Future for CollectFields<S>
+where
+    S: TryStream,
+    S::Ok: BodyChunk,
+    Error<S::Error>: From<S::Error>,
+{
+    type Output = super::Result<HashMap<String, Vec<FieldValue>>, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        loop {
+            let field = match this.current {
+                Some(ref mut field) => field,
+                None => match ready!(this.next.poll(this.multipart.as_mut(), cx)?) {
+                    Some(headers) => this.current.get_or_insert(BufferedField {
+                        headers,
+                        data: Vec::new(),
+                    }),
+                    None => return Poll::Ready(Ok(mem::take(&mut this.fields))),
+                },
+            };
+
+            while let Some(chunk) = ready!(this.multipart.as_mut().poll_field_chunk(cx)?) {
+                let chunk = chunk.as_slice();
+
+                if let Some(limit) = this.field_limit {
+                    if field.data.len().saturating_add(chunk.len()) > limit {
+                        ret_err!(
+                            "field {:?} exceeds the limit of {} bytes",
+                            field.headers.name,
+                            limit
+                        );
+                    }
+                }
+
+                this.total = this.total.saturating_add(chunk.len());
+
+                if let Some(limit) = this.total_limit {
+                    if this.total > limit {
+                        ret_err!(
+                            "request field data exceeds the total limit of {} bytes",
+                            limit
+                        );
+                    }
+                }
+
+                field.data.extend_from_slice(chunk);
+            }
+
+            let field = this.current.take().expect("no current field");
+
+            let value = if field.headers.is_text_field() {
+                match String::from_utf8(field.data) {
+                    Ok(text) => FieldValue::Text(field.headers, text),
+                    Err(e) => return Poll::Ready(Err(Error::Utf8(e.utf8_error()))),
+                }
+            } else {
+                FieldValue::File(field)
+            };
+
+            this.fields
+                .entry(value.headers().name.clone())
+                .or_default()
+                .push(value);
+        }
+    }
+}
+
+impl<S: TryStream> fmt::Debug for CollectFields<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CollectFields")
+            .field("fields", &self.fields)
+            .field("field_limit", &self.field_limit)
+            .field("total_limit", &self.total_limit)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::server::{FieldValue, Multipart};
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    const BODY: &[&[u8]] = &[
+        b"--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"check\"\r\n\r\n",
+        b"one",
+        b"\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"files\"; filename=\"a.bin\"\r\n",
+        b"Content-Type: application/octet-stream\r\n\r\n",
+        b"\x00\x01",
+        b"\x02\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"check\"\r\n\r\n",
+        b"two",
+        b"\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"files\"; filename=\"b.txt\"\r\n\r\n",
+        b"text file",
+        b"\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"check\"\r\n\r\n",
+        b"three",
+        b"\r\n--boundary--",
+    ];
+
+    #[test]
+    fn test_collect_fields() {
+        let _ = ::env_logger::try_init();
+
+        let multipart = Multipart::with_body(mock_stream(BODY), "boundary");
+        let fields = run_future_hot(multipart.collect_fields()).unwrap();
+
+        assert_eq!(fields.len(), 2);
+
+        let checks: Vec<_> = fields["check"]
+            .iter()
+            .map(|value| match value {
+                FieldValue::Text(_, text) => &text[..],
+                other => panic!("expected text field: {:?}", other),
+            })
+            .collect();
+        assert_eq!(checks, ["one", "two", "three"]);
+
+        let files = &fields["files"];
+        assert_eq!(files.len(), 2);
+
+        assert!(matches!(files[0], FieldValue::File(_)));
+        assert_eq!(files[0].headers().filename.as_deref(), Some("a.bin"));
+        assert_eq!(files[0].data(), b"\x00\x01\x02");
+
+        assert!(matches!(files[1], FieldValue::File(_)));
+        assert_eq!(files[1].headers().filename.as_deref(), Some("b.txt"));
+        assert_eq!(files[1].data(), b"text file");
+    }
+
+    #[test]
+    fn test_collect_fields_limits() {
+        let _ = ::env_logger::try_init();
+
+        let multipart = Multipart::with_body(mock_stream(BODY), "boundary");
+        let err = run_future_hot(multipart.collect_fields().field_limit(8)).unwrap_err();
+        assert!(err.is_client_error());
+        assert!(
+            err.to_string()
+                .contains("field \"files\" exceeds the limit of 8 bytes"),
+            "{}",
+            err
+        );
+
+        // 23 bytes of field data in total
+        let multipart = Multipart::with_body(mock_stream(BODY), "boundary");
+        let err = run_future_hot(multipart.collect_fields().total_limit(22)).unwrap_err();
+        assert!(
+            err.to_string().contains("total limit of 22 bytes"),
+            "{}",
+            err
+        );
+
+        let multipart = Multipart::with_body(mock_stream(BODY), "boundary");
+        let fields = run_future_hot(multipart.collect_fields().field_limit(9).total_limit(23));
+        assert_eq!(fields.unwrap()["check"].len(), 3);
+    }
+}
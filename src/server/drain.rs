@@ -0,0 +1,182 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::ops::{Deref, DerefMut};
+
+use futures_core::TryStream;
+use tokio::runtime::Handle;
+
+use crate::BodyChunk;
+
+use super::Multipart;
+
+impl<S> Multipart<S>
+where
+    S: TryStream + Send + 'static,
+    S::Ok: BodyChunk + Send + 'static,
+    S::Error: Send + 'static,
+{
+    /// Wrap `self` in a guard which reads and discards the rest of the request body when it is
+    /// dropped, so that an early return from a handler doesn't leave unread data on a
+    /// keep-alive connection. Enabled with the `drain` feature.
+    ///
+    /// As there is no async `Drop`, the body is drained on a task spawned onto the current
+    /// Tokio runtime; nothing is done if the body was already fully read, or if the guard is
+    /// dropped outside of a runtime. The drain stops at the first error.
+    pub fn drain_on_drop(self) -> DrainOnDrop<S> {
+        DrainOnDrop {
+            multipart: Some(self),
+        }
+    }
+}
+
+/// A `Multipart` which drains the rest of its body when dropped.
+///
+/// Returned by [`Multipart::drain_on_drop()`](struct.Multipart.html#method.drain_on_drop).
+/// Dereferences to the wrapped `Multipart`.
+pub struct DrainOnDrop<S>
+where
+    S: TryStream + Send + 'static,
+    S::Ok: BodyChunk + Send + 'static,
+    S::Error: Send + 'static,
+{
+    multipart: Option<Multipart<S>>,
+}
+
+impl<S> DrainOnDrop<S>
+where
+    S: TryStream + Send + 'static,
+    S::Ok: BodyChunk + Send + 'static,
+    S::Error: Send + 'static,
+{
+    /// Unwrap the `Multipart` without draining it.
+    pub fn into_inner(mut self) -> Multipart<S> {
+        self.multipart.take().expect("DrainOnDrop already dropped")
+    }
+}
+
+impl<S> Deref for DrainOnDrop<S>
+where
+    S: TryStream + Send + 'static,
+    S::Ok: BodyChunk + Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Target = Multipart<S>;
+
+    fn deref(&self) -> &Multipart<S> {
+        self.multipart
+            .as_ref()
+            .expect("DrainOnDrop already dropped")
+    }
+}
+
+impl<S> DerefMut for DrainOnDrop<S>
+where
+    S: TryStream + Send + 'static,
+    S::Ok: BodyChunk + Send + 'static,
+    S::Error: Send + 'static,
+{
+    fn deref_mut(&mut self) -> &mut Multipart<S> {
+        self.multipart
+            .as_mut()
+            .expect("DrainOnDrop already dropped")
+    }
+}
+
+impl<S> Drop for DrainOnDrop<S>
+where
+    S: TryStream + Send + 'static,
+    S::Ok: BodyChunk + Send + 'static,
+    S::Error: Send + 'static,
+{
+    fn drop(&mut self) {
+        let multipart = match self.multipart.take() {
+            Some(multipart) if !multipart.is_consumed() => multipart,
+            _ => return,
+        };
+
+        let handle = match Handle::try_current() {
+            Ok(handle) => handle,
+            Err(_) => {
+                warn!("DrainOnDrop dropped outside of a Tokio runtime; body not drained");
+                return;
+            }
+        };
+
+        handle.spawn(async move {
+            let mut multipart = Box::pin(multipart);
+
+            // `poll_has_next_field()` discards the rest of the current field
+            loop {
+                let has_next =
+                    tokio::future::poll_fn(|cx| multipart.as_mut().poll_has_next_field(cx));
+
+                match has_next.await {
+                    Ok(true) => (),
+                    Ok(false) => break,
+                    Err(_) => {
+                        debug!("error while draining request body");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_drain_on_drop() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use futures_util::StreamExt;
+
+    use crate::test_util::mock_stream;
+
+    let _ = ::env_logger::try_init();
+
+    const END: &[u8] = b"\r\n--boundary--";
+
+    let reached_end = Arc::new(AtomicBool::new(false));
+    let set_end = reached_end.clone();
+
+    let body = mock_stream(&[
+        b"--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+        b"foo data",
+        b"\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+        b"bar data",
+        END,
+    ])
+    .inspect(move |chunk| {
+        if *chunk == Ok(END) {
+            set_end.store(true, Ordering::SeqCst);
+        }
+    });
+
+    let mut multipart = Multipart::with_body(body, "boundary").drain_on_drop();
+
+    // read only the first field's headers, as if the handler returned early
+    let field = multipart.next_field().await.unwrap().unwrap();
+    assert_eq!(field.headers.name, "foo");
+    assert!(!reached_end.load(Ordering::SeqCst));
+
+    drop(field);
+    drop(multipart);
+
+    // the drain task runs on the same runtime
+    for _ in 0..100 {
+        if reached_end.load(Ordering::SeqCst) {
+            return;
+        }
+
+        let _ = tokio::task::yield_now().await;
+    }
+
+    panic!("request body was not drained");
+}
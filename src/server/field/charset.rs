@@ -0,0 +1,200 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::mem;
+use std::pin::Pin;
+use std::task::Poll::{self, *};
+
+use encoding_rs::{Decoder, DecoderResult, Encoding};
+use futures_core::task::Context;
+use futures_core::{Future, Stream, TryStream};
+
+use crate::server::Error;
+use crate::BodyChunk;
+
+use super::FieldData;
+
+/// A `Future` that yields the body of a field decoded from a given charset to a `String`.
+///
+/// See [`FieldData::read_to_string_with_charset()`](struct.FieldData.html#method.read_to_string_with_charset).
+pub struct ReadToStringWithCharset<'a, S: TryStream + 'a> {
+    data: FieldData<'a, S>,
+    charset: String,
+    decoder: Option<Decoder>,
+    string: String,
+    read: usize,
+    limit: Option<usize>,
+}
+
+impl<'a, S: TryStream + 'a> ReadToStringWithCharset<'a, S> {
+    pub(crate) fn new(data: FieldData<'a, S>, charset: &str) -> Self {
+        ReadToStringWithCharset {
+            data,
+            charset: charset.to_string(),
+            decoder: Encoding::for_label(charset.as_bytes()).map(Encoding::new_decoder),
+            string: String::new(),
+            read: 0,
+            limit: None,
+        }
+    }
+
+    /// Return an error instead of reading more than `limit` bytes.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl<S: TryStream> Future for ReadToStringWithCharset<'_, S>
+where
+    S::Ok: BodyChunk,
+    Error<S::Error>: From<S::Error>,
+{
+    type Output = super::super::Result<String, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        let decoder = match this.decoder {
+            Some(ref mut decoder) => decoder,
+            None => ret_err!("unknown charset: {:?}", this.charset),
+        };
+
+        loop {
+            let chunk = ready!(Pin::new(&mut this.data).poll_next(cx)?);
+            let last = chunk.is_none();
+            let mut src = chunk.as_ref().map_or(&[][..], BodyChunk::as_slice);
+
+            this.read = this.read.saturating_add(src.len());
+
+            if let Some(limit) = this.limit {
+                if this.read > limit {
+                    ret_err!("field data exceeds the limit of {} bytes", limit);
+                }
+            }
+
+            // a sequence split between chunks is held by the decoder until the next call
+            loop {
+                let needed = decoder
+                    .max_utf8_buffer_length_without_replacement(src.len())
+                    .unwrap_or(src.len());
+                this.string.reserve(needed);
+
+                let (res, read) =
+                    decoder.decode_to_string_without_replacement(src, &mut this.string, last);
+                src = &src[read..];
+
+                match res {
+                    DecoderResult::InputEmpty => break,
+                    DecoderResult::OutputFull => continue,
+                    DecoderResult::Malformed(..) => {
+                        ret_err!("field data is not valid {}", decoder.encoding().name())
+                    }
+                }
+            }
+
+            if last {
+                return Ready(Ok(mem::take(&mut this.string)));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::server::Multipart;
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    fn decode_field(
+        charset: &str,
+        data: &[&[u8]],
+    ) -> Result<String, crate::server::Error<std::convert::Infallible>> {
+        let mut chunks = vec![
+            &b"--boundary\r\n"[..],
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+        ];
+        chunks.extend_from_slice(data);
+        chunks.push(b"\r\n--boundary--");
+
+        let mut multipart = Multipart::with_body(mock_stream(&chunks), "boundary");
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+
+        run_future_hot(field.data.read_to_string_with_charset(charset))
+    }
+
+    #[test]
+    fn test_windows_1252() {
+        let _ = ::env_logger::try_init();
+
+        assert_eq!(
+            decode_field("windows-1252", &[b"caf\xE9 ", b"\x80100"]).unwrap(),
+            "caf\u{E9} \u{20AC}100"
+        );
+
+        // `latin1` is an alias per the WHATWG Encoding Standard
+        assert_eq!(decode_field("latin1", &[b"\xE9"]).unwrap(), "\u{E9}");
+    }
+
+    #[test]
+    fn test_utf_16_split_between_chunks() {
+        let _ = ::env_logger::try_init();
+
+        // "h\u{E9}\u{1F600}" with the code units and surrogate pair split between chunks
+        assert_eq!(
+            decode_field(
+                "UTF-16LE",
+                &[b"h", b"\x00\xE9\x00\x3D", b"\xD8", b"\x00\xDE"]
+            )
+            .unwrap(),
+            "h\u{E9}\u{1F600}"
+        );
+
+        let err = decode_field("utf-16le", &[b"h\x00\x3D"]).unwrap_err();
+        assert!(err.is_client_error(), "{}", err);
+        assert!(err.to_string().contains("UTF-16LE"), "{}", err);
+    }
+
+    #[test]
+    fn test_default_charset() {
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"before\"\r\n\r\n",
+                b"caf\xC3\xA9",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"_charset_\"\r\n\r\n",
+                b"iso-8859-1",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"after\"\r\n\r\n",
+                b"caf\xE9",
+                b"\r\n--boundary--",
+            ]),
+            "boundary",
+        )
+        .with_charset_field(true);
+
+        // UTF-8 until the `_charset_` field is read
+        for name in &["before", "after"] {
+            let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+            assert_eq!(field.headers.name, *name);
+            assert_eq!(
+                run_future_hot(field.data.read_to_string_with_default_charset()).unwrap(),
+                "caf\u{E9}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_charset() {
+        let _ = ::env_logger::try_init();
+
+        let err = decode_field("x-no-such-charset", &[b"data"]).unwrap_err();
+        assert!(err.is_client_error(), "{}", err);
+        assert!(err.to_string().contains("unknown charset"), "{}", err);
+    }
+}
@@ -5,15 +5,22 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 use std::ascii::AsciiExt;
+use std::borrow::Cow;
+use std::convert::Infallible;
 use std::pin::Pin;
 use std::str;
 use std::task::Poll::{self, *};
 
+use bytes::Bytes;
+
 use futures_core::stream::{Stream, TryStream};
 use futures_core::task::Context;
 
 use http::header::{HeaderMap, HeaderName, HeaderValue};
 use httparse::{Status, EMPTY_HEADER};
+#[cfg(feature = "httpdate")]
+use httpdate::HttpDate;
+use memchr::memmem;
 use mime::{self, Mime, Name};
 
 use crate::server::helpers::*;
@@ -21,7 +28,8 @@ use crate::server::{Error, PushChunk};
 use crate::BodyChunk;
 use http::Response;
 
-const MAX_BUF_LEN: usize = 1024;
+/// The default for `ReadHeaders::max_len`
+pub(crate) const MAX_BUF_LEN: usize = 1024;
 const MAX_HEADERS: usize = 4;
 
 /// The headers of a `Field`, including the name, filename, and `Content-Type`, if provided.
@@ -53,21 +61,89 @@ pub struct FieldHeaders {
     pub content_type: Option<Mime>,
     /// Any additional headers, standard or otherwise, for this field as provided by the client.
     ///
+    /// Header names are always stored lowercased (e.g. `X-Custom-Header` becomes
+    /// `x-custom-header`) as `HeaderName` normalizes them; the casing sent by the client
+    /// is not kept.
+    ///
     /// The size of this map will be limited internally.
     pub ext_headers: HeaderMap,
+    /// The `creation-date` parameter of `Content-Disposition`
+    /// ([IETF RFC 2183, Section 2.4](https://tools.ietf.org/html/rfc2183#section-2.4)),
+    /// as reported by the client. Enabled with the `httpdate` feature.
+    ///
+    /// Only dates in one of the HTTP formats, such as `Wed, 12 Feb 1997 21:29:51 GMT`, are
+    /// understood; anything else, including a numeric timezone, is ignored.
+    #[cfg(feature = "httpdate")]
+    pub creation_date: Option<HttpDate>,
+    /// The `modification-date` parameter of `Content-Disposition`, as reported by the client.
+    /// See `creation_date` for the formats understood. Enabled with the `httpdate` feature.
+    #[cfg(feature = "httpdate")]
+    pub modification_date: Option<HttpDate>,
+    /// Whether `content_type` is text, if decided by
+    /// [`Multipart::text_content_types()`](struct.Multipart.html#method.text_content_types)
+    pub(crate) text_type: Option<bool>,
     pub(crate) _backcompat: (),
 }
 
 impl FieldHeaders {
     /// `true` if `content_type` is `None` or `text/*` (such as `text/plain`).
     ///
+    /// Which content types count as text can be changed with
+    /// [`Multipart::text_content_types()`](struct.Multipart.html#method.text_content_types).
+    ///
     /// **Note**: this does not guarantee that the field data is compatible with
     /// `FieldData::read_text()`; supporting more encodings than ASCII/UTF-8 is (currently)
     /// beyond the scope of this crate.
     pub fn is_text(&self) -> bool {
-        self.content_type
-            .as_ref()
-            .map_or(true, |ct| ct.type_() == mime::TEXT)
+        match self.content_type {
+            Some(ref ct) => self.text_type.unwrap_or(ct.type_() == mime::TEXT),
+            None => true,
+        }
+    }
+
+    /// `true` if this field is a file upload: it has a `filename`, or a `content_type` that
+    /// isn't [text](#method.is_text).
+    ///
+    /// Browsers send a `filename` (possibly empty) for every `<input type="file">`, even if no
+    /// file was selected.
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some() || !self.is_text()
+    }
+
+    /// `true` if this is a plain text field that can be read to a `String`: it has no
+    /// `filename`, its `content_type` is `None` or [text](#method.is_text), and its charset,
+    /// if provided, is UTF-8 or US-ASCII.
+    pub fn is_text_field(&self) -> bool {
+        let utf8_compatible = match self.charset() {
+            Some(charset) => charset == mime::UTF_8 || charset == "us-ascii",
+            None => true,
+        };
+
+        self.filename.is_none() && self.is_text() && utf8_compatible
+    }
+
+    /// Get a header from `ext_headers` by name.
+    pub fn get_ext(&self, name: &HeaderName) -> Option<&HeaderValue> {
+        self.ext_headers.get(name)
+    }
+
+    /// The `Content-Transfer-Encoding` of this field, if provided, lowercased so it can be
+    /// compared directly (e.g. `"base64"` or `"quoted-printable"`).
+    ///
+    /// Returns `None` if the header's value is not valid ASCII.
+    pub fn content_transfer_encoding(&self) -> Option<Cow<'_, str>> {
+        let val = self
+            .ext_headers
+            .get("content-transfer-encoding")?
+            .to_str()
+            .ok()?
+            .trim();
+
+        if val.bytes().any(|b| b.is_ascii_uppercase()) {
+            Some(Cow::Owned(val.to_ascii_lowercase()))
+        } else {
+            Some(Cow::Borrowed(val))
+        }
     }
 
     /// The character set of this field, if provided.
@@ -76,11 +152,53 @@ impl FieldHeaders {
             .as_ref()
             .and_then(|ct| ct.get_param(mime::CHARSET))
     }
+
+    /// The `type/subtype` of `content_type` without any parameters, e.g. `"text/plain"` for
+    /// `text/plain; charset=utf-8`, for comparing against a list of allowed types.
+    ///
+    /// The type and subtype are always lowercase, as `Mime` lowercases them when parsing.
+    pub fn content_type_essence(&self) -> Option<&str> {
+        self.content_type.as_ref().map(Mime::essence_str)
+    }
+
+    /// Guess a file extension (without the leading `.`) from `content_type`, e.g. `"png"` for
+    /// `image/png`.
+    ///
+    /// If several extensions are known for the type, one matching the subtype is preferred
+    /// (`"jpeg"` for `image/jpeg`). Useful when `filename` is missing or cannot be trusted.
+    #[cfg(feature = "mime_guess")]
+    pub fn guess_extension(&self) -> Option<&'static str> {
+        let content_type = self.content_type.as_ref()?;
+        let exts = mime_guess::get_mime_extensions(content_type)?;
+        let subtype = content_type.subtype().as_str();
+
+        exts.iter()
+            .find(|ext| ext.eq_ignore_ascii_case(subtype))
+            .or_else(|| exts.first())
+            .copied()
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub(crate) struct ReadHeaders {
     accumulator: Vec<u8>,
+    /// Replace invalid UTF-8 in extended `Content-Disposition` parameters instead of erroring
+    pub lossy_utf8: bool,
+    /// Accept whitespace between header names and the colon instead of erroring
+    pub lenient_header_names: bool,
+    /// The maximum length of the headers section of a field
+    pub max_len: usize,
+}
+
+impl Default for ReadHeaders {
+    fn default() -> Self {
+        ReadHeaders {
+            accumulator: Vec::new(),
+            lossy_utf8: false,
+            lenient_header_names: false,
+            max_len: MAX_BUF_LEN,
+        }
+    }
 }
 
 impl ReadHeaders {
@@ -88,16 +206,60 @@ impl ReadHeaders {
         !self.accumulator.is_empty()
     }
 
+    pub fn accumulated_len(&self) -> usize {
+        self.accumulator.len()
+    }
+
+    pub fn accumulated(&self) -> &[u8] {
+        &self.accumulator
+    }
+
+    pub fn clear(&mut self) {
+        self.accumulator.clear();
+    }
+
+    fn parse(&self, bytes: &[u8]) -> Result<FieldHeaders, ParseError> {
+        if self.lenient_header_names {
+            parse_headers(&trim_header_names(bytes), self.lossy_utf8)
+        } else {
+            parse_headers(bytes, self.lossy_utf8)
+        }
+    }
+
     pub fn read_headers<S: TryStream>(
         &mut self,
-        mut stream: Pin<&mut PushChunk<S, S::Ok>>,
+        stream: Pin<&mut PushChunk<S, S::Ok>>,
         cx: &mut Context,
     ) -> Poll<crate::server::Result<FieldHeaders, S::Error>>
     where
         S::Ok: BodyChunk,
     {
-        let map_err = Error::<S::Error>::parsing;
+        let res = ready!(self.read_block(stream, cx, Self::parse)?);
+        Ready(res.map_err(ParseError::with_stream_error::<S::Error>))
+    }
+
+    /// Read the header block of a field, up to and including the double-CRLF, without parsing it
+    pub fn read_raw_headers<S: TryStream>(
+        &mut self,
+        stream: Pin<&mut PushChunk<S, S::Ok>>,
+        cx: &mut Context,
+    ) -> Poll<crate::server::Result<Bytes, S::Error>>
+    where
+        S::Ok: BodyChunk,
+    {
+        self.read_block(stream, cx, |_, bytes| Bytes::copy_from_slice(bytes))
+    }
 
+    /// Accumulate the header block and pass it to `f` once the double-CRLF is found
+    fn read_block<S: TryStream, T>(
+        &mut self,
+        mut stream: Pin<&mut PushChunk<S, S::Ok>>,
+        cx: &mut Context,
+        f: impl FnOnce(&Self, &[u8]) -> T,
+    ) -> Poll<crate::server::Result<T, S::Error>>
+    where
+        S::Ok: BodyChunk,
+    {
         loop {
             trace!(
                 "read_headers state: accumulator: {}",
@@ -107,15 +269,29 @@ impl ReadHeaders {
             let chunk = match ready!(stream.as_mut().poll_next(cx)?) {
                 Some(chunk) => chunk,
                 None => ret_err!(
-                    "unexpected end of stream while reading headers: \"{}\"",
+                    UnexpectedEof: "unexpected end of stream while reading headers: \"{}\"",
                     show_bytes(self.accumulator.as_slice())
                 ),
             };
 
             trace!("got chunk for headers: {}", show_bytes(chunk.as_slice()));
 
-            // End of the headers section is signalled by a double-CRLF
-            if let Some(header_end) = twoway::find_bytes(chunk.as_slice(), b"\r\n\r\n") {
+            // The double-CRLF may be split between the accumulator and this chunk; this has to be
+            // checked first as it comes before any double-CRLF found within the chunk itself
+            if let Some(split_idx) = header_end_split(&self.accumulator, &chunk) {
+                let (head, tail) = chunk.split_into(split_idx);
+                self.accumulator.extend_from_slice(head.as_slice());
+
+                if !tail.is_empty() {
+                    stream.as_mut().push_chunk(tail);
+                }
+
+                let ret = f(self, &self.accumulator);
+                self.accumulator.clear();
+
+                return ready_ok(ret);
+            } else if let Some(header_end) = memmem::find(chunk.as_slice(), b"\r\n\r\n") {
+                // End of the headers section is signalled by a double-CRLF
                 // Split after the double-CRLF because we don't want to yield it and httparse expects it
                 let (headers, rem) = chunk.split_into(header_end + 4);
 
@@ -125,29 +301,21 @@ impl ReadHeaders {
 
                 if !self.accumulator.is_empty() {
                     self.accumulator.extend_from_slice(headers.as_slice());
-                    let headers = parse_headers(&self.accumulator).map_err(map_err)?;
+                    let ret = f(self, &self.accumulator);
                     self.accumulator.clear();
 
-                    return ready_ok(headers);
+                    return ready_ok(ret);
                 } else {
-                    return ready_ok(parse_headers(headers.as_slice()).map_err(map_err)?);
+                    return ready_ok(f(self, headers.as_slice()));
                 }
-            } else if let Some(split_idx) = header_end_split(&self.accumulator, chunk.as_slice()) {
-                let (head, tail) = chunk.split_into(split_idx);
-                self.accumulator.extend_from_slice(head.as_slice());
-
-                if !tail.is_empty() {
-                    stream.as_mut().push_chunk(tail);
-                }
-
-                let headers = parse_headers(&self.accumulator).map_err(map_err)?;
-                self.accumulator.clear();
-
-                return ready_ok(headers);
             }
 
-            if self.accumulator.len().saturating_add(chunk.len()) > MAX_BUF_LEN {
-                ret_err!("headers section too long or trailing double-CRLF missing");
+            if self.accumulator.len().saturating_add(chunk.len()) > self.max_len {
+                ret_err!(
+                    HeadersTooLong: "headers section longer than the limit of {} bytes \
+                     or trailing double-CRLF missing",
+                    self.max_len
+                );
             }
 
             self.accumulator.extend_from_slice(chunk.as_slice());
@@ -159,28 +327,85 @@ const CRLF2: &[u8] = b"\r\n\r\n";
 
 /// Check if the double-CRLF falls between chunk boundaries, and if so, the split index of
 /// the second boundary
-fn header_end_split(first: &[u8], second: &[u8]) -> Option<usize> {
-    fn split_subcheck(start: usize, first: &[u8], second: &[u8]) -> bool {
-        first.len() >= start
-            && first[first.len() - start..]
-                .iter()
-                .chain(second)
-                .take(4)
-                .eq(CRLF2)
-    }
-
-    if split_subcheck(3, first, second) {
-        Some(1)
-    } else if split_subcheck(2, first, second) {
-        Some(2)
-    } else if split_subcheck(1, first, second) {
-        Some(3)
-    } else {
-        None
+fn header_end_split<B: BodyChunk>(first: &[u8], second: &B) -> Option<usize> {
+    (1..CRLF2.len())
+        .rev()
+        .find(|&start| first.ends_with(&CRLF2[..start]) && second.starts_with(&CRLF2[start..]))
+        .map(|start| CRLF2.len() - start)
+}
+
+/// Remove whitespace between header names and the following colon, e.g.
+/// `Content-Disposition : form-data`, which `httparse` rejects as RFC 7230 requires.
+///
+/// Continuation lines (starting with whitespace) are left alone.
+fn trim_header_names(bytes: &[u8]) -> Cow<'_, [u8]> {
+    fn is_ws(b: &u8) -> bool {
+        *b == b' ' || *b == b'\t'
+    }
+
+    fn needs_trim(line: &[u8]) -> Option<(usize, usize)> {
+        if line.is_empty() || is_ws(&line[0]) {
+            return None;
+        }
+
+        let colon = line.iter().position(|&b| b == b':')?;
+        let name_end = line[..colon].iter().rposition(|b| !is_ws(b))? + 1;
+
+        if name_end < colon {
+            Some((name_end, colon))
+        } else {
+            None
+        }
+    }
+
+    if !bytes
+        .split(|&b| b == b'\n')
+        .any(|line| needs_trim(line).is_some())
+    {
+        return Cow::Borrowed(bytes);
+    }
+
+    let mut out = Vec::with_capacity(bytes.len());
+
+    for (i, line) in bytes.split(|&b| b == b'\n').enumerate() {
+        if i > 0 {
+            out.push(b'\n');
+        }
+
+        match needs_trim(line) {
+            Some((name_end, colon)) => {
+                out.extend_from_slice(&line[..name_end]);
+                out.extend_from_slice(&line[colon..]);
+            }
+            None => out.extend_from_slice(line),
+        }
     }
+
+    Cow::Owned(out)
+}
+
+type ParseError = Error<Infallible>;
+
+fn too_many_headers(bytes: &[u8]) -> ParseError {
+    Error::HeadersTooLong(
+        format!(
+            "too many headers on field (the limit is {}): {}",
+            MAX_HEADERS,
+            show_bytes(bytes)
+        )
+        .into(),
+    )
+}
+
+fn invalid_header(msg: impl Into<Cow<'static, str>>) -> ParseError {
+    Error::InvalidHeader(msg.into())
+}
+
+fn missing_cont_disp(msg: String) -> ParseError {
+    Error::MissingContentDisposition(msg.into())
 }
 
-fn parse_headers(bytes: &[u8]) -> Result<FieldHeaders, String> {
+fn parse_headers(bytes: &[u8], lossy_utf8: bool) -> Result<FieldHeaders, ParseError> {
     debug_assert!(
         bytes.ends_with(b"\r\n\r\n"),
         "header byte sequence does not end with `\\r\\n\\r\\n`: {}",
@@ -191,15 +416,21 @@ fn parse_headers(bytes: &[u8]) -> Result<FieldHeaders, String> {
 
     let headers = match httparse::parse_headers(bytes, &mut header_buf) {
         Ok(Status::Complete((_, headers))) => headers,
+        // older versions of `httparse` return `Partial` when they run out of header slots
+        Ok(Status::Partial) if bytes.ends_with(b"\r\n\r\n") => return Err(too_many_headers(bytes)),
         Ok(Status::Partial) => {
-            return Err(format!("field headers incomplete: {}", show_bytes(bytes)))
+            return Err(invalid_header(format!(
+                "field headers incomplete: {}",
+                show_bytes(bytes)
+            )))
         }
+        Err(httparse::Error::TooManyHeaders) => return Err(too_many_headers(bytes)),
         Err(e) => {
-            return Err(format!(
+            return Err(invalid_header(format!(
                 "error parsing headers: {}; from buffer: {}",
                 e,
                 show_bytes(bytes)
-            ))
+            )))
         }
     };
 
@@ -212,20 +443,22 @@ fn parse_headers(bytes: &[u8]) -> Result<FieldHeaders, String> {
     for header in headers {
         if "Content-Disposition".eq_ignore_ascii_case(header.name) {
             if !out_headers.name.is_empty() {
-                return Err(format!(
+                return Err(invalid_header(format!(
                     "duplicate `Content-Disposition` header on field: {}",
                     out_headers.name
-                ));
+                )));
             }
 
             let str_val = str::from_utf8(header.value)
                 .map_err(|_| {
-                    "multipart `Content-Disposition` header values \
-                     must be UTF-8 encoded"
+                    invalid_header(
+                        "multipart `Content-Disposition` header values \
+                         must be UTF-8 encoded",
+                    )
                 })?
                 .trim();
 
-            parse_cont_disp_val(str_val, &mut out_headers)?;
+            parse_cont_disp_val(str_val, lossy_utf8, &mut out_headers).map_err(invalid_header)?;
         } else if "Content-Type".eq_ignore_ascii_case(header.name) {
             if out_headers.content_type.is_some() {
                 // try to get the field name from `Content-Disposition` first
@@ -236,23 +469,29 @@ fn parse_headers(bytes: &[u8]) -> Result<FieldHeaders, String> {
 
             let str_val = str::from_utf8(header.value)
                 .map_err(|_| {
-                    "multipart `Content-Type` header values \
-                     must be UTF-8 encoded"
+                    invalid_header(
+                        "multipart `Content-Type` header values \
+                         must be UTF-8 encoded",
+                    )
                 })?
                 .trim();
 
-            out_headers.content_type = Some(
-                str_val
-                    .parse::<Mime>()
-                    .map_err(|_| format!("could not parse MIME type from {:?}", str_val))?,
-            );
+            out_headers.content_type = Some(str_val.parse::<Mime>().map_err(|_| {
+                invalid_header(format!("could not parse MIME type from {:?}", str_val))
+            })?);
         } else {
             let hdr_name = HeaderName::from_bytes(header.name.as_bytes()).map_err(|e| {
-                format!("error on multipart field header \"{}\": {}", header.name, e)
+                invalid_header(format!(
+                    "error on multipart field header \"{}\": {}",
+                    header.name, e
+                ))
             })?;
 
-            let hdr_val = HeaderValue::from_bytes(bytes).map_err(|e| {
-                format!("error on multipart field header \"{}\": {}", header.name, e)
+            let hdr_val = HeaderValue::from_bytes(header.value).map_err(|e| {
+                invalid_header(format!(
+                    "error on multipart field header \"{}\": {}",
+                    header.name, e
+                ))
             })?;
 
             out_headers.ext_headers.append(hdr_name, hdr_val);
@@ -262,37 +501,37 @@ fn parse_headers(bytes: &[u8]) -> Result<FieldHeaders, String> {
     if out_headers.name.is_empty() {
         // missing `name` parameter in a provided `Content-Disposition` is covered separately
         if let Some(filename) = out_headers.filename {
-            return Err(format!(
+            return Err(missing_cont_disp(format!(
                 "missing `Content-Disposition` header on a field \
                  (filename: {}) in this multipart request",
                 filename
-            ));
+            )));
         }
 
         if let Some(content_type) = out_headers.content_type {
-            return Err(format!(
+            return Err(missing_cont_disp(format!(
                 "missing `Content-Disposition` header on a field \
                  (Content-Type: {}) in this multipart request",
                 content_type
-            ));
+            )));
         }
 
-        return Err(format!(
-            "missing `Content-Disposition` header on a field in this multipart request"
+        return Err(missing_cont_disp(
+            "missing `Content-Disposition` header on a field in this multipart request".into(),
         ));
     }
 
     if dupe_cont_type {
-        return Err(format!(
+        return Err(invalid_header(format!(
             "duplicate `Content-Type` header in field: {}",
             out_headers.name
-        ));
+        )));
     }
 
     Ok(out_headers)
 }
 
-fn parse_cont_disp_val(val: &str, out: &mut FieldHeaders) -> Result<(), String> {
+fn parse_cont_disp_val(val: &str, lossy_utf8: bool, out: &mut FieldHeaders) -> Result<(), String> {
     debug!("parse_cont_disp_val({:?})", val);
 
     // Only take the first section, the rest can be in quoted strings that we want to handle
@@ -313,12 +552,22 @@ fn parse_cont_disp_val(val: &str, out: &mut FieldHeaders) -> Result<(), String>
 
     let mut rem = sections.next().unwrap_or("");
 
+    // extended parameters take precedence regardless of order (RFC 6266, Section 4.3)
+    let mut ext_name = None;
+    let mut ext_filename = None;
+
     while let Some((key, val, rest)) = parse_keyval(rem) {
         rem = rest;
 
         match key {
             "name" => out.name = val.to_string(),
             "filename" => out.filename = Some(val.to_string()),
+            "name*" => ext_name = Some(decode_ext_value(key, val, lossy_utf8)?),
+            "filename*" => ext_filename = Some(decode_ext_value(key, val, lossy_utf8)?),
+            #[cfg(feature = "httpdate")]
+            "creation-date" => out.creation_date = parse_date(key, val),
+            #[cfg(feature = "httpdate")]
+            "modification-date" => out.modification_date = parse_date(key, val),
             _ => debug!(
                 "unknown key-value pair in Content-Disposition: {:?} = {:?}",
                 key, val
@@ -326,6 +575,21 @@ fn parse_cont_disp_val(val: &str, out: &mut FieldHeaders) -> Result<(), String>
         }
     }
 
+    // if the charset of an extended parameter isn't supported, fall back to the plain one
+    match ext_name {
+        Some(Ok(name)) => out.name = name,
+        Some(Err(e)) if out.name.is_empty() => return Err(e),
+        Some(Err(e)) => debug!("{}; using `name` instead", e),
+        None => (),
+    }
+
+    match ext_filename {
+        Some(Ok(filename)) => out.filename = Some(filename),
+        Some(Err(e)) if out.filename.is_none() => return Err(e),
+        Some(Err(e)) => debug!("{}; using `filename` instead", e),
+        None => (),
+    }
+
     if out.name.is_empty() {
         return Err(format!(
             "expected 'name' parameter in `Content-Disposition: {}`",
@@ -336,6 +600,93 @@ fn parse_cont_disp_val(val: &str, out: &mut FieldHeaders) -> Result<(), String>
     Ok(())
 }
 
+/// Decode an extended parameter value of the form `charset'language'percent-encoded`
+/// ([RFC 5987, Section 3.2](https://tools.ietf.org/html/rfc5987#section-3.2)).
+///
+/// If `lossy_utf8` is set, invalid UTF-8 is replaced with U+FFFD instead of returning an error.
+///
+/// An unsupported charset isn't fatal as the plain parameter may also be present, so it's
+/// returned as the inner error.
+fn decode_ext_value(
+    key: &str,
+    val: &str,
+    lossy_utf8: bool,
+) -> Result<Result<String, String>, String> {
+    let mut parts = val.splitn(3, '\'');
+
+    let (charset, encoded) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(charset), Some(_lang), Some(encoded)) => (charset, encoded),
+        _ => {
+            return Err(format!(
+                "malformed extended parameter `{}` in `Content-Disposition`: {:?}",
+                key, val
+            ))
+        }
+    };
+
+    let bytes = percent_decode(encoded).ok_or_else(|| {
+        format!(
+            "invalid percent-encoding in `{}` in `Content-Disposition`: {:?}",
+            key, val
+        )
+    })?;
+
+    if charset.eq_ignore_ascii_case("ISO-8859-1") {
+        // every byte maps directly to the codepoint of the same value
+        return Ok(Ok(bytes.into_iter().map(char::from).collect()));
+    }
+
+    if !charset.eq_ignore_ascii_case("UTF-8") {
+        return Ok(Err(format!(
+            "unsupported charset {:?} for `{}` in `Content-Disposition`",
+            charset, key
+        )));
+    }
+
+    if lossy_utf8 {
+        return Ok(Ok(String::from_utf8_lossy(&bytes).into_owned()));
+    }
+
+    String::from_utf8(bytes).map(Ok).map_err(|e| {
+        format!(
+            "invalid UTF-8 in `{}` in `Content-Disposition`: {:?} ({})",
+            key,
+            val,
+            e.utf8_error()
+        )
+    })
+}
+
+#[cfg(feature = "httpdate")]
+fn parse_date(key: &str, val: &str) -> Option<HttpDate> {
+    val.parse()
+        .map_err(|_| {
+            debug!(
+                "unrecognized date in `{}` in `Content-Disposition`: {:?}",
+                key, val
+            )
+        })
+        .ok()
+}
+
+fn percent_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut bytes = input.bytes();
+
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            out.push(b);
+            continue;
+        }
+
+        let hi = char::from(try_opt!(bytes.next())).to_digit(16)?;
+        let lo = char::from(try_opt!(bytes.next())).to_digit(16)?;
+        out.push((hi * 16 + lo) as u8);
+    }
+
+    Some(out)
+}
+
 fn parse_keyval(input: &str) -> Option<(&str, &str, &str)> {
     if input.trim().is_empty() {
         return None;
@@ -383,13 +734,83 @@ fn param_val(input: &str) -> Option<(&str, &str)> {
     Some((qstr, rem))
 }
 
+#[test]
+fn test_content_type_essence() {
+    let headers = |content_type: Option<&str>| FieldHeaders {
+        content_type: content_type.map(|ct| ct.parse().unwrap()),
+        ..FieldHeaders::default()
+    };
+
+    assert_eq!(
+        headers(Some("text/plain; charset=utf-8")).content_type_essence(),
+        Some("text/plain")
+    );
+    assert_eq!(
+        headers(Some("Image/PNG")).content_type_essence(),
+        Some("image/png")
+    );
+    assert_eq!(
+        headers(Some("multipart/mixed; boundary=\"a; b\"")).content_type_essence(),
+        Some("multipart/mixed")
+    );
+    assert_eq!(headers(None).content_type_essence(), None);
+}
+
+#[test]
+fn test_is_file() {
+    let headers = |filename: Option<&str>, content_type: Option<Mime>| FieldHeaders {
+        filename: filename.map(Into::into),
+        content_type,
+        ..FieldHeaders::default()
+    };
+
+    assert!(!headers(None, None).is_file());
+    assert!(!headers(None, Some(mime::TEXT_PLAIN_UTF_8)).is_file());
+    assert!(headers(Some("file.txt"), None).is_file());
+    assert!(headers(Some("file.txt"), Some(mime::TEXT_PLAIN)).is_file());
+    assert!(headers(Some(""), None).is_file());
+    assert!(headers(None, Some(mime::IMAGE_PNG)).is_file());
+    assert!(headers(None, Some(mime::APPLICATION_OCTET_STREAM)).is_file());
+}
+
+#[cfg(feature = "mime_guess")]
+#[test]
+fn test_guess_extension() {
+    let headers = |content_type: Option<Mime>| FieldHeaders {
+        content_type,
+        ..FieldHeaders::default()
+    };
+
+    assert_eq!(
+        headers(Some(mime::IMAGE_PNG)).guess_extension(),
+        Some("png")
+    );
+    assert_eq!(
+        headers(Some(mime::IMAGE_JPEG)).guess_extension(),
+        Some("jpeg")
+    );
+    assert_eq!(
+        headers(Some(mime::APPLICATION_PDF)).guess_extension(),
+        Some("pdf")
+    );
+    assert_eq!(
+        headers(Some("application/x-unknown".parse().unwrap())).guess_extension(),
+        None
+    );
+    assert_eq!(headers(None).guess_extension(), None);
+}
+
 #[test]
 fn test_header_end_split() {
+    let header_end_split = |first: &[u8], second: &[u8]| header_end_split(first, &second);
+
     assert_eq!(header_end_split(b"\r\n\r", b"\n"), Some(1));
     assert_eq!(header_end_split(b"\r\n", b"\r\n"), Some(2));
     assert_eq!(header_end_split(b"\r", b"\n\r\n"), Some(3));
     assert_eq!(header_end_split(b"\r\n\r\n", b"FOOBAR"), None);
     assert_eq!(header_end_split(b"FOOBAR", b"\r\n\r\n"), None);
+    // the rest of the double-CRLF isn't in this chunk yet
+    assert_eq!(header_end_split(b"\r\n", b"\r"), None);
 }
 
 #[test]
@@ -419,7 +840,10 @@ fn test_parse_keyval() {
 #[test]
 fn test_parse_headers() {
     assert_eq!(
-        parse_headers(b"Content-Disposition: form-data; name = \"field\"\r\n\r\n"),
+        parse_headers(
+            b"Content-Disposition: form-data; name = \"field\"\r\n\r\n",
+            false
+        ),
         Ok(FieldHeaders {
             name: "field".into(),
             ..FieldHeaders::default()
@@ -429,7 +853,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = \"field\"\r\n\
-                        Content-Type: application/octet-stream\r\n\r\n"
+                        Content-Type: application/octet-stream\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -441,7 +866,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = \"field\"\r\n\
-                        Content-Type: text/plain; charset=\"utf-8\"\r\n\r\n"
+                        Content-Type: text/plain; charset=\"utf-8\"\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -452,7 +878,10 @@ fn test_parse_headers() {
 
     // lowercase
     assert_eq!(
-        parse_headers(b"content-disposition: form-data; name = \"field\"\r\n\r\n"),
+        parse_headers(
+            b"content-disposition: form-data; name = \"field\"\r\n\r\n",
+            false
+        ),
         Ok(FieldHeaders {
             name: "field".into(),
             ..FieldHeaders::default()
@@ -462,7 +891,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"content-disposition: form-data; name = \"field\"\r\n\
-                        content-type: application/octet-stream\r\n\r\n"
+                        content-type: application/octet-stream\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -473,7 +903,10 @@ fn test_parse_headers() {
 
     // mixed case
     assert_eq!(
-        parse_headers(b"cOnTent-dIsPosition: form-data; name = \"field\"\r\n\r\n"),
+        parse_headers(
+            b"cOnTent-dIsPosition: form-data; name = \"field\"\r\n\r\n",
+            false
+        ),
         Ok(FieldHeaders {
             name: "field".into(),
             ..FieldHeaders::default()
@@ -483,7 +916,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"contEnt-disPosition: form-data; name = \"field\"\r\n\
-                        coNtent-tyPe: application/octet-stream\r\n\r\n"
+                        coNtent-tyPe: application/octet-stream\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -494,7 +928,10 @@ fn test_parse_headers() {
 
     // omitted quotes
     assert_eq!(
-        parse_headers(b"Content-Disposition: form-data; name = field\r\n\r\n"),
+        parse_headers(
+            b"Content-Disposition: form-data; name = field\r\n\r\n",
+            false
+        ),
         Ok(FieldHeaders {
             name: "field".into(),
             ..FieldHeaders::default()
@@ -504,7 +941,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = field\r\n\
-                        Content-Type: application/octet-stream\r\n\r\n"
+                        Content-Type: application/octet-stream\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -516,7 +954,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = field\r\n\
-                        Content-Type: text/plain; charset=utf-8\r\n\r\n"
+                        Content-Type: text/plain; charset=utf-8\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -529,7 +968,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = field; filename = file.bin\r\n\
-                        Content-Type: application/octet-stream\r\n\r\n"
+                        Content-Type: application/octet-stream\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -543,7 +983,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Type: application/octet-stream\r\n\
-                        Content-Disposition: form-data; name = field; filename = file.bin\r\n\r\n"
+                        Content-Disposition: form-data; name = field; filename = file.bin\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -557,7 +998,8 @@ fn test_parse_headers() {
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = field; x-attr = \"some;value\"; \
-                        filename = file.bin\r\n\r\n"
+                        filename = file.bin\r\n\r\n",
+            false
         ),
         Ok(FieldHeaders {
             name: "field".into(),
@@ -572,19 +1014,62 @@ fn test_parse_headers() {
 fn test_parse_headers_errors() {
     // missing content-disposition
     assert_eq!(
-        parse_headers(b"Content-Type: application/octet-stream\r\n\r\n").unwrap_err(),
-        "missing `Content-Disposition` header on a field \
-         (Content-Type: application/octet-stream) in this multipart request"
+        parse_headers(b"Content-Type: application/octet-stream\r\n\r\n", false).unwrap_err(),
+        Error::MissingContentDisposition(
+            "missing `Content-Disposition` header on a field \
+             (Content-Type: application/octet-stream) in this multipart request"
+                .into()
+        )
     );
 
     // duplicate content-disposition
     assert_eq!(
         parse_headers(
             b"Content-Disposition: form-data; name = field\r\n\
-                        Content-Disposition: form-data; name = field2\r\n\r\n"
+                        Content-Disposition: form-data; name = field2\r\n\r\n",
+            false
         )
         .unwrap_err(),
-        "duplicate `Content-Disposition` header on field: field"
+        Error::InvalidHeader("duplicate `Content-Disposition` header on field: field".into())
+    );
+}
+
+#[test]
+fn test_parse_headers_too_many() {
+    assert_eq!(MAX_HEADERS, 4);
+
+    let err = parse_headers(
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\
+          Content-Type: text/plain\r\n\
+          X-One: 1\r\n\
+          X-Two: 2\r\n\
+          X-Three: 3\r\n\r\n",
+        false,
+    )
+    .unwrap_err();
+
+    match err {
+        Error::HeadersTooLong(msg) => assert!(msg.starts_with("too many headers"), "{}", msg),
+        err => panic!("unexpected error: {}", err),
+    }
+}
+
+#[test]
+fn test_ext_header_names_lowercased() {
+    let headers = parse_headers(
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\
+          X-Custom-Header: Some Value\r\n\r\n",
+        false,
+    )
+    .unwrap();
+
+    let names: Vec<&str> = headers.ext_headers.keys().map(HeaderName::as_str).collect();
+    assert_eq!(names, ["x-custom-header"]);
+
+    // the value is kept as sent
+    assert_eq!(
+        headers.get_ext(&HeaderName::from_static("x-custom-header")),
+        Some(&HeaderValue::from_static("Some Value"))
     );
 }
 
@@ -613,3 +1098,243 @@ fn test_read_headers() {
     assert_eq!(headers.ext_headers, HeaderMap::new());
     assert!(read_headers.accumulator.is_empty());
 }
+
+#[test]
+fn test_read_headers_pending() {
+    use crate::test_util::mock_stream;
+    use futures_test::task::noop_context;
+
+    const HEADERS: &[u8] =
+        b"Content-Disposition: form-data; name=\"foo\"; filename=\"foo.txt\"\r\n\
+        Content-Type: text/plain\r\n\r\n";
+
+    // one byte per chunk, with `Pending` returned before each
+    let mut chunks: Vec<&[u8]> = HEADERS.chunks(1).collect();
+    chunks.push(b"field data");
+
+    let stream = PushChunk::new(mock_stream(&chunks));
+    pin_mut!(stream);
+
+    let mut read_headers = ReadHeaders::default();
+    let mut cx = noop_context();
+    let mut pending = 0;
+
+    let headers = loop {
+        match read_headers.read_headers(stream.as_mut(), &mut cx) {
+            Ready(res) => break res.unwrap(),
+            Pending => {
+                // every byte received so far is kept while waiting for the next chunk
+                assert_eq!(read_headers.accumulator, &HEADERS[..pending]);
+                pending += 1;
+            }
+        }
+    };
+
+    assert_eq!(pending, HEADERS.len());
+    assert_eq!(headers.name, "foo");
+    assert_eq!(headers.filename.as_deref(), Some("foo.txt"));
+    assert_eq!(headers.content_type, Some(mime::TEXT_PLAIN));
+    assert!(read_headers.accumulator.is_empty());
+
+    // the data following the headers is left in the stream
+    let next = until_ready!(|cx| stream.as_mut().poll_next(cx));
+    assert_eq!(next, Some(Ok(&b"field data"[..])));
+}
+
+#[test]
+fn test_ext_filename_utf8() {
+    const HEADERS: &[u8] = b"Content-Disposition: form-data; name=\"foo\"; \
+        filename=\"fallback.txt\"; filename*=UTF-8''%E2%82%AC%20rates.txt\r\n\r\n";
+
+    let headers = parse_headers(HEADERS, false).unwrap();
+    assert_eq!(headers.name, "foo");
+    assert_eq!(headers.filename.as_ref().unwrap(), "\u{20AC} rates.txt");
+}
+
+#[test]
+fn test_ext_filename_invalid_utf8() {
+    const HEADERS: &[u8] = b"Content-Disposition: form-data; name=\"foo\"; \
+        filename*=UTF-8''%FFfoo.txt\r\n\r\n";
+
+    let err = parse_headers(HEADERS, false).unwrap_err();
+    assert!(matches!(err, Error::InvalidHeader(_)), "{}", err);
+    assert!(
+        err.to_string().contains("invalid UTF-8 in `filename*`"),
+        "{}",
+        err
+    );
+
+    let headers = parse_headers(HEADERS, true).unwrap();
+    assert_eq!(headers.filename.as_ref().unwrap(), "\u{FFFD}foo.txt");
+}
+
+#[test]
+fn test_ext_filename_unsupported_charset() {
+    const HEADERS: &[u8] = b"Content-Disposition: form-data; name=\"foo\"; \
+        filename*=Shift_JIS''%83%65%83%58%83%67.txt; filename=\"fallback.txt\"\r\n\r\n";
+
+    let headers = parse_headers(HEADERS, false).unwrap();
+    assert_eq!(headers.filename.as_deref(), Some("fallback.txt"));
+
+    const NO_FALLBACK: &[u8] = b"Content-Disposition: form-data; name=\"foo\"; \
+        filename*=Shift_JIS''%83%65%83%58%83%67.txt\r\n\r\n";
+
+    let err = parse_headers(NO_FALLBACK, false).unwrap_err();
+    assert!(matches!(err, Error::InvalidHeader(_)), "{}", err);
+    assert!(
+        err.to_string()
+            .contains("unsupported charset \"Shift_JIS\""),
+        "{}",
+        err
+    );
+}
+
+#[cfg(feature = "httpdate")]
+#[test]
+fn test_disposition_dates() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const HEADERS: &[u8] = b"Content-Disposition: form-data; name=\"foo\"; filename=\"foo.txt\"; \
+        creation-date=\"Wed, 12 Feb 1997 16:29:51 GMT\"; \
+        modification-date=\"Wed, 12 Feb 1997 16:29:51 -0500\"\r\n\r\n";
+
+    let headers = parse_headers(HEADERS, false).unwrap();
+    assert_eq!(
+        headers.creation_date,
+        Some(HttpDate::from(
+            UNIX_EPOCH + Duration::from_secs(855_764_991)
+        ))
+    );
+    // numeric timezones aren't supported
+    assert_eq!(headers.modification_date, None);
+}
+
+#[test]
+fn test_space_before_colon() {
+    use crate::test_util::mock_stream;
+
+    const HEADERS: &[u8] = b"Content-Disposition : form-data; name=\"foo\"\r\n\
+        Content-Type\t: text/plain\r\n\r\n";
+
+    fn read(lenient: bool) -> Result<FieldHeaders, Error<std::convert::Infallible>> {
+        let stream = PushChunk::new(mock_stream(&[HEADERS]));
+        pin_mut!(stream);
+
+        let mut read_headers = ReadHeaders {
+            lenient_header_names: lenient,
+            ..ReadHeaders::default()
+        };
+
+        until_ready!(|cx| read_headers.read_headers(stream.as_mut(), cx))
+    }
+
+    let err = read(false).unwrap_err();
+    assert!(err.is_client_error());
+    assert!(err.to_string().contains("invalid header name"), "{}", err);
+
+    let headers = read(true).unwrap();
+    assert_eq!(headers.name, "foo");
+    assert_eq!(headers.content_type, Some(mime::TEXT_PLAIN));
+
+    // nothing to trim
+    let normal = b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n";
+    assert!(matches!(trim_header_names(normal), Cow::Borrowed(_)));
+}
+
+#[test]
+fn test_no_space_after_colon() {
+    use crate::test_util::mock_stream;
+
+    const HEADERS: &[u8] = b"Content-Disposition:form-data; name=\"foo\"; filename=\"bar.txt\"\r\n\
+        Content-Type:text/plain\r\n\r\n";
+
+    // valid as the whitespace after the colon is optional, so accepted in either mode
+    for &lenient in &[false, true] {
+        let stream = PushChunk::new(mock_stream(&[HEADERS]));
+        pin_mut!(stream);
+
+        let mut read_headers = ReadHeaders {
+            lenient_header_names: lenient,
+            ..ReadHeaders::default()
+        };
+
+        let res: Result<FieldHeaders, Error<std::convert::Infallible>> =
+            until_ready!(|cx| read_headers.read_headers(stream.as_mut(), cx));
+        let headers = res.unwrap_or_else(|e| panic!("lenient: {}, {}", lenient, e));
+        assert_eq!(headers.name, "foo");
+        assert_eq!(headers.filename.as_deref(), Some("bar.txt"));
+        assert_eq!(headers.content_type, Some(mime::TEXT_PLAIN));
+    }
+}
+
+#[test]
+fn test_read_headers_limit() {
+    use crate::test_util::mock_stream;
+
+    let long_filename = "a".repeat(MAX_BUF_LEN);
+    let cont_disp = format!(
+        "Content-Disposition: form-data; name=\"foo\"; filename=\"{}\"\r\n",
+        long_filename
+    );
+    let chunks = [cont_disp.as_bytes(), b"\r\n"];
+
+    let read = |max_len| -> Result<FieldHeaders, Error<std::convert::Infallible>> {
+        let stream = PushChunk::new(mock_stream(&chunks));
+        pin_mut!(stream);
+
+        let mut read_headers = ReadHeaders {
+            max_len,
+            ..ReadHeaders::default()
+        };
+
+        until_ready!(|cx| read_headers.read_headers(stream.as_mut(), cx))
+    };
+
+    let err = read(MAX_BUF_LEN).unwrap_err();
+    assert!(matches!(err, Error::HeadersTooLong(_)), "{}", err);
+    assert!(err.to_string().contains("limit of 1024 bytes"), "{}", err);
+
+    let headers = read(4096).unwrap();
+    assert_eq!(headers.filename.unwrap(), long_filename);
+}
+
+#[test]
+fn test_content_transfer_encoding() {
+    let headers = parse_headers(
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\
+          Content-Transfer-Encoding: BASE64\r\n\
+          X-Custom: value\r\n\r\n",
+        false,
+    )
+    .unwrap();
+
+    assert_eq!(headers.content_transfer_encoding().unwrap(), "base64");
+    assert_eq!(
+        headers.get_ext(&HeaderName::from_static("x-custom")),
+        Some(&HeaderValue::from_static("value"))
+    );
+    // the raw value is left as-is
+    assert_eq!(
+        headers.get_ext(&HeaderName::from_static("content-transfer-encoding")),
+        Some(&HeaderValue::from_static("BASE64"))
+    );
+
+    let headers = parse_headers(
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\
+          Content-Transfer-Encoding: base64\r\n\r\n",
+        false,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        headers.content_transfer_encoding(),
+        Some(Cow::Borrowed("base64"))
+    ));
+
+    let headers = parse_headers(
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+        false,
+    )
+    .unwrap();
+    assert_eq!(headers.content_transfer_encoding(), None);
+}
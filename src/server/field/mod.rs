@@ -9,9 +9,10 @@ use std::fmt;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::Poll::{self, *};
-use std::{mem, str};
+use std::{io, mem, str};
 
 use futures_core::{Future, Stream, TryStream};
+use futures_io::{AsyncBufRead, AsyncRead, AsyncWrite};
 //pub use self::collect::{ReadTextField, TextField};
 use futures_core::task::Context;
 
@@ -22,11 +23,17 @@ use crate::BodyChunk;
 use super::boundary::BoundaryFinder;
 use super::Multipart;
 
+#[cfg(feature = "charset")]
+pub use self::charset::ReadToStringWithCharset;
 pub use self::headers::FieldHeaders;
 pub(crate) use self::headers::ReadHeaders;
+pub use self::transfer::DecodedFieldData;
 
+#[cfg(feature = "charset")]
+mod charset;
 // mod collect;
 mod headers;
+mod transfer;
 
 /// A `Future` potentially yielding the next field in the multipart stream.
 ///
@@ -35,20 +42,16 @@ mod headers;
 /// See [`Multipart::next_field()`](../struct.Multipart.html#method.next_field) for usage.
 pub struct NextField<'a, S: TryStream + 'a> {
     multipart: Option<Pin<&'a mut Multipart<S>>>,
-    has_next_field: bool,
+    state: PollNextField,
 }
 
 impl<'a, S: TryStream + 'a> NextField<'a, S> {
     pub(crate) fn new(multipart: Pin<&'a mut Multipart<S>>) -> Self {
         NextField {
             multipart: Some(multipart),
-            has_next_field: false,
+            state: PollNextField::default(),
         }
     }
-
-    fn multipart(&mut self) -> Option<Pin<&mut Multipart<S>>> {
-        Some(self.multipart.as_mut()?.as_mut())
-    }
 }
 
 impl<'a, S: 'a> Future for NextField<'a, S>
@@ -60,40 +63,100 @@ where
     type Output = super::Result<Option<Field<'a, S>>, S::Error>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        // since we can't use `?` with `Option<...>` in this context
-        macro_rules! multipart {
-            (get) => {
-                if let Some(ref mut multipart) = self.multipart {
-                    multipart.as_mut()
-                } else {
+        let this = &mut *self;
+
+        let multipart = match this.multipart {
+            Some(ref mut multipart) => multipart.as_mut(),
+            None => return Ready(Ok(None)),
+        };
+
+        match ready!(this.state.poll(multipart, cx)?) {
+            Some(headers) => Ready(Ok(Some(Field {
+                headers,
+                data: FieldData {
+                    multipart: this.multipart.take().expect("checked above"),
+                },
+                _priv: (),
+            }))),
+            None => {
+                // end of stream
+                this.multipart = None;
+                Ready(Ok(None))
+            }
+        }
+    }
+}
+
+/// A `_charset_` field only needs to hold a charset label
+const MAX_CHARSET_LEN: usize = 64;
+
+/// The state of polling for the headers of the next field, shared by `NextField`,
+/// `MultipartStream` and `CollectFields`.
+#[derive(Default)]
+pub(crate) struct PollNextField {
+    has_next_field: bool,
+    /// The value of a `_charset_` field being captured
+    charset: Option<Vec<u8>>,
+}
+
+impl PollNextField {
+    /// Yields `None` at the end of the request; may be polled again for the following field
+    /// after yielding `Some`.
+    pub(crate) fn poll<S>(
+        &mut self,
+        mut multipart: Pin<&mut Multipart<S>>,
+        cx: &mut Context,
+    ) -> Poll<super::Result<Option<FieldHeaders>, S::Error>>
+    where
+        S: TryStream,
+        S::Ok: BodyChunk,
+        Error<S::Error>: From<S::Error>,
+    {
+        loop {
+            // `false` means we haven't polled for next field yet
+            if !self.has_next_field {
+                if !ready!(multipart.as_mut().poll_has_next_field(cx)?) {
                     return Ready(Ok(None));
                 }
-            };
-            (take) => {
-                if let Some(multipart) = self.multipart.take() {
-                    multipart
-                } else {
-                    return Ready(Ok(None));
+
+                self.has_next_field = true;
+            }
+
+            if self.charset.is_none() {
+                let headers = ready!(multipart.as_mut().poll_field_headers(cx)?);
+
+                if !(multipart.capture_charset && headers.name == "_charset_") {
+                    self.has_next_field = false;
+                    return Ready(Ok(Some(headers)));
+                }
+
+                self.charset = Some(Vec::new());
+            }
+
+            while let Some(chunk) = ready!(multipart.as_mut().poll_field_chunk(cx)?) {
+                let charset = self.charset.as_mut().expect("not capturing `_charset_`");
+
+                if charset.len() + chunk.len() > MAX_CHARSET_LEN {
+                    ret_err!(
+                        "`_charset_` field longer than the limit of {} bytes",
+                        MAX_CHARSET_LEN
+                    );
                 }
+
+                charset.extend_from_slice(chunk.as_slice());
+            }
+
+            let charset = self.charset.take().expect("not capturing `_charset_`");
+            let charset = match String::from_utf8(charset) {
+                Ok(charset) => charset,
+                Err(_) => ret_err!("`_charset_` field is not valid UTF-8"),
             };
-        }
 
-        // `false` and `multipart = Some` means we haven't polled for next field yet
-        self.has_next_field =
-            self.has_next_field || ready!(multipart!(get).poll_has_next_field(cx)?);
+            debug!("default charset: {:?}", charset);
 
-        if !self.has_next_field {
-            // end of stream, next `?` will return
-            self.multipart = None;
+            *multipart.as_mut().charset_field() = Some(charset.trim().to_string());
+            self.has_next_field = false;
         }
-
-        Ready(Ok(Some(Field {
-            headers: ready!(multipart!(get).poll_field_headers(cx)?),
-            data: FieldData {
-                multipart: multipart!(take),
-            },
-            _priv: (),
-        })))
     }
 }
 
@@ -117,15 +180,48 @@ impl<S: TryStream> fmt::Debug for Field<'_, S> {
     }
 }
 
+/// A field read entirely into memory by
+/// [`Multipart::into_single_field()`](../struct.Multipart.html#method.into_single_field).
+#[derive(Clone, Debug)]
+pub struct BufferedField {
+    /// The headers of this field, including the name, filename, and `Content-Type`, if provided.
+    pub headers: FieldHeaders,
+    /// The data of this field.
+    pub data: Vec<u8>,
+}
+
+/// A field yielded by [`Multipart::next_text_field()`](../struct.Multipart.html#method.next_text_field).
+pub enum TextOrFile<'a, S: TryStream + 'a> {
+    /// A text field, read to a string.
+    Text(FieldHeaders, String),
+    /// A field which is not text, or is in a charset other than UTF-8, to be read as a stream.
+    File(Field<'a, S>),
+}
+
+impl<S: TryStream> fmt::Debug for TextOrFile<'_, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextOrFile::Text(headers, text) => {
+                f.debug_tuple("Text").field(headers).field(text).finish()
+            }
+            TextOrFile::File(field) => f.debug_tuple("File").field(field).finish(),
+        }
+    }
+}
+
 /// The data of a field in a multipart stream, as a stream of chunks.
 ///
 /// It may be read to completion via the `Stream` impl, or collected to a string with
 /// `.read_to_string()`.
+///
+/// It is safe to drop this before the field has been read to the end, e.g. on an early return
+/// from a handler or when a future reading it is cancelled; the next call to
+/// `Multipart::next_field()` will discard the rest of the field before reading the next one.
 pub struct FieldData<'a, S: TryStream + 'a> {
     multipart: Pin<&'a mut Multipart<S>>,
 }
 
-impl<S: TryStream> FieldData<'_, S>
+impl<'a, S: TryStream> FieldData<'a, S>
 where
     S::Ok: BodyChunk,
     Error<S::Error>: From<S::Error>,
@@ -142,9 +238,164 @@ where
     /// use a non-UTF-8 charset, or:
     /// * the field is actually a text file encoded in a charset that is not UTF-8
     /// (most likely Windows-1252 or UTF-16).
+    ///
+    /// To handle these, enable the `charset` feature and use
+    /// [`.read_to_string_with_charset()`](#method.read_to_string_with_charset).
     pub fn read_to_string(self) -> ReadToString<Self> {
         ReadToString::new(self)
     }
+
+    /// Return a `Future` which yields the result of decoding this field's data from `charset`
+    /// to a `String`. Enabled with the `charset` feature.
+    ///
+    /// `charset` is a label as defined by the [WHATWG Encoding Standard][labels], such as the
+    /// value of the `_charset_` field or the `charset` parameter of the field's `Content-Type`;
+    /// matching is case-insensitive. The future yields an error without reading any data if the
+    /// label is unknown, or once the data turns out to be malformed for the charset.
+    ///
+    /// A byte order mark at the start of the data overrides `charset`.
+    ///
+    /// [labels]: https://encoding.spec.whatwg.org/#names-and-labels
+    #[cfg(feature = "charset")]
+    pub fn read_to_string_with_charset(self, charset: &str) -> ReadToStringWithCharset<'a, S> {
+        ReadToStringWithCharset::new(self, charset)
+    }
+
+    /// Return a `Future` which yields this field's data concatenated into a single `Vec<u8>`.
+    ///
+    /// Unbounded by default; call [`.limit()`](struct.CollectVec.html#method.limit) on the
+    /// returned future to error out instead of buffering an arbitrarily large field.
+    pub fn collect_vec(self) -> CollectVec<'a, S> {
+        CollectVec {
+            data: self,
+            buf: Vec::new(),
+            limit: None,
+        }
+    }
+
+    /// Parse this field's data as a nested multipart body. `boundary` is as passed to
+    /// [`Multipart::with_body()`](../struct.Multipart.html#method.with_body), e.g. from the
+    /// field's `Content-Type`; the same rules apply to the parts of the nested body, so each
+    /// must have a `Content-Disposition: form-data` header with a `name`.
+    ///
+    /// If a [`FieldBudget`](../struct.FieldBudget.html) was set on the `Multipart`, the nested
+    /// body shares it, and an error is returned instead if this would nest deeper than the
+    /// budget allows.
+    pub fn into_nested(self, boundary: &str) -> super::Result<Multipart<Self>, S::Error> {
+        let budget = self.multipart.budget.clone();
+        let depth = self.multipart.depth + 1;
+
+        if let Some(ref budget) = budget {
+            if depth > budget.max_depth() {
+                return fmt_err!(
+                    "multipart body exceeds the nesting depth limit of {}",
+                    budget.max_depth()
+                );
+            }
+        }
+
+        let mut nested = Multipart::with_body(self, boundary);
+        nested.budget = budget;
+        nested.depth = depth;
+        Ok(nested)
+    }
+
+    /// The value of the `_charset_` field, if it came before this field and
+    /// [`Multipart::with_charset_field(true)`](../struct.Multipart.html#method.with_charset_field)
+    /// was set.
+    pub fn default_charset(&self) -> Option<&str> {
+        self.multipart.default_charset()
+    }
+
+    /// Like [`.read_to_string_with_charset()`](#method.read_to_string_with_charset) using
+    /// [`.default_charset()`](#method.default_charset), or UTF-8 if there is none.
+    /// Enabled with the `charset` feature.
+    #[cfg(feature = "charset")]
+    pub fn read_to_string_with_default_charset(self) -> ReadToStringWithCharset<'a, S> {
+        let charset = self.default_charset().unwrap_or("utf-8").to_string();
+        ReadToStringWithCharset::new(self, &charset)
+    }
+
+    /// Return a `Future` which writes this field's data to `writer`, yielding the number of
+    /// bytes written.
+    ///
+    /// Each chunk is written as it is read, without buffering the whole field. The writer is
+    /// flushed at the end of the field. Errors from the request are converted to `io::Error`
+    /// with a kind of `Other`.
+    pub fn copy_to<W: AsyncWrite + Unpin>(self, writer: W) -> CopyTo<'a, S, W> {
+        CopyTo {
+            data: self,
+            writer,
+            chunk: None,
+            pos: 0,
+            written: 0,
+        }
+    }
+
+    /// Return a `Stream` which yields the result of applying `f` to each chunk of this field.
+    ///
+    /// Errors are passed through unchanged.
+    pub fn map_chunks<F, B>(self, f: F) -> MapChunks<Self, F>
+    where
+        F: FnMut(S::Ok) -> B,
+    {
+        MapChunks { stream: self, f }
+    }
+
+    /// Return a `Stream` which calls `f` with `state` and the bytes of each chunk of this field
+    /// before yielding the chunk unchanged, e.g. to feed a virus scanner or a hasher as the
+    /// data is read.
+    ///
+    /// The state can be retrieved afterwards with
+    /// [`Scan::into_state()`](struct.Scan.html#method.into_state). Errors are passed through
+    /// unchanged and `f` is not called for them.
+    pub fn scan<T, F>(self, state: T, f: F) -> Scan<Self, T, F>
+    where
+        F: FnMut(&mut T, &[u8]),
+    {
+        Scan {
+            stream: self,
+            state,
+            f,
+        }
+    }
+
+    /// Return a `Stream` which coalesces the chunks of this field, yielding only once at least
+    /// `min_chunk` bytes have been read or the field has ended.
+    ///
+    /// Useful when the underlying stream yields many tiny chunks, such as from a slow connection.
+    ///
+    /// A `min_chunk` of 0 is treated as 1, so every chunk is yielded as it is read.
+    pub fn buffered(self, min_chunk: usize) -> Buffered<Self> {
+        Buffered {
+            stream: self,
+            min_chunk: min_chunk.max(1),
+            buf: Vec::new(),
+        }
+    }
+
+    /// Return a `Stream` which decodes the field data according to the
+    /// `Content-Transfer-Encoding` in `headers`, which should be the headers of this field.
+    ///
+    /// `base64` and `quoted-printable` are decoded, buffering any group or escape that is split
+    /// between chunks. Data with no `Content-Transfer-Encoding`, or `7bit`, `8bit` or `binary`,
+    /// is passed through unchanged. Any other encoding yields an error.
+    ///
+    /// `Content-Transfer-Encoding` is deprecated for `multipart/form-data`
+    /// ([IETF RFC 7578 Section 4.7](https://tools.ietf.org/html/rfc7578#section-4.7))
+    /// but some legacy clients still send it.
+    pub fn decode_transfer_encoding(self, headers: &FieldHeaders) -> DecodedFieldData<'a, S> {
+        DecodedFieldData::new(self, headers)
+    }
+
+    /// Wrap this field's data in a type implementing `AsyncBufRead` (and `AsyncRead`) from
+    /// `futures-io`.
+    ///
+    /// The current chunk is exposed directly by `poll_fill_buf()` without copying.
+    /// Errors are converted to `io::Error` with a kind of `Other`.
+    pub fn into_async_buf_read(self) -> FieldReader<'a, S> {
+        FieldReader { data: self }
+    }
 }
 
 impl<S: TryStream> Stream for FieldData<'_, S>
@@ -159,11 +410,283 @@ where
     }
 }
 
+/// The data of a field exposed as `AsyncBufRead` and `AsyncRead`.
+///
+/// See [`FieldData::into_async_buf_read()`](struct.FieldData.html#method.into_async_buf_read).
+pub struct FieldReader<'a, S: TryStream + 'a> {
+    data: FieldData<'a, S>,
+}
+
+impl<'a, S: TryStream + 'a> FieldReader<'a, S> {
+    /// Unwrap the field data; any chunk buffered but not yet consumed will be yielded first.
+    pub fn into_inner(self) -> FieldData<'a, S> {
+        self.data
+    }
+}
+
+fn to_io_error<E>(e: Error<E>) -> io::Error
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    io::Error::other(e)
+}
+
+impl<S: TryStream> AsyncBufRead for FieldReader<'_, S>
+where
+    S::Ok: BodyChunk,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    Error<S::Error>: From<S::Error>,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context) -> Poll<io::Result<&[u8]>> {
+        self.get_mut()
+            .data
+            .multipart
+            .as_mut()
+            .poll_fill_buf(cx)
+            .map_err(to_io_error)
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.data.multipart.as_mut().consume(amt)
+    }
+}
+
+impl<S: TryStream> AsyncRead for FieldReader<'_, S>
+where
+    S::Ok: BodyChunk,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    Error<S::Error>: From<S::Error>,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let read = {
+            let available = ready!(self.as_mut().poll_fill_buf(cx)?);
+            let read = available.len().min(buf.len());
+            buf[..read].copy_from_slice(&available[..read]);
+            read
+        };
+
+        self.consume(read);
+        Ready(Ok(read))
+    }
+}
+
+/// A `Stream` that transforms the chunks of a field with a closure.
+///
+/// See [`FieldData::map_chunks()`](struct.FieldData.html#method.map_chunks).
+pub struct MapChunks<S, F> {
+    stream: S,
+    f: F,
+}
+
+impl<S, F> MapChunks<S, F> {
+    pub(crate) fn new(stream: S, f: F) -> Self {
+        MapChunks { stream, f }
+    }
+}
+
+// `f` is never pinned
+impl<S: Unpin, F> Unpin for MapChunks<S, F> {}
+
+impl<S, F, B> Stream for MapChunks<S, F>
+where
+    S: TryStream + Unpin,
+    F: FnMut(S::Ok) -> B,
+{
+    type Item = Result<B, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        Pin::new(&mut this.stream)
+            .try_poll_next(cx)
+            .map(|opt| opt.map(|res| res.map(&mut this.f)))
+    }
+}
+
+/// A `Stream` that passes the chunks of a field through a closure with some state before
+/// yielding them.
+///
+/// See [`FieldData::scan()`](struct.FieldData.html#method.scan).
+pub struct Scan<S, T, F> {
+    stream: S,
+    state: T,
+    f: F,
+}
+
+impl<S, T, F> Scan<S, T, F> {
+    /// Get a reference to the state.
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    /// Unwrap the state, e.g. once the stream has ended.
+    pub fn into_state(self) -> T {
+        self.state
+    }
+}
+
+// neither `state` nor `f` is ever pinned
+impl<S: Unpin, T, F> Unpin for Scan<S, T, F> {}
+
+impl<S, T, F> Stream for Scan<S, T, F>
+where
+    S: TryStream + Unpin,
+    S::Ok: BodyChunk,
+    F: FnMut(&mut T, &[u8]),
+{
+    type Item = Result<S::Ok, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let res = ready!(Pin::new(&mut this.stream).try_poll_next(cx));
+
+        if let Some(Ok(ref chunk)) = res {
+            (this.f)(&mut this.state, chunk.as_slice());
+        }
+
+        Ready(res)
+    }
+}
+
+/// A `Stream` that coalesces the chunks of a field.
+///
+/// See [`FieldData::buffered()`](struct.FieldData.html#method.buffered).
+pub struct Buffered<S> {
+    stream: S,
+    min_chunk: usize,
+    buf: Vec<u8>,
+}
+
+impl<S> Stream for Buffered<S>
+where
+    S: TryStream + Unpin,
+    S::Ok: BodyChunk,
+{
+    type Item = Result<Vec<u8>, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        while this.buf.len() < this.min_chunk {
+            match ready!(Pin::new(&mut this.stream).try_poll_next(cx)?) {
+                Some(chunk) => this.buf.extend_from_slice(chunk.as_slice()),
+                None if this.buf.is_empty() => return Ready(None),
+                None => break,
+            }
+        }
+
+        Ready(Some(Ok(mem::take(&mut this.buf))))
+    }
+}
+
+/// A `Future` that yields the data of a field collected into a single `Vec<u8>`.
+///
+/// See [`FieldData::collect_vec()`](struct.FieldData.html#method.collect_vec).
+pub struct CollectVec<'a, S: TryStream + 'a> {
+    data: FieldData<'a, S>,
+    buf: Vec<u8>,
+    limit: Option<usize>,
+}
+
+impl<'a, S: TryStream + 'a> CollectVec<'a, S> {
+    /// Return an error instead of collecting more than `limit` bytes.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+impl<S: TryStream> Future for CollectVec<'_, S>
+where
+    S::Ok: BodyChunk,
+    Error<S::Error>: From<S::Error>,
+{
+    type Output = super::Result<Vec<u8>, S::Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        while let Some(chunk) = ready!(Pin::new(&mut this.data).poll_next(cx)?) {
+            if let Some(limit) = this.limit {
+                if this.buf.len().saturating_add(chunk.len()) > limit {
+                    ret_err!("field data exceeds the limit of {} bytes", limit);
+                }
+            }
+
+            this.buf.extend_from_slice(chunk.as_slice());
+        }
+
+        Ready(Ok(mem::take(&mut this.buf)))
+    }
+}
+
+/// A `Future` that writes the data of a field to an `AsyncWrite`.
+///
+/// See [`FieldData::copy_to()`](struct.FieldData.html#method.copy_to).
+pub struct CopyTo<'a, S: TryStream + 'a, W> {
+    data: FieldData<'a, S>,
+    writer: W,
+    chunk: Option<S::Ok>,
+    pos: usize,
+    written: u64,
+}
+
+// nothing is pinned structurally
+impl<S: TryStream, W: Unpin> Unpin for CopyTo<'_, S, W> {}
+
+impl<S: TryStream, W: AsyncWrite + Unpin> Future for CopyTo<'_, S, W>
+where
+    S::Ok: BodyChunk,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    Error<S::Error>: From<S::Error>,
+{
+    type Output = io::Result<u64>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(ref chunk) = this.chunk {
+                while this.pos < chunk.len() {
+                    let written =
+                        ready!(Pin::new(&mut this.writer).poll_write(cx, chunk.slice(this.pos..)))?;
+
+                    if written == 0 {
+                        return Ready(Err(io::ErrorKind::WriteZero.into()));
+                    }
+
+                    this.pos += written;
+                    this.written += written as u64;
+                }
+
+                this.chunk = None;
+            }
+
+            match ready!(Pin::new(&mut this.data).poll_next(cx)) {
+                Some(Ok(chunk)) => {
+                    this.chunk = Some(chunk);
+                    this.pos = 0;
+                }
+                Some(Err(e)) => return Ready(Err(to_io_error(e))),
+                None => {
+                    ready!(Pin::new(&mut this.writer).poll_flush(cx))?;
+                    return Ready(Ok(this.written));
+                }
+            }
+        }
+    }
+}
+
 /// A `Future` that yields the body of a field read to a `String`.
 pub struct ReadToString<S: TryStream + Unpin> {
     stream: S,
     string: String,
     surrogate: Option<([u8; 3], u8)>,
+    read: usize,
+    limit: Option<usize>,
 }
 
 impl<S: TryStream + Unpin> ReadToString<S> {
@@ -172,8 +695,16 @@ impl<S: TryStream + Unpin> ReadToString<S> {
             stream,
             string: String::new(),
             surrogate: None,
+            read: 0,
+            limit: None,
         }
     }
+
+    /// Return an error instead of reading more than `limit` bytes.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
 }
 
 impl<S: TryStream + Unpin> Future for ReadToString<S>
@@ -185,6 +716,14 @@ where
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
         while let Some(mut data) = ready!(Pin::new(&mut self.stream).try_poll_next(cx)?) {
+            self.read = self.read.saturating_add(data.len());
+
+            if let Some(limit) = self.limit {
+                if self.read > limit {
+                    ret_err!("field data exceeds the limit of {} bytes", limit);
+                }
+            }
+
             if let Some((mut start, start_len)) = self.surrogate {
                 assert!(
                     start_len > 0 && start_len < 4,
@@ -219,13 +758,23 @@ where
                 trace!("decoding surrogate: {:?}", &surrogate[..width]);
 
                 self.string
-                    .push_str(str::from_utf8(&surrogate[..width]).map_err(Utf8)?);
+                    .push_str(str::from_utf8(&surrogate[..width]).map_err(Utf8::<S::Error>)?);
 
                 let (_, rem) = data.split_into(needed);
                 data = rem;
                 self.surrogate = None;
             }
 
+            // most text fields are entirely ASCII, which can be checked a word at a time
+            // without the bookkeeping of full UTF-8 validation
+            if data.as_slice().is_ascii() {
+                self.string.push_str(unsafe {
+                    // ASCII is always valid UTF-8
+                    str::from_utf8_unchecked(data.as_slice())
+                });
+                continue;
+            }
+
             match str::from_utf8(data.as_slice()) {
                 Ok(s) => self.string.push_str(s),
                 Err(e) => {
@@ -278,6 +827,7 @@ fn assert_types_unpin() {
 
     fn inner<'a, S: TryStream + 'a>() {
         assert_unpin::<FieldData<'a, S>>();
+        assert_unpin::<CollectVec<'a, S>>();
     }
 
     // `Unpin` is checked on `ReadToString` in `test_read_to_string()`.
@@ -314,3 +864,293 @@ fn test_read_to_string() {
         Ok("(╯°□°)╯︵ ┻━┻".to_string())
     );
 }
+
+#[test]
+fn test_read_to_string_mixed_ascii() {
+    use crate::test_util::mock_stream;
+    use futures_util::TryFutureExt;
+
+    let _ = ::env_logger::try_init();
+
+    // "price: €5, ¶ done" with ASCII-only chunks between and around split surrogates
+    let test_data = mock_stream(&[
+        b"price: ",
+        &[0xE2, 0x82],
+        &[0xAC, b'5', b',', b' '],
+        &[0xC2],
+        &[0xB6],
+        b" done",
+    ]);
+
+    let mut read_to_string = ReadToString::new(test_data);
+
+    ready_assert_eq!(
+        |cx| read_to_string.try_poll_unpin(cx),
+        Ok("price: \u{20AC}5, \u{B6} done".to_string())
+    );
+
+    // invalid UTF-8 after an ASCII chunk is still caught
+    let test_data = mock_stream(&[b"ascii", &[b'a', 0xFF]]);
+    let mut read_to_string = ReadToString::new(test_data);
+
+    let err = until_ready!(|cx| read_to_string.try_poll_unpin(cx)).unwrap_err();
+    assert!(err.is_client_error(), "{}", err);
+}
+
+#[test]
+fn test_map_chunks() {
+    use crate::test_util::{mock_stream, run_future_hot};
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"field ",
+            b"data",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let chunks: Vec<Vec<u8>> = run_future_hot(
+        field
+            .data
+            .map_chunks(<[u8]>::to_ascii_uppercase)
+            .try_collect(),
+    )
+    .unwrap();
+
+    assert_eq!(chunks, [&b"FIELD "[..], &b"DATA"[..]]);
+}
+
+#[test]
+fn test_scan() {
+    use crate::test_util::{mock_stream, run_future_hot};
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"field ",
+            b"data",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let mut scan = field.data.scan((0usize, 0usize), |(chunks, bytes), chunk| {
+        *chunks += 1;
+        *bytes += chunk.len();
+    });
+
+    // the chunks are forwarded unchanged
+    let mut chunks = Vec::new();
+
+    while let Some(chunk) = run_future_hot(scan.try_next()).unwrap() {
+        chunks.push(chunk);
+    }
+
+    assert_eq!(chunks, [&b"field "[..], &b"data"[..]]);
+    assert_eq!(scan.into_state(), (2, 10));
+}
+
+#[test]
+fn test_collect_vec() {
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"\x00\x01",
+            b"\x02\xFF",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+            b"too ",
+            b"long",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    assert_eq!(
+        run_future_hot(field.data.collect_vec().limit(4)).unwrap(),
+        b"\x00\x01\x02\xFF"
+    );
+
+    // the borrow of `multipart` is released when the future completes
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let err = run_future_hot(field.data.collect_vec().limit(7)).unwrap_err();
+    assert!(err.is_client_error(), "{}", err);
+    assert!(err.to_string().contains("limit of 7 bytes"), "{}", err);
+
+    assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+}
+
+#[test]
+fn test_copy_to() {
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"; filename=\"foo.bin\"\r\n\r\n",
+            b"\x00\x01",
+            b"\r\n\x02",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let mut out = Vec::new();
+    let written = run_future_hot(field.data.copy_to(&mut out)).unwrap();
+
+    assert_eq!(written, 5);
+    assert_eq!(out, b"\x00\x01\r\n\x02");
+}
+
+#[test]
+fn test_async_buf_read() {
+    use crate::test_util::{mock_stream, run_future_hot};
+    use futures::io::AsyncBufReadExt;
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"line one\nline",
+            b" two\n",
+            b"line three",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let mut reader = field.data.into_async_buf_read();
+    let mut lines = Vec::new();
+
+    loop {
+        let mut line = Vec::new();
+
+        if run_future_hot(reader.read_until(b'\n', &mut line)).unwrap() == 0 {
+            break;
+        }
+
+        lines.push(line);
+    }
+
+    assert_eq!(
+        lines,
+        [&b"line one\n"[..], &b"line two\n"[..], &b"line three"[..]]
+    );
+}
+
+#[test]
+fn test_buffered() {
+    use crate::test_util::{mock_stream, run_future_hot};
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    const DATA: &[u8] = b"field data";
+    let mut chunks: Vec<&[u8]> = vec![
+        b"--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+    ];
+    chunks.extend(DATA.chunks(1));
+    chunks.push(b"\r\n--boundary--");
+
+    let mut multipart = Multipart::with_body(mock_stream(&chunks), "boundary");
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let chunks: Vec<Vec<u8>> = run_future_hot(field.data.buffered(4).try_collect()).unwrap();
+
+    assert_eq!(chunks, [&b"fiel"[..], &b"d da"[..], &b"ta"[..]]);
+}
+
+#[test]
+fn test_buffered_zero() {
+    use crate::test_util::{mock_stream, run_future_hot};
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"field ",
+            b"data",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    let chunks: Vec<Vec<u8>> = run_future_hot(field.data.buffered(0).try_collect()).unwrap();
+
+    assert_eq!(chunks, [&b"field "[..], &b"data"[..]]);
+}
+
+#[test]
+fn test_drop_unread_field() {
+    use crate::test_util::{mock_stream, run_future_hot};
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"field ",
+            b"data\r\n--bou",
+            b"ndary\r\nContent-Disposition: form-data; name=\"bar\"\r\n\r\n",
+            b"bar data",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"baz\"\r\n\r\n",
+            b"baz data",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let mut field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    assert_eq!(field.headers.name, "foo");
+    assert_eq!(
+        run_future_hot(field.data.try_next()).unwrap(),
+        Some(&b"field "[..])
+    );
+    drop(field);
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    assert_eq!(field.headers.name, "bar");
+    // dropped without reading anything
+    drop(field);
+
+    let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+    assert_eq!(field.headers.name, "baz");
+    assert_eq!(
+        run_future_hot(field.data.read_to_string()).unwrap(),
+        "baz data"
+    );
+
+    assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+}
@@ -0,0 +1,347 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::pin::Pin;
+use std::task::Poll::{self, *};
+
+use futures_core::task::Context;
+use futures_core::{Stream, TryStream};
+
+use crate::server::Error;
+use crate::BodyChunk;
+
+use super::{FieldData, FieldHeaders};
+
+/// The data of a field with its `Content-Transfer-Encoding` decoded.
+///
+/// See [`FieldData::decode_transfer_encoding()`](struct.FieldData.html#method.decode_transfer_encoding).
+pub struct DecodedFieldData<'a, S: TryStream + 'a> {
+    data: FieldData<'a, S>,
+    decoder: Decoder,
+    finished: bool,
+}
+
+impl<'a, S: TryStream + 'a> DecodedFieldData<'a, S> {
+    pub(crate) fn new(data: FieldData<'a, S>, headers: &FieldHeaders) -> Self {
+        let decoder = match headers.content_transfer_encoding().as_deref() {
+            None | Some("7bit") | Some("8bit") | Some("binary") => Decoder::Identity,
+            Some("base64") => Decoder::Base64 {
+                group: [0; 4],
+                len: 0,
+                padded: false,
+            },
+            Some("quoted-printable") => Decoder::QuotedPrintable {
+                pending: Vec::new(),
+            },
+            Some(other) => Decoder::Unsupported(other.to_string()),
+        };
+
+        DecodedFieldData {
+            data,
+            decoder,
+            finished: false,
+        }
+    }
+}
+
+impl<S: TryStream> Stream for DecodedFieldData<'_, S>
+where
+    S::Ok: BodyChunk,
+    Error<S::Error>: From<S::Error>,
+{
+    type Item = super::super::Result<Vec<u8>, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if this.finished {
+            return Ready(None);
+        }
+
+        if let Decoder::Unsupported(ref encoding) = this.decoder {
+            this.finished = true;
+            return Ready(Some(fmt_err!(
+                "unsupported Content-Transfer-Encoding: {:?}",
+                encoding
+            )));
+        }
+
+        loop {
+            let res = match ready!(Pin::new(&mut this.data).poll_next(cx)?) {
+                Some(chunk) => this.decoder.decode(chunk),
+                None => {
+                    this.finished = true;
+                    this.decoder.finish().map(|()| Vec::new())
+                }
+            };
+
+            match res {
+                Ok(ref decoded) if decoded.is_empty() && !this.finished => continue,
+                Ok(decoded) if decoded.is_empty() => return Ready(None),
+                Ok(decoded) => return Ready(Some(Ok(decoded))),
+                Err(e) => {
+                    this.finished = true;
+                    return Ready(Some(Err(Error::Parsing(e.into()))));
+                }
+            }
+        }
+    }
+}
+
+enum Decoder {
+    Identity,
+    Base64 {
+        /// The 6-bit values of the incomplete 4-character group
+        group: [u8; 4],
+        len: usize,
+        /// Padding was seen, so only whitespace may follow
+        padded: bool,
+    },
+    QuotedPrintable {
+        /// An escape sequence split from the end of the previous chunk
+        pending: Vec<u8>,
+    },
+    Unsupported(String),
+}
+
+impl Decoder {
+    fn decode<B: BodyChunk>(&mut self, chunk: B) -> Result<Vec<u8>, String> {
+        match self {
+            Decoder::Identity => Ok(chunk.into_vec()),
+            Decoder::Base64 { group, len, padded } => {
+                decode_base64(chunk.as_slice(), group, len, padded)
+            }
+            Decoder::QuotedPrintable { pending } => {
+                decode_quoted_printable(chunk.as_slice(), pending)
+            }
+            Decoder::Unsupported(_) => unreachable!(),
+        }
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        match self {
+            Decoder::Base64 { len, .. } if *len != 0 => Err(format!(
+                "incomplete base64 group of {} characters at end of field",
+                len
+            )),
+            Decoder::QuotedPrintable { pending } if !pending.is_empty() => Err(format!(
+                "incomplete quoted-printable escape at end of field: {:?}",
+                String::from_utf8_lossy(pending)
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+const BASE64_PAD: u8 = 64;
+
+fn base64_value(b: u8) -> Option<u8> {
+    match b {
+        b'A'..=b'Z' => Some(b - b'A'),
+        b'a'..=b'z' => Some(b - b'a' + 26),
+        b'0'..=b'9' => Some(b - b'0' + 52),
+        b'+' => Some(62),
+        b'/' => Some(63),
+        b'=' => Some(BASE64_PAD),
+        _ => None,
+    }
+}
+
+fn decode_base64(
+    input: &[u8],
+    group: &mut [u8; 4],
+    len: &mut usize,
+    padded: &mut bool,
+) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(input.len() / 4 * 3 + 3);
+
+    // base64 bodies are usually broken into lines
+    for &b in input.iter().filter(|b| !b.is_ascii_whitespace()) {
+        let val = base64_value(b)
+            .ok_or_else(|| format!("invalid character in base64 field data: {:?}", b as char))?;
+
+        if *padded && *len == 0 {
+            return Err("base64 field data continues after padding".into());
+        }
+
+        group[*len] = val;
+        *len += 1;
+
+        if *len < 4 {
+            continue;
+        }
+
+        *len = 0;
+
+        let decoded_len = match *group {
+            [a, b, BASE64_PAD, BASE64_PAD] if a != BASE64_PAD && b != BASE64_PAD => 1,
+            [a, b, c, BASE64_PAD] if a != BASE64_PAD && b != BASE64_PAD && c != BASE64_PAD => 2,
+            _ if !group.contains(&BASE64_PAD) => 3,
+            _ => return Err("misplaced padding in base64 field data".into()),
+        };
+
+        *padded = decoded_len < 3;
+
+        let bits = group
+            .iter()
+            .fold(0u32, |bits, &val| bits << 6 | u32::from(val & 0x3F));
+        out.extend_from_slice(&bits.to_be_bytes()[1..=decoded_len]);
+    }
+
+    Ok(out)
+}
+
+fn decode_quoted_printable(input: &[u8], pending: &mut Vec<u8>) -> Result<Vec<u8>, String> {
+    fn hex_value(b: u8) -> Option<u8> {
+        (b as char).to_digit(16).map(|d| d as u8)
+    }
+
+    let joined;
+
+    let input = if pending.is_empty() {
+        input
+    } else {
+        pending.extend_from_slice(input);
+        joined = std::mem::take(pending);
+        &joined
+    };
+
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        if input[i] != b'=' {
+            out.push(input[i]);
+            i += 1;
+            continue;
+        }
+
+        match input[i + 1..] {
+            // soft line break
+            [b'\r', b'\n', ..] => i += 3,
+            [b'\n', ..] => i += 2,
+            [hi, lo, ..] if hex_value(hi).is_some() && hex_value(lo).is_some() => {
+                out.push(hex_value(hi).unwrap() << 4 | hex_value(lo).unwrap());
+                i += 3;
+            }
+            // the rest of the escape is in the next chunk
+            [] | [b'\r'] => {
+                pending.extend_from_slice(&input[i..]);
+                break;
+            }
+            [digit] if hex_value(digit).is_some() => {
+                pending.extend_from_slice(&input[i..]);
+                break;
+            }
+            _ => {
+                return Err(format!(
+                    "invalid quoted-printable escape: {:?}",
+                    String::from_utf8_lossy(&input[i..input.len().min(i + 3)])
+                ))
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::TryStreamExt;
+
+    use crate::server::Multipart;
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    fn decode_field(
+        transfer_encoding: &str,
+        data: &[&[u8]],
+    ) -> Result<Vec<u8>, crate::server::Error<std::convert::Infallible>> {
+        let headers = format!(
+            "Content-Disposition: form-data; name=\"foo\"\r\n\
+             Content-Transfer-Encoding: {}\r\n\r\n",
+            transfer_encoding
+        );
+
+        let mut chunks = vec![&b"--boundary\r\n"[..], headers.as_bytes()];
+        chunks.extend_from_slice(data);
+        chunks.push(b"\r\n--boundary--");
+
+        let mut multipart = Multipart::with_body(mock_stream(&chunks), "boundary");
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+
+        let decoded: Vec<Vec<u8>> = run_future_hot(
+            field
+                .data
+                .decode_transfer_encoding(&field.headers)
+                .try_collect(),
+        )?;
+
+        Ok(decoded.concat())
+    }
+
+    #[test]
+    fn test_decode_base64() {
+        let _ = ::env_logger::try_init();
+
+        // "Hello, world!\n" with the groups split across chunks and line breaks
+        assert_eq!(
+            decode_field("BASE64", &[b"SGVsb", b"G8sIHdv\r", b"\ncmxkIQ", b"o="]).unwrap(),
+            b"Hello, world!\n"
+        );
+
+        assert_eq!(
+            decode_field("base64", &[b"AAEC/w=="]).unwrap(),
+            b"\x00\x01\x02\xFF"
+        );
+
+        let err = decode_field("base64", &[b"SGVsbG8"]).unwrap_err();
+        assert!(
+            err.to_string().contains("incomplete base64 group"),
+            "{}",
+            err
+        );
+
+        let err = decode_field("base64", &[b"SGV*bG8="]).unwrap_err();
+        assert!(err.is_client_error());
+        assert!(err.to_string().contains("invalid character"), "{}", err);
+    }
+
+    #[test]
+    fn test_decode_quoted_printable() {
+        let _ = ::env_logger::try_init();
+
+        assert_eq!(
+            decode_field(
+                "quoted-printable",
+                &[b"caf=C3=A9 =", b"E2=8", b"2=AC long=", b"\r", b"\nline"]
+            )
+            .unwrap(),
+            "caf\u{E9} \u{20AC} longline".as_bytes()
+        );
+
+        let err = decode_field("quoted-printable", &[b"bad =XY escape"]).unwrap_err();
+        assert!(
+            err.to_string().contains("invalid quoted-printable"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_decode_identity_and_unsupported() {
+        let _ = ::env_logger::try_init();
+
+        for encoding in &["7bit", "8bit", "binary"] {
+            assert_eq!(
+                decode_field(encoding, &[b"as ", b"is=41"]).unwrap(),
+                b"as is=41"
+            );
+        }
+
+        let err = decode_field("x-uuencode", &[b"data"]).unwrap_err();
+        assert!(err.to_string().contains("unsupported"), "{}", err);
+    }
+}
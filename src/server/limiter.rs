@@ -0,0 +1,146 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use futures_core::TryStream;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::BodyChunk;
+
+use super::Multipart;
+
+/// Bounds how many `Multipart` requests may be read at once, to limit memory pressure when
+/// accepting many large uploads. Enabled with the `concurrency` feature.
+///
+/// Clones share the same limit.
+#[derive(Clone, Debug)]
+pub struct ConcurrencyLimiter {
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConcurrencyLimiter {
+    /// Allow at most `max` requests to be read at once.
+    pub fn new(max: usize) -> Self {
+        ConcurrencyLimiter {
+            semaphore: Arc::new(Semaphore::new(max)),
+        }
+    }
+
+    /// The number of requests that may still begin reading without waiting.
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    /// Wait until fewer than `max` requests are being read, then wrap `multipart` in a guard
+    /// which holds its place until it is dropped.
+    pub async fn acquire<S>(&self, multipart: Multipart<S>) -> LimitedMultipart<S>
+    where
+        S: TryStream,
+        S::Ok: BodyChunk,
+    {
+        let permit = self.semaphore.clone().acquire_owned().await;
+
+        LimitedMultipart {
+            multipart,
+            _permit: permit,
+        }
+    }
+}
+
+/// A `Multipart` counted against a [`ConcurrencyLimiter`](struct.ConcurrencyLimiter.html).
+///
+/// Dereferences to the wrapped `Multipart`; its place is released when this is dropped.
+pub struct LimitedMultipart<S: TryStream> {
+    multipart: Multipart<S>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<S: TryStream> Deref for LimitedMultipart<S> {
+    type Target = Multipart<S>;
+
+    fn deref(&self) -> &Multipart<S> {
+        &self.multipart
+    }
+}
+
+impl<S: TryStream> DerefMut for LimitedMultipart<S> {
+    fn deref_mut(&mut self) -> &mut Multipart<S> {
+        &mut self.multipart
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll};
+
+    use futures::future::{self, Future};
+
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    use super::{ConcurrencyLimiter, Multipart};
+
+    /// Return `Pending` once to let the other futures make progress.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    #[test]
+    fn test_concurrency_limiter() {
+        let _ = ::env_logger::try_init();
+
+        let limiter = ConcurrencyLimiter::new(2);
+        let active = AtomicUsize::new(0);
+        let max_active = AtomicUsize::new(0);
+        let completed = AtomicUsize::new(0);
+
+        let parses = (0..4).map(|_| async {
+            let multipart = Multipart::with_body(
+                mock_stream(&[
+                    b"--boundary\r\n",
+                    b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                    b"field data",
+                    b"\r\n--boundary--",
+                ]),
+                "boundary",
+            );
+
+            let mut multipart = limiter.acquire(multipart).await;
+
+            let now_active = active.fetch_add(1, Ordering::SeqCst) + 1;
+            max_active.fetch_max(now_active, Ordering::SeqCst);
+
+            while let Some(field) = multipart.next_field().await.unwrap() {
+                assert_eq!(field.data.read_to_string().await.unwrap(), "field data");
+                YieldOnce(false).await;
+            }
+
+            active.fetch_sub(1, Ordering::SeqCst);
+            completed.fetch_add(1, Ordering::SeqCst);
+        });
+
+        run_future_hot(future::join_all(parses));
+
+        assert_eq!(max_active.load(Ordering::SeqCst), 2);
+        assert_eq!(completed.load(Ordering::SeqCst), 4);
+        assert_eq!(limiter.available(), 2);
+    }
+}
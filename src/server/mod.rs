@@ -14,6 +14,7 @@
 use std::fmt;
 use std::pin::Pin;
 
+use bytes::Bytes;
 use futures_core::task::{self, Context};
 use futures_core::{Future, Stream};
 use http::{Method, Request};
@@ -25,8 +26,27 @@ use self::helpers::*;
 use crate::BodyChunk;
 
 use self::boundary::BoundaryFinder;
+pub use self::budget::FieldBudget;
+pub use self::collect::{CollectFields, FieldValue};
+#[cfg(feature = "drain")]
+pub use self::drain::DrainOnDrop;
 use self::field::ReadHeaders;
-pub use self::field::{Field, FieldData, FieldHeaders, NextField, ReadToString};
+#[cfg(feature = "charset")]
+pub use self::field::ReadToStringWithCharset;
+pub use self::field::{
+    Buffered, BufferedField, CollectVec, CopyTo, DecodedFieldData, Field, FieldData, FieldHeaders,
+    FieldReader, MapChunks, NextField, ReadToString, Scan, TextOrFile,
+};
+#[cfg(feature = "concurrency")]
+pub use self::limiter::{ConcurrencyLimiter, LimitedMultipart};
+pub use self::parse::parse_all;
+pub use self::read::{AsyncReadConfig, ReadStream};
+#[cfg(feature = "save")]
+pub use self::save::SavedFile;
+#[cfg(feature = "spill")]
+pub use self::spill::SpilledField;
+pub use self::stream::{MultipartStream, OwnedField, OwnedFieldData};
+pub use self::take::RemainingBody;
 use std::borrow::Cow;
 use std::convert::Infallible;
 use std::str::Utf8Error;
@@ -44,7 +64,7 @@ macro_rules! try_opt (
 
 macro_rules! ret_err (
     ($($args:tt)+) => (
-        return fmt_err!($($args)+).into();
+        return fmt_err!($($args)+).into()
     )
 );
 
@@ -53,6 +73,9 @@ macro_rules! ret_ok(
 );
 
 macro_rules! fmt_err (
+    ($kind:ident: $string:literal $($args:tt)*) => (
+        Err(crate::server::Error::$kind(format!($string $($args)*).into()))
+    );
     ($string:expr) => (
         Err(crate::server::Error::Parsing($string.into()))
     );
@@ -61,6 +84,12 @@ macro_rules! fmt_err (
     );
 );
 
+macro_rules! eof_err (
+    ($string:expr, $($args:tt)*) => (
+        Err(crate::server::Error::UnexpectedEof(format!($string, $($args)*).into()))
+    );
+);
+
 macro_rules! debug_panic(
     ($($args:tt)*) => {
         if cfg!(debug_assertions) {
@@ -71,13 +100,36 @@ macro_rules! debug_panic(
     }
 );
 
+#[cfg(feature = "actix")]
+pub mod actix;
+#[cfg(feature = "axum")]
+pub mod axum;
 mod boundary;
+mod budget;
+mod collect;
+#[cfg(feature = "drain")]
+mod drain;
 mod field;
+#[cfg(feature = "hyper_014")]
+pub mod hyper;
+#[cfg(feature = "concurrency")]
+mod limiter;
+mod parse;
+pub mod prelude;
+mod read;
+#[cfg(feature = "save")]
+mod save;
+#[cfg(feature = "spill")]
+mod spill;
+mod stream;
+mod take;
+#[cfg(feature = "tide")]
+pub mod tide;
 
-// pub use self::field::{ReadTextField, TextField};
+#[cfg(test)]
+mod prop_test;
 
-// #[cfg(feature = "hyper")]
-// mod hyper;
+// pub use self::field::{ReadTextField, TextField};
 
 #[cfg(any(test, feature = "fuzzing"))]
 pub(crate) mod fuzzing {
@@ -87,7 +139,8 @@ pub(crate) mod fuzzing {
 
 /// The server-side implementation of `multipart/form-data` requests.
 ///
-/// After constructing with either the [`::with_body()`](#method.with_body) or
+/// After constructing with either the [`::with_body()`](#method.with_body),
+/// [`::with_reader()`](#method.with_reader) or
 /// [`::try_from_request()`](#method.try_from_request), two different workflows for processing the
 /// request are provided, assuming any `Poll::Pending` and `Ready(Err(_))`/`Ready(Some(Err(_)))`
 /// results are handled in the typical fashion:
@@ -109,14 +162,39 @@ pub(crate) mod fuzzing {
 /// 3. Poll for the field's data chunks with [`.poll_field_chunk()](#method.poll_field_chunk)
 /// until `None` is returned, then loop back to step 2.
 ///
-/// Any data before the first boundary and past the end of the terminating boundary is ignored
-/// as it is out-of-spec and should not be expected to be left in the underlying stream intact.
+/// Any data before the first boundary (the preamble) is skipped, as is any data past the end
+/// of the terminating boundary (the epilogue), per
+/// [IETF RFC 2046 section 5.1.1](https://tools.ietf.org/html/rfc2046#section-5.1.1).
+/// Neither should be expected to be left in the underlying stream intact.
 /// Please open an issue if you have a legitimate use-case for extraneous data in a multipart request.
 pub struct Multipart<S: TryStream> {
     inner: PushChunk<BoundaryFinder<S>, S::Ok>,
     read_hdr: ReadHeaders,
+    started: bool,
+    /// The headers of the current field have been read
+    headers_read: bool,
+    fields: usize,
+    max_fields: Option<usize>,
+    field_size_limit: Option<usize>,
+    field_bytes: usize,
+    /// The part of a chunk past `field_size_limit`, yielded by the next `poll_field_chunk()`
+    overflow: Option<S::Ok>,
+    total_limit: Option<usize>,
+    total_bytes: usize,
+    forbid_data: bool,
+    first_field: Option<String>,
+    text_types: Option<TextTypes>,
+    capture_charset: bool,
+    charset_field: Option<String>,
+    budget: Option<FieldBudget>,
+    /// The number of bodies this one is nested in
+    depth: usize,
+    consumed: bool,
 }
 
+/// The predicate set by `Multipart::text_content_types()`
+type TextTypes = Box<dyn Fn(&Mime) -> bool + Send + Sync>;
+
 // Q: why can't we just wrap up these bounds into a trait?
 // A: https://github.com/rust-lang/rust/issues/24616#issuecomment-112065997
 // (The workaround mentioned in a later comment doesn't seem to be worth the added complexity)
@@ -127,6 +205,14 @@ where
 {
     unsafe_pinned!(inner: PushChunk<BoundaryFinder<S>, S::Ok>);
     unsafe_unpinned!(read_hdr: ReadHeaders);
+    unsafe_unpinned!(started: bool);
+    unsafe_unpinned!(headers_read: bool);
+    unsafe_unpinned!(fields: usize);
+    unsafe_unpinned!(field_bytes: usize);
+    unsafe_unpinned!(overflow: Option<S::Ok>);
+    unsafe_unpinned!(total_bytes: usize);
+    unsafe_unpinned!(charset_field: Option<String>);
+    unsafe_unpinned!(consumed: bool);
 
     /// Construct a new `Multipart` with the given body reader and boundary.
     ///
@@ -139,33 +225,270 @@ where
 
         debug!("Boundary: {}", boundary);
 
+        Self::with_full_boundary(stream, boundary.into_bytes())
+    }
+
+    /// Construct a new `Multipart` with the given body reader and a boundary given as bytes,
+    /// e.g. as taken from a raw header value without first converting it to a string.
+    ///
+    /// As with [`with_body()`](#method.with_body), this will add the requisite `--` to the
+    /// boundary.
+    ///
+    /// Returns `InvalidBoundary` if `boundary` is empty or contains non-ASCII bytes, which
+    /// [IETF RFC 2046 section 5.1.1](https://tools.ietf.org/html/rfc2046#section-5.1.1)
+    /// doesn't allow.
+    pub fn with_body_bytes_boundary<B: Into<Vec<u8>>>(
+        stream: S,
+        boundary: B,
+    ) -> std::result::Result<Self, InvalidBoundary> {
+        let boundary = boundary.into();
+
+        if boundary.is_empty() || !boundary.is_ascii() {
+            debug!("invalid boundary: {}", show_bytes(&boundary));
+            return Err(InvalidBoundary);
+        }
+
+        let mut full = b"--".to_vec();
+        full.extend_from_slice(&boundary);
+
+        debug!("Boundary: {}", show_bytes(&full));
+
+        Ok(Self::with_full_boundary(stream, full))
+    }
+
+    /// `boundary` includes the leading `--`
+    fn with_full_boundary(stream: S, boundary: Vec<u8>) -> Self {
         Multipart {
             inner: PushChunk::new(BoundaryFinder::new(stream, boundary)),
             read_hdr: ReadHeaders::default(),
+            started: false,
+            headers_read: false,
+            fields: 0,
+            max_fields: None,
+            field_size_limit: None,
+            field_bytes: 0,
+            overflow: None,
+            total_limit: None,
+            total_bytes: 0,
+            forbid_data: false,
+            first_field: None,
+            text_types: None,
+            capture_charset: false,
+            charset_field: None,
+            budget: None,
+            depth: 0,
+            consumed: false,
         }
     }
 
+    /// If `true`, invalid UTF-8 in the RFC 5987 extended `name*` and `filename*` parameters of
+    /// `Content-Disposition` is replaced with U+FFFD instead of returning an error.
+    ///
+    /// Defaults to `false`.
+    pub fn with_lossy_utf8_names(mut self, lossy: bool) -> Self {
+        self.read_hdr.lossy_utf8 = lossy;
+        self
+    }
+
+    /// Set the maximum length in bytes of the headers section of each field, after which
+    /// reading the field returns an error.
+    ///
+    /// Defaults to 1024, which may be too small for clients sending long filenames
+    /// (especially percent-encoded in `filename*`) or many extra headers.
+    pub fn with_header_limit(mut self, limit: usize) -> Self {
+        self.read_hdr.max_len = limit;
+        self
+    }
+
+    /// Set the maximum number of fields the request may contain, after which
+    /// [`.poll_has_next_field()`](#method.poll_has_next_field) (and so
+    /// [`.next_field()`](#method.next_field)) returns an error instead of starting another field.
+    ///
+    /// Unlimited by default. Intended to stop a client from exhausting server resources with
+    /// a huge number of tiny fields.
+    pub fn with_max_fields(mut self, max_fields: usize) -> Self {
+        self.max_fields = Some(max_fields);
+        self
+    }
+
+    /// Count the fields of this body against `budget`, which is shared with any bodies nested in
+    /// its fields and read with [`FieldData::into_nested()`](struct.FieldData.html#method.into_nested).
+    ///
+    /// Once the budget is spent, [`.poll_has_next_field()`](#method.poll_has_next_field) returns an
+    /// error at whichever level the next field is in. This applies in addition to
+    /// [`.with_max_fields()`](#method.with_max_fields).
+    pub fn with_field_budget(mut self, budget: FieldBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Set the maximum number of data bytes of any single field, after which
+    /// [`.poll_field_chunk()`](#method.poll_field_chunk) (and so reading from
+    /// [`FieldData`](struct.FieldData.html)) returns an error.
+    ///
+    /// Only the field's data is counted, not its headers or the boundaries around it.
+    /// Unlimited by default.
+    ///
+    /// A chunk which crosses the limit is split: the bytes up to the limit are yielded, and the
+    /// rest is kept for the next call, which returns the error. See
+    /// [`.set_field_data_limit()`](#method.set_field_data_limit) to keep reading past it.
+    pub fn with_field_size_limit(mut self, limit: usize) -> Self {
+        self.field_size_limit = Some(limit);
+        self
+    }
+
+    /// Set or clear the limit of [`.with_field_size_limit()`](#method.with_field_size_limit)
+    /// while reading.
+    ///
+    /// After [`.poll_field_chunk()`](#method.poll_field_chunk) has returned the error for the
+    /// limit, raising or clearing it continues the field from the first byte past the limit;
+    /// no data is lost.
+    pub fn set_field_data_limit(&mut self, limit: Option<usize>) {
+        self.field_size_limit = limit;
+    }
+
+    /// If `true`, return an error for any field with data, e.g. to check that a request which
+    /// should only contain empty fields doesn't carry file uploads before handing it off.
+    ///
+    /// [`.poll_field_chunk()`](#method.poll_field_chunk) returns the error when the data is
+    /// read, and [`.poll_has_next_field()`](#method.poll_has_next_field) when it would be
+    /// skipped. Only the data is checked, so fields may still have any headers.
+    ///
+    /// Defaults to `false`.
+    pub fn forbid_field_data(mut self, forbid: bool) -> Self {
+        self.forbid_data = forbid;
+        self
+    }
+
+    /// Return an error if the first field in the request isn't named `name`, e.g. for a
+    /// signature or CSRF token which must be checked before the rest of the request is trusted.
+    ///
+    /// [`.poll_field_headers()`](#method.poll_field_headers) returns the error as soon as the
+    /// headers of the first field are read, before any of its data.
+    ///
+    /// A `_charset_` field captured with
+    /// [`.with_charset_field(true)`](#method.with_charset_field) doesn't count, so it may
+    /// come before the expected field, as browsers send it.
+    pub fn expect_first_field<N: Into<String>>(mut self, name: N) -> Self {
+        self.first_field = Some(name.into());
+        self
+    }
+
+    /// Decide which field content types count as text for
+    /// [`FieldHeaders::is_text()`](struct.FieldHeaders.html#method.is_text), and so which fields
+    /// [`.next_text_field()`](#method.next_text_field) and
+    /// [`.collect_fields()`](#method.collect_fields) read to a string,
+    /// e.g. to include `application/json`.
+    ///
+    /// Fields without a `Content-Type` are always text. By default only `text/*` is.
+    pub fn text_content_types<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Mime) -> bool + Send + Sync + 'static,
+    {
+        self.text_types = Some(Box::new(predicate));
+        self
+    }
+
+    /// Set the maximum number of data bytes of all fields in the request combined, after which
+    /// [`.poll_field_chunk()`](#method.poll_field_chunk) and
+    /// [`.poll_has_next_field()`](#method.poll_has_next_field) return an error.
+    ///
+    /// Like [`.with_field_size_limit()`](#method.with_field_size_limit) only field data is
+    /// counted, but the count carries over from one field to the next so that many small fields
+    /// can't add up to an unbounded amount. If both limits are set then whichever is exceeded
+    /// first causes the error, and the per-field limit is checked first if a single chunk
+    /// exceeds both. Unlimited by default.
+    pub fn with_total_limit(mut self, limit: usize) -> Self {
+        self.total_limit = Some(limit);
+        self
+    }
+
+    /// If `true`, a field named `_charset_` is not yielded by
+    /// [`.next_field()`](#method.next_field); instead its value is kept as the default charset
+    /// for the text fields after it, as described in
+    /// [IETF RFC 7578 Section 4.6](https://tools.ietf.org/html/rfc7578#section-4.6).
+    ///
+    /// The value is available from [`.default_charset()`](#method.default_charset) and
+    /// [`FieldData::default_charset()`](struct.FieldData.html#method.default_charset).
+    ///
+    /// Defaults to `false`, in which case `_charset_` is yielded like any other field.
+    pub fn with_charset_field(mut self, capture: bool) -> Self {
+        self.capture_charset = capture;
+        self
+    }
+
+    /// The value of the `_charset_` field, if one has been read and
+    /// [`.with_charset_field(true)`](#method.with_charset_field) was set.
+    pub fn default_charset(&self) -> Option<&str> {
+        self.charset_field.as_deref()
+    }
+
+    /// If `true`, accept field headers with whitespace between the name and the colon,
+    /// e.g. `Content-Disposition : form-data; name="foo"`, which some non-conformant clients send.
+    ///
+    /// Defaults to `false`, in which case such headers are rejected with a parsing error as
+    /// [RFC 7230 section 3.2.4](https://tools.ietf.org/html/rfc7230#section-3.2.4) requires.
+    ///
+    /// Headers with no whitespace after the colon, e.g. `Content-Disposition:form-data`, are
+    /// valid and accepted either way.
+    pub fn with_lenient_header_names(mut self, lenient: bool) -> Self {
+        self.read_hdr.lenient_header_names = lenient;
+        self
+    }
+
+    /// Construct a new `Multipart` with the given body reader, taking the boundary from the
+    /// `boundary` parameter of `content_type`, e.g. a `Content-Type` header already parsed by
+    /// a web framework.
+    ///
+    /// Returns `NoBoundary` if the parameter is missing or empty. The type of `content_type`
+    /// itself is not checked.
+    pub fn from_content_type(
+        stream: S,
+        content_type: &Mime,
+    ) -> std::result::Result<Self, NoBoundary> {
+        boundary_param(content_type)
+            .map(|boundary| Self::with_body(stream, boundary))
+            .ok_or(NoBoundary)
+    }
+
     /// If `req` is a `POST multipart/form-data` request, take the body and
     /// return the wrapped stream. Else, return the request.
+    ///
+    /// A request with an empty `boundary` parameter is also returned, as it cannot be parsed.
+    ///
+    /// Requests with any other method, such as `PUT` or `PATCH`, are always returned; use
+    /// [`::try_from_request_any_method()`](#method.try_from_request_any_method) to accept them.
+    // the request is handed back whole so the caller can still use it
+    #[allow(clippy::result_large_err)]
     pub fn try_from_request(req: Request<S>) -> std::result::Result<Self, Request<S>> {
-        fn get_boundary(parts: &http::request::Parts) -> Option<String> {
-            Some(
-                parts
-                    .headers
-                    .get(http::header::CONTENT_TYPE)?
-                    .to_str()
-                    .ok()?
-                    .parse::<Mime>()
-                    .ok()?
-                    .get_param(mime::BOUNDARY)?
-                    .to_string(),
-            )
-        }
-
-        if req.method() != &Method::POST {
+        if req.method() != Method::POST {
             return Err(req);
         }
 
+        Self::try_from_request_any_method(req)
+    }
+
+    /// As [`::try_from_request()`](#method.try_from_request) but without checking the method,
+    /// e.g. for `PUT` or `PATCH` requests that update a resource with file attachments.
+    ///
+    /// The content type must still be `multipart/form-data` with a non-empty `boundary`.
+    #[allow(clippy::result_large_err)]
+    pub fn try_from_request_any_method(req: Request<S>) -> std::result::Result<Self, Request<S>> {
+        fn get_boundary(parts: &http::request::Parts) -> Option<String> {
+            let content_type = parts
+                .headers
+                .get(http::header::CONTENT_TYPE)?
+                .to_str()
+                .ok()?
+                .parse::<Mime>()
+                .ok()
+                .filter(|mime| {
+                    mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA
+                })?;
+
+            boundary_param(&content_type)
+        }
+
         let (parts, body) = req.into_parts();
 
         if let Some(boundary) = get_boundary(&parts) {
@@ -175,6 +498,45 @@ where
         Err(Request::from_parts(parts, body))
     }
 
+    /// Convert the chunks of the body stream with `f`, e.g. to get `Bytes` from a stream of
+    /// `Vec<u8>`.
+    ///
+    /// This may be called at any point; chunks already read from the stream but not yet
+    /// yielded are converted as well.
+    pub fn map_ok<F, B>(self, mut f: F) -> Multipart<MapChunks<S, F>>
+    where
+        S: Unpin,
+        F: FnMut(S::Ok) -> B,
+        B: BodyChunk,
+    {
+        let overflow = self.overflow.map(&mut f);
+
+        Multipart {
+            inner: PushChunk {
+                pushed: self.inner.pushed.map(&mut f),
+                stream: self.inner.stream.map_ok(f),
+            },
+            read_hdr: self.read_hdr,
+            started: self.started,
+            headers_read: self.headers_read,
+            fields: self.fields,
+            max_fields: self.max_fields,
+            field_size_limit: self.field_size_limit,
+            field_bytes: self.field_bytes,
+            overflow,
+            total_limit: self.total_limit,
+            total_bytes: self.total_bytes,
+            forbid_data: self.forbid_data,
+            first_field: self.first_field,
+            text_types: self.text_types,
+            capture_charset: self.capture_charset,
+            charset_field: self.charset_field,
+            budget: self.budget,
+            depth: self.depth,
+            consumed: self.consumed,
+        }
+    }
+
     /// Get a future yielding the next field in the stream, if the stream is not at an end.
     ///
     /// If a field was previously being read, its contents will be discarded.
@@ -220,6 +582,87 @@ where
         NextField::new(Pin::new(self))
     }
 
+    /// Get the next field in the stream and, if it is a
+    /// [text field](struct.FieldHeaders.html#method.is_text_field), read it to a string,
+    /// returning an error if its data is longer than `limit` bytes.
+    ///
+    /// Other fields are returned as `TextOrFile::File` to be read as a stream instead.
+    ///
+    /// If a `_charset_` field was captured with
+    /// [`.with_charset_field(true)`](#method.with_charset_field), text fields are decoded from
+    /// that charset as with
+    /// [`FieldData::read_to_string_with_default_charset()`](struct.FieldData.html#method.read_to_string_with_default_charset)
+    /// when the `charset` feature is enabled. Without it, they are returned as
+    /// `TextOrFile::File` unless the charset is UTF-8 or US-ASCII.
+    pub async fn next_text_field(
+        &mut self,
+        limit: usize,
+    ) -> Result<Option<TextOrFile<'_, S>>, S::Error>
+    where
+        Self: Unpin,
+        Error<S::Error>: From<S::Error>,
+    {
+        let field = match self.next_field().await? {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+
+        if !field.headers.is_text_field() {
+            return Ok(Some(TextOrFile::File(field)));
+        }
+
+        #[cfg(feature = "charset")]
+        let text = field
+            .data
+            .read_to_string_with_default_charset()
+            .limit(limit)
+            .await?;
+
+        #[cfg(not(feature = "charset"))]
+        let text = {
+            let utf8_compatible = match field.data.default_charset() {
+                Some(charset) => {
+                    charset.eq_ignore_ascii_case("utf-8")
+                        || charset.eq_ignore_ascii_case("us-ascii")
+                }
+                None => true,
+            };
+
+            if !utf8_compatible {
+                return Ok(Some(TextOrFile::File(field)));
+            }
+
+            field.data.read_to_string().limit(limit).await?
+        };
+
+        Ok(Some(TextOrFile::Text(field.headers, text)))
+    }
+
+    /// Read the first field of a request that is expected to have only one, such as a simple
+    /// file upload, buffering its data in memory.
+    ///
+    /// Returns `Ok(None)` if the request has no fields. Any fields after the first are ignored.
+    /// Returns an error if the data of the field is longer than `limit` bytes.
+    pub async fn into_single_field(self, limit: usize) -> Result<Option<BufferedField>, S::Error>
+    where
+        Error<S::Error>: From<S::Error>,
+    {
+        let multipart = self;
+        pin_mut!(multipart);
+
+        let field = match multipart.next_field_pinned().await? {
+            Some(field) => field,
+            None => return Ok(None),
+        };
+
+        let data = field.data.collect_vec().limit(limit).await?;
+
+        Ok(Some(BufferedField {
+            headers: field.headers,
+            data,
+        }))
+    }
+
     /// Same as [`.next_field()`](#method.next_field) but with a receiver of `Pin<&mut Self>`.
     pub fn next_field_pinned(self: Pin<&mut Self>) -> NextField<S> {
         NextField::new(self)
@@ -231,7 +674,12 @@ where
     /// If this returns `Ready(Ok(true))`, you may then begin
     /// [polling for the headers of the next field](#method.poll_field_headers).
     ///
-    /// If a field was being read, its contents will be discarded.
+    /// A form with no fields, whose body is just the terminating boundary (`--boundary--`) or
+    /// empty, returns `false` from the first call.
+    ///
+    /// If a field was being read, the rest of its headers and data will be silently read and
+    /// discarded. Check [`.current_field_done()`](#method.current_field_done) first if this is
+    /// not desired.
     ///
     /// This is a low-level call and is expected to be supplemented/replaced by a more ergonomic
     /// API once more design work has taken place.
@@ -239,7 +687,86 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut Context,
     ) -> Poll<Result<bool, S::Error>> {
-        self.as_mut().inner().stream().consume_boundary(cx)
+        // read the rest of the current field instead of skipping it so any data is caught
+        if self.forbid_data && self.started && !self.consumed {
+            // otherwise the header block would be read as data
+            if !self.headers_read {
+                ready!(self.as_mut().poll_raw_field_headers(cx)?);
+            }
+
+            while ready!(self.as_mut().poll_field_chunk(cx)?).is_some() {}
+        }
+
+        // anything buffered belongs to the current field
+        self.as_mut().inner().pushed().take();
+        self.as_mut().overflow().take();
+        self.as_mut().read_hdr().clear();
+        *self.as_mut().field_bytes() = 0;
+
+        self.as_mut().check_total_limit()?;
+
+        let has_next = ready!(self.as_mut().inner().stream().consume_boundary(cx)?);
+        *self.as_mut().started() = true;
+        *self.as_mut().headers_read() = false;
+
+        if has_next {
+            if let Some(max_fields) = self.max_fields {
+                if self.fields >= max_fields {
+                    ret_err!(
+                        "request contains more than the limit of {} fields",
+                        max_fields
+                    );
+                }
+            }
+
+            if let Some(ref budget) = self.budget {
+                if !budget.take_field() {
+                    ret_err!(
+                        "request contains more than the limit of {} fields across all \
+                         nesting levels",
+                        budget.max_fields()
+                    );
+                }
+            }
+
+            *self.as_mut().fields() += 1;
+        } else {
+            *self.as_mut().consumed() = true;
+        }
+
+        ready_ok(has_next)
+    }
+
+    /// Returns `true` once the end of the body has been reached, i.e.
+    /// [`.poll_has_next_field()`](#method.poll_has_next_field) has returned `Ready(Ok(false))`
+    /// (or [`.next_field()`](#method.next_field) has yielded `None`).
+    ///
+    /// Useful to check that the body was fully read before responding or reusing the
+    /// connection.
+    pub fn is_consumed(&self) -> bool {
+        self.consumed
+    }
+
+    /// Returns `true` if there is no current field or all of its data has been read, i.e.
+    /// [`.poll_field_chunk()`](#method.poll_field_chunk) has returned `Ready(None)`.
+    ///
+    /// If this returns `false`, calling [`.poll_has_next_field()`](#method.poll_has_next_field)
+    /// will discard the rest of the current field.
+    pub fn current_field_done(&self) -> bool {
+        !self.started
+            || (self.inner.pushed.is_none()
+                && self.overflow.is_none()
+                && self.inner.stream.at_boundary())
+    }
+
+    /// The line ending which followed the first boundary, or `None` if it hasn't been read yet
+    /// or was followed by neither, e.g. for a body with no fields.
+    ///
+    /// Only CRLF line endings are supported by the parser, so a body using bare LF will fail
+    /// to parse once its first field is read; this is intended for logging the cause of that
+    /// error, as some clients are known to send LF.
+    pub fn detected_line_ending(&self) -> Option<LineEnding> {
+        self.inner.stream.detected_line_ending()
     }
 
     /// Poll for the headers of the next field, returning the headers or an error otherwise.
@@ -257,6 +784,10 @@ where
     /// This is a low-level call and is expected to be supplemented/replaced by a more ergonomic
     /// API once more design work has taken place.
     ///
+    /// If this is called before the first call to
+    /// [`.poll_has_next_field()`](#method.poll_has_next_field), the first boundary will be
+    /// consumed automatically; an error is returned if the body turns out to contain no fields.
+    ///
     /// ### Note: Calling This Is Not Enforced
     /// If this step is skipped then [`.poll_field_chunk()`](#method.poll_field_chunk)
     /// will return chunks of the header segment which may or may not be desirable depending
@@ -268,11 +799,102 @@ where
         mut self: Pin<&mut Self>,
         cx: &mut Context,
     ) -> Poll<Result<FieldHeaders, S::Error>> {
-        unsafe {
+        if !self.started && !ready!(self.as_mut().poll_has_next_field(cx)?) {
+            ret_err!("poll_field_headers() called but the multipart body contains no fields");
+        }
+
+        let res = unsafe {
+            let this = self.as_mut().get_unchecked_mut();
+            this.read_hdr
+                .read_headers(Pin::new_unchecked(&mut this.inner), cx)
+        };
+
+        match ready!(res) {
+            Err(e) => Poll::Ready(Err(self.headers_error(e))),
+            Ok(mut headers) => {
+                *self.as_mut().headers_read() = true;
+
+                if let (Some(text_types), Some(content_type)) =
+                    (&self.text_types, &headers.content_type)
+                {
+                    headers.text_type = Some(text_types(content_type));
+                }
+
+                let captured = self.capture_charset && headers.name == "_charset_";
+                // a `_charset_` field captured before this one isn't counted
+                let is_first = self.fields == 1 + usize::from(self.charset_field.is_some());
+
+                match self.first_field {
+                    Some(ref expected) if is_first && !captured && headers.name != *expected => {
+                        ret_err!(
+                            "expected the first field to be {:?} but found {:?}",
+                            expected,
+                            headers.name
+                        )
+                    }
+                    _ => ready_ok(headers),
+                }
+            }
+        }
+    }
+
+    /// Like [`.poll_field_headers()`](#method.poll_field_headers) but return the header block of
+    /// the field as-is, up to and including the terminating `\r\n\r\n`, e.g. to parse headers
+    /// this crate doesn't model such as `Content-ID`.
+    ///
+    /// The headers of a field can only be read once, so for a given field call either this or
+    /// `.poll_field_headers()`, not both. As the headers aren't parsed, they aren't checked
+    /// against [`.expect_first_field()`](#method.expect_first_field).
+    pub fn poll_raw_field_headers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<Bytes, S::Error>> {
+        if !self.started && !ready!(self.as_mut().poll_has_next_field(cx)?) {
+            ret_err!("poll_raw_field_headers() called but the multipart body contains no fields");
+        }
+
+        let res = unsafe {
             let this = self.as_mut().get_unchecked_mut();
             this.read_hdr
-                .read_headers(Pin::new_unchecked(&mut this.inner), cx)?
-                .map(Ok)
+                .read_raw_headers(Pin::new_unchecked(&mut this.inner), cx)
+        };
+
+        match ready!(res) {
+            Ok(headers) => {
+                *self.as_mut().headers_read() = true;
+                ready_ok(headers)
+            }
+            Err(e) => Poll::Ready(Err(self.headers_error(e))),
+        }
+    }
+
+    /// Add the offset to an error from reading the headers of a field, and tell apart headers
+    /// cut off by the end of the stream from those cut off by a boundary.
+    fn headers_error(mut self: Pin<&mut Self>, e: Error<Error<S::Error>>) -> Error<S::Error> {
+        let offset = self.inner.stream.position()
+            - self
+                .inner
+                .pushed
+                .as_ref()
+                .map_or(0, |chunk| chunk.len() as u64);
+        let with_offset = |e| format!("{} (at byte offset {})", e, offset).into();
+
+        match Error::from(e) {
+            // the headers were cut off by the end of the stream
+            Error::UnexpectedEof(e) if self.inner.stream.is_end() => {
+                Error::UnexpectedEof(with_offset(e))
+            }
+            // the boundary finder ended the field before the headers were terminated
+            Error::UnexpectedEof(_) => {
+                let e = format!(
+                    "boundary found inside header section: \"{}\"",
+                    show_bytes(self.read_hdr.accumulated())
+                );
+                self.as_mut().read_hdr().clear();
+
+                Error::BoundaryMismatch(with_offset(e.into()))
+            }
+            e => e.map_message(with_offset),
         }
     }
 
@@ -295,26 +917,231 @@ where
     /// If you do want to inspect the raw field headers, they are separated by one CRLF (`\r\n`) and
     /// terminated by two CRLFs (`\r\n\r\n`) after which the field chunks follow.
     pub fn poll_field_chunk(
-        self: Pin<&mut Self>,
+        mut self: Pin<&mut Self>,
         cx: &mut Context,
     ) -> Poll<Option<self::Result<S::Ok, S::Error>>> {
-        if !self.read_hdr.is_reading_headers() {
-            self.inner().poll_next(cx)
-        } else {
-            Poll::Ready(None)
+        if self.read_hdr.is_reading_headers() {
+            return Poll::Ready(None);
+        }
+
+        // a chunk pushed back by `.consume()` comes before the overflow
+        let overflow = match self.inner.pushed {
+            Some(_) => None,
+            None => self.as_mut().overflow().take(),
+        };
+
+        let mut chunk = match overflow {
+            Some(chunk) => chunk,
+            None => match ready!(self.as_mut().inner().poll_next(cx)?) {
+                Some(chunk) => chunk,
+                None => return Poll::Ready(None),
+            },
+        };
+
+        if let Some(limit) = self.field_size_limit {
+            let remaining = limit.saturating_sub(self.field_bytes);
+
+            if chunk.len() > remaining {
+                let read = self.field_bytes + chunk.len();
+
+                let (head, tail) = chunk.split_into(remaining);
+                *self.as_mut().overflow() = Some(tail);
+
+                if head.is_empty() {
+                    return Poll::Ready(Some(fmt_err!(
+                        "field data exceeds the limit of {} bytes ({} bytes read)",
+                        limit,
+                        read
+                    )));
+                }
+
+                chunk = head;
+            }
+        }
+
+        *self.as_mut().field_bytes() += chunk.len();
+        *self.as_mut().total_bytes() += chunk.len();
+
+        if let Err(e) = self.check_total_limit() {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        if self.forbid_data && !chunk.is_empty() {
+            return Poll::Ready(Some(fmt_err!(
+                "field data is not allowed in this request ({} bytes read)",
+                self.field_bytes
+            )));
+        }
+
+        Poll::Ready(Some(Ok(chunk)))
+    }
+
+    fn check_total_limit(&self) -> self::Result<(), S::Error> {
+        match self.total_limit {
+            Some(limit) if self.total_bytes > limit => fmt_err!(
+                "request field data exceeds the total limit of {} bytes ({} bytes read)",
+                limit,
+                self.total_bytes
+            ),
+            _ => Ok(()),
+        }
+    }
+
+    /// The boundary of this request as it was passed to [`with_body()`](#method.with_body),
+    /// i.e. without the leading `--`.
+    pub fn boundary(&self) -> &str {
+        let boundary = &self.inner.stream.boundary()[2..];
+        // constructed from a `String` or from ASCII bytes
+        std::str::from_utf8(boundary).expect("boundary is not UTF-8")
+    }
+
+    /// Get a snapshot of the parser's state, for logging when a request fails.
+    ///
+    /// The format of the snapshot is not stable and should not be parsed.
+    pub fn debug_state(&self) -> MultipartDebug {
+        let finder = &self.inner.stream;
+
+        MultipartDebug {
+            fields: self.fields,
+            bytes_read: finder.total_bytes_read(),
+            boundary: String::from_utf8_lossy(finder.boundary()).into_owned(),
+            finder_state: finder.debug_state(),
+            headers_buffered: self.read_hdr.accumulated_len(),
+            chunk_pushed: self.inner.pushed.as_ref().map(|chunk| chunk.len()),
+        }
+    }
+
+    /// Poll for the next chunk of the current field without consuming it, yielding an empty
+    /// slice at the end of the field.
+    ///
+    /// The chunk is kept in the pushed-back slot until it is consumed with `.consume()`.
+    pub(crate) fn poll_fill_buf<'b>(
+        mut self: Pin<&'b mut Self>,
+        cx: &mut Context,
+    ) -> Poll<Result<&'b [u8], S::Error>> {
+        while self.as_mut().inner().pushed().is_none() {
+            match ready!(self.as_mut().poll_field_chunk(cx)?) {
+                Some(chunk) if !chunk.is_empty() => self.as_mut().inner().push_chunk(chunk),
+                Some(_) => (),
+                None => return ready_ok(&[][..]),
+            }
+        }
+
+        match self.inner().pushed() {
+            Some(chunk) => ready_ok(chunk.as_slice()),
+            None => unreachable!(),
         }
     }
+
+    /// Consume `amt` bytes of the chunk returned by `.poll_fill_buf()`.
+    pub(crate) fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        if amt == 0 {
+            return;
+        }
+
+        let chunk = self
+            .as_mut()
+            .inner()
+            .pushed()
+            .take()
+            .expect("consume() called without a buffered chunk");
+
+        if amt < chunk.len() {
+            self.inner().push_chunk(chunk.split_into(amt).1);
+        }
+    }
+}
+
+/// The `boundary` parameter of a `multipart/*` content type, if present and not empty.
+fn boundary_param(content_type: &Mime) -> Option<String> {
+    let boundary = content_type.get_param(mime::BOUNDARY)?.as_str();
+
+    // `--` alone would match the start of every boundary-like line in the body
+    if boundary.is_empty() {
+        return None;
+    }
+
+    Some(boundary.to_string())
+}
+
+/// The error returned by
+/// [`Multipart::from_content_type()`](struct.Multipart.html#method.from_content_type) when the
+/// content type has no usable `boundary` parameter.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NoBoundary;
+
+impl fmt::Display for NoBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("content type has no `boundary` parameter or it is empty")
+    }
+}
+
+impl std::error::Error for NoBoundary {}
+
+/// The error returned by
+/// [`Multipart::with_body_bytes_boundary()`](struct.Multipart.html#method.with_body_bytes_boundary)
+/// when the boundary is empty or contains non-ASCII bytes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidBoundary;
+
+impl fmt::Display for InvalidBoundary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("multipart boundary is empty or contains non-ASCII bytes")
+    }
+}
+
+impl std::error::Error for InvalidBoundary {}
+
+/// A line ending convention, returned by
+/// [`Multipart::detected_line_ending()`](struct.Multipart.html#method.detected_line_ending).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\r\n`, as required by the spec.
+    Crlf,
+    /// A bare `\n`.
+    Lf,
+}
+
+/// A snapshot of the state of a `Multipart`, returned by
+/// [`Multipart::debug_state()`](struct.Multipart.html#method.debug_state).
+#[derive(Debug)]
+pub struct MultipartDebug {
+    /// The number of fields started so far.
+    pub fields: usize,
+    /// The total number of bytes received from the underlying stream.
+    pub bytes_read: u64,
+    /// The boundary being searched for, including the leading `--`.
+    pub boundary: String,
+    /// The internal state of the boundary search.
+    pub finder_state: String,
+    /// The number of bytes buffered while looking for the end of the current field's headers.
+    pub headers_buffered: usize,
+    /// The length of the chunk that was read but not yet consumed, if any.
+    pub chunk_pushed: Option<usize>,
 }
 
 /// `multipart-async`'s server error type, containing a message about a problem in the stream.
 ///
 /// This may either be from the underlying transport, or an error that occurred while parsing
-/// the request.
+/// the request. Errors for common problems with the request have their own variants so they
+/// can be matched on; the message of every variant is the same as its `Display` output.
 #[derive(Debug, Eq, PartialEq)]
 pub enum Error<E> {
-    /// An error occurred while parsing the request. Either the body was improperly formatted,
-    /// a field was missing headers, or the underlying transport returned an abnormally small chunk.
+    /// An error occurred while parsing the request which isn't covered by one of the more specific
+    /// variants, e.g. a limit set on the `Multipart` was exceeded.
     Parsing(Cow<'static, str>),
+    /// A boundary in the request was malformed, e.g. cut short or followed by unexpected bytes.
+    BoundaryMismatch(Cow<'static, str>),
+    /// The headers section of a field was longer than the limit set with
+    /// [`Multipart::with_header_limit()`](struct.Multipart.html#method.with_header_limit)
+    /// or contained too many headers.
+    HeadersTooLong(Cow<'static, str>),
+    /// A field had no `Content-Disposition` header.
+    MissingContentDisposition(Cow<'static, str>),
+    /// A field header was malformed or had an invalid or unsupported value.
+    InvalidHeader(Cow<'static, str>),
+    /// The stream ended before the multipart body was complete.
+    UnexpectedEof(Cow<'static, str>),
     /// An error occurred while trying to read a field to a string.
     Utf8(Utf8Error),
     /// An error was returned from the source stream.
@@ -322,8 +1149,63 @@ pub enum Error<E> {
 }
 
 impl<E> Error<E> {
-    fn parsing(s: impl Into<Cow<'static, str>>) -> Self {
-        Self::Parsing(s.into())
+    /// Apply `f` to the message of the variants which have one.
+    fn map_message(self, f: impl FnOnce(Cow<'static, str>) -> Cow<'static, str>) -> Self {
+        use Error::*;
+
+        match self {
+            Parsing(s) => Parsing(f(s)),
+            BoundaryMismatch(s) => BoundaryMismatch(f(s)),
+            HeadersTooLong(s) => HeadersTooLong(f(s)),
+            MissingContentDisposition(s) => MissingContentDisposition(f(s)),
+            InvalidHeader(s) => InvalidHeader(f(s)),
+            UnexpectedEof(s) => UnexpectedEof(f(s)),
+            other => other,
+        }
+    }
+
+    /// Returns `true` if the request body was malformed, i.e. the fault lies with the client.
+    pub fn is_client_error(&self) -> bool {
+        use Error::*;
+
+        matches!(
+            self,
+            Parsing(_)
+                | BoundaryMismatch(_)
+                | HeadersTooLong(_)
+                | MissingContentDisposition(_)
+                | InvalidHeader(_)
+                | Utf8(_)
+        )
+    }
+
+    /// Returns `true` if the stream ended before the multipart body was complete.
+    pub fn is_truncation(&self) -> bool {
+        matches!(self, Error::UnexpectedEof(_))
+    }
+
+    /// Returns `true` if the error was returned from the underlying stream.
+    pub fn is_io(&self) -> bool {
+        matches!(self, Error::Stream(_))
+    }
+}
+
+impl Error<Infallible> {
+    /// Convert to an error for a stream with an error type, as this one can't have come from
+    /// a stream.
+    pub(crate) fn with_stream_error<E>(self) -> Error<E> {
+        use Error::*;
+
+        match self {
+            Parsing(s) => Parsing(s),
+            BoundaryMismatch(s) => BoundaryMismatch(s),
+            HeadersTooLong(s) => HeadersTooLong(s),
+            MissingContentDisposition(s) => MissingContentDisposition(s),
+            InvalidHeader(s) => InvalidHeader(s),
+            UnexpectedEof(s) => UnexpectedEof(s),
+            Utf8(e) => Utf8(e),
+            Stream(never) => match never {},
+        }
     }
 }
 
@@ -339,6 +1221,13 @@ impl<E> From<Error<Error<E>>> for Error<E> {
 
         match inner {
             Parsing(parsing) | Stream(Parsing(parsing)) => Parsing(parsing),
+            BoundaryMismatch(s) | Stream(BoundaryMismatch(s)) => BoundaryMismatch(s),
+            HeadersTooLong(s) | Stream(HeadersTooLong(s)) => HeadersTooLong(s),
+            MissingContentDisposition(s) | Stream(MissingContentDisposition(s)) => {
+                MissingContentDisposition(s)
+            }
+            InvalidHeader(s) | Stream(InvalidHeader(s)) => InvalidHeader(s),
+            UnexpectedEof(eof) | Stream(UnexpectedEof(eof)) => UnexpectedEof(eof),
             Utf8(e) | Stream(Utf8(e)) => Utf8(e),
             Stream(Stream(e)) => Stream(e),
         }
@@ -350,9 +1239,9 @@ impl<E: std::error::Error + 'static> std::error::Error for Error<E> {
         use Error::*;
 
         match self {
-            Parsing(_) => None,
             Utf8(ref e) => Some(e),
             Stream(ref e) => Some(e),
+            _ => None,
         }
     }
 }
@@ -364,7 +1253,12 @@ impl<E: fmt::Display> fmt::Display for Error<E> {
         f.write_str("error occured while reading multipart body: ")?;
 
         match self {
-            Parsing(ref e) => f.write_str(e),
+            Parsing(ref e)
+            | BoundaryMismatch(ref e)
+            | HeadersTooLong(ref e)
+            | MissingContentDisposition(ref e)
+            | InvalidHeader(ref e)
+            | UnexpectedEof(ref e) => f.write_str(e),
             Utf8(ref e) => e.fmt(f),
             Stream(ref e) => e.fmt(f),
         }
@@ -427,7 +1321,7 @@ mod test {
     use crate::server::FieldHeaders;
     use crate::test_util::mock_stream;
 
-    use super::Multipart;
+    use super::{Error, LineEnding, Multipart, TextOrFile};
     use std::convert::Infallible;
 
     const BOUNDARY: &str = "boundary";
@@ -436,62 +1330,1446 @@ mod test {
     fn test_empty_body() {
         let _ = ::env_logger::try_init();
         let multipart = Multipart::with_body(mock_stream(&[]), BOUNDARY);
+        assert_eq!(multipart.boundary(), BOUNDARY);
         pin_mut!(multipart);
         ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
     }
 
     #[test]
-    fn test_no_headers() {
+    fn test_boundary_mid_line_in_preamble() {
+        use crate::test_util::run_future_hot;
+
         let _ = ::env_logger::try_init();
-        let multipart = Multipart::with_body(
-            mock_stream(&[b"--boundary", b"\r\n", b"\r\n", b"--boundary--"]),
-            BOUNDARY,
-        );
-        pin_mut!(multipart);
-        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
-        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
-        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+
+        const BODY: &[u8] = b"this preamble mentions --boundary mid-line and ends --boun\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+            field data\r\n\
+            --boundary--";
+
+        for split in 1..BODY.len() {
+            let chunks = [&BODY[..split], &BODY[split..]];
+            let mut multipart = Multipart::with_body(mock_stream(&chunks), BOUNDARY);
+
+            let field = run_future_hot(multipart.next_field())
+                .unwrap_or_else(|e| panic!("split at {}: {}", split, e))
+                .unwrap();
+            assert_eq!(field.headers.name, "foo", "split at {}", split);
+            assert_eq!(
+                run_future_hot(field.data.read_to_string()).unwrap(),
+                "field data",
+                "split at {}",
+                split
+            );
+            assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+        }
     }
 
     #[test]
-    fn test_single_field() {
+    fn test_preamble_and_epilogue() {
+        use crate::test_util::run_future_hot;
+
         let _ = ::env_logger::try_init();
-        let multipart = Multipart::with_body(
-            mock_stream(&[
-                b"--boundary\r",
-                b"\n",
-                b"Content-Disposition:",
-                b" form-data; name=",
-                b"\"foo\"",
-                b"\r\n\r\n",
-                b"field data",
-                b"\r",
-                b"\n--boundary--",
-            ]),
-            BOUNDARY,
-        );
-        pin_mut!(multipart);
 
-        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        const BODY: &[u8] = b"this is preamble\r\n\
+            with --dashes-- on a second line\r\n\
+            --boundary\r\n\
+            Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+            field data\r\n\
+            --boundary--\r\n\
+            this is epilogue\r\n\
+            --boundary\r\n";
+
+        for split in 1..BODY.len() {
+            let chunks = [&BODY[..split], &BODY[split..]];
+            let mut multipart = Multipart::with_body(mock_stream(&chunks), BOUNDARY);
+
+            let field = run_future_hot(multipart.next_field())
+                .unwrap_or_else(|e| panic!("split at {}: {}", split, e))
+                .unwrap();
+            assert_eq!(field.headers.name, "foo", "split at {}", split);
+            assert_eq!(
+                run_future_hot(field.data.read_to_string()).unwrap(),
+                "field data",
+                "split at {}",
+                split
+            );
+            assert!(
+                run_future_hot(multipart.next_field()).unwrap().is_none(),
+                "split at {}",
+                split
+            );
+        }
+    }
 
-        ready_assert_eq!(
-            |cx| multipart.as_mut().poll_field_headers(cx),
-            Ok(FieldHeaders {
-                name: "foo".into(),
-                filename: None,
-                content_type: None,
-                ext_headers: Default::default(),
-                _backcompat: (),
-            })
-        );
+    #[test]
+    fn test_detected_line_ending() {
+        use crate::test_util::run_future_hot;
 
-        ready_assert_eq!(
-            |cx| multipart.as_mut().poll_field_chunk(cx),
-            Some(Ok(&b"field data"[..]))
-        );
+        let _ = ::env_logger::try_init();
 
+        const CRLF: &[u8] = b"--boundary\r\n\
+            Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+            field data\r\n\
+            --boundary--\r\n";
+
+        for split in 1..CRLF.len() {
+            let chunks = [&CRLF[..split], &CRLF[split..]];
+            let mut multipart = Multipart::with_body(mock_stream(&chunks), BOUNDARY);
+            assert_eq!(multipart.detected_line_ending(), None);
+
+            run_future_hot(multipart.next_field()).unwrap().unwrap();
+            assert_eq!(
+                multipart.detected_line_ending(),
+                Some(LineEnding::Crlf),
+                "split at {}",
+                split
+            );
+        }
+
+        const LF: &[u8] = b"--boundary\n\
+            Content-Disposition: form-data; name=\"foo\"\n\n\
+            field data\n\
+            --boundary--\n";
+
+        let mut multipart = Multipart::with_body(mock_stream(&[LF]), BOUNDARY);
+        assert!(run_future_hot(multipart.next_field()).is_err());
+        assert_eq!(multipart.detected_line_ending(), Some(LineEnding::Lf));
+    }
+
+    #[test]
+    fn test_error_variants() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        fn first_field_err(headers: &[u8], header_limit: usize) -> Error<Infallible> {
+            let chunks = [&b"--boundary\r\n"[..], headers, b"data\r\n--boundary--"];
+            let mut multipart = Multipart::with_body(mock_stream(&chunks), BOUNDARY)
+                .with_header_limit(header_limit);
+
+            run_future_hot(multipart.next_field())
+                .map(|_| ())
+                .unwrap_err()
+        }
+
+        let err = first_field_err(b"Content-Type: text/plain\r\n\r\n", 1024);
+        assert!(
+            matches!(err, Error::MissingContentDisposition(_)),
+            "{:?}",
+            err
+        );
+        assert!(err.is_client_error());
+        // the message is unchanged apart from the offset
+        assert!(
+            err.to_string().starts_with(
+                "error occured while reading multipart body: \
+                 missing `Content-Disposition` header on a field"
+            ),
+            "{}",
+            err
+        );
+        assert!(err.to_string().ends_with("(at byte offset 40)"), "{}", err);
+
+        let err = first_field_err(
+            b"Content-Disposition: attachment; name=\"foo\"\r\n\r\n",
+            1024,
+        );
+        assert!(matches!(err, Error::InvalidHeader(_)), "{:?}", err);
+        assert!(err.is_client_error());
+
+        let err = first_field_err(b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n", 16);
+        assert!(matches!(err, Error::HeadersTooLong(_)), "{:?}", err);
+        assert!(err.is_client_error());
+    }
+
+    #[test]
+    fn test_with_body_bytes_boundary() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body_bytes_boundary(
+            mock_stream(&[
+                b"--'()+_,-./:=?\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"field data",
+                b"\r\n--'()+_,-./:=?--",
+            ]),
+            &b"'()+_,-./:=?"[..],
+        )
+        .unwrap();
+
+        assert_eq!(multipart.boundary(), "'()+_,-./:=?");
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "foo");
+        assert_eq!(
+            run_future_hot(field.data.read_to_string()).unwrap(),
+            "field data"
+        );
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_with_body_bytes_boundary_invalid() {
+        use super::InvalidBoundary;
+
+        let res = Multipart::with_body_bytes_boundary(mock_stream(&[]), &b"bound\xFFary"[..]);
+        assert_eq!(res.err(), Some(InvalidBoundary));
+
+        let res = Multipart::with_body_bytes_boundary(mock_stream(&[]), &b""[..]);
+        assert_eq!(res.err(), Some(InvalidBoundary));
+    }
+
+    #[test]
+    fn test_with_max_fields() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let body = || {
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"foo data",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+                b"bar data",
+                b"\r\n--boundary--",
+            ])
+        };
+
+        let mut multipart = Multipart::with_body(body(), BOUNDARY).with_max_fields(1);
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "foo");
+
+        let err = run_future_hot(multipart.next_field()).unwrap_err();
+        assert!(err.is_client_error());
+        assert!(err.to_string().contains("limit of 1 fields"), "{}", err);
+
+        // exactly at the limit
+        let mut multipart = Multipart::with_body(body(), BOUNDARY).with_max_fields(2);
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_some());
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_some());
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_with_field_size_limit() {
+        use crate::test_util::run_future_hot;
+        use futures_util::TryStreamExt;
+
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"small\"\r\n\r\n",
+                b"12345",
+                b"678",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"large\"\r\n\r\n",
+                b"12345",
+                b"6789",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        )
+        .with_field_size_limit(8);
+
+        // exactly at the limit; the boundaries and headers don't count
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let data: Vec<&[u8]> = run_future_hot(field.data.try_collect()).unwrap();
+        assert_eq!(data.concat(), b"12345678");
+
+        // the count starts over for each field
+        let mut field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(
+            run_future_hot(field.data.try_next()).unwrap(),
+            Some(&b"12345"[..])
+        );
+        assert_eq!(
+            run_future_hot(field.data.try_next()).unwrap(),
+            Some(&b"678"[..])
+        );
+
+        let err = run_future_hot(field.data.try_next()).unwrap_err();
+        assert!(err.is_client_error());
+        assert!(
+            err.to_string().contains("limit of 8 bytes (9 bytes read)"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_set_field_data_limit() {
+        use crate::test_util::run_future_hot;
+        use futures_util::future::poll_fn;
+        use futures_util::io::AsyncReadExt;
+        use std::pin::Pin;
+
+        let _ = ::env_logger::try_init();
+
+        const BODY: &[&[u8]] = &[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"field\"\r\n\r\n",
+            b"12345",
+            b"6789ab",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"next\"\r\n\r\n",
+            b"next",
+            b"\r\n--boundary--",
+        ];
+
+        let mut multipart = Multipart::with_body(mock_stream(BODY), BOUNDARY);
+        multipart.set_field_data_limit(Some(7));
+
+        let poll_chunk = |multipart: &mut Multipart<_>| {
+            run_future_hot(poll_fn(|cx| Pin::new(&mut *multipart).poll_field_chunk(cx)))
+        };
+
+        let _ = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(poll_chunk(&mut multipart).unwrap().unwrap(), b"12345");
+        // the chunk crossing the limit is split exactly at it
+        assert_eq!(poll_chunk(&mut multipart).unwrap().unwrap(), b"67");
+
+        let err = poll_chunk(&mut multipart).unwrap().unwrap_err();
+        assert!(err.is_client_error());
+        assert!(
+            err.to_string().contains("limit of 7 bytes (11 bytes read)"),
+            "{}",
+            err
+        );
+
+        // the rest of the chunk was kept
+        multipart.set_field_data_limit(None);
+        assert_eq!(poll_chunk(&mut multipart).unwrap().unwrap(), b"89ab");
+        assert!(poll_chunk(&mut multipart).is_none());
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "next");
+
+        // reading through `FieldReader` stops at the same place
+        let mut multipart =
+            Multipart::with_body(mock_stream(BODY), BOUNDARY).with_field_size_limit(7);
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let mut reader = field.data.into_async_buf_read();
+
+        let mut data = Vec::new();
+        let mut buf = [0; 3];
+
+        let err = loop {
+            match run_future_hot(reader.read(&mut buf)) {
+                Ok(read) => data.extend_from_slice(&buf[..read]),
+                Err(e) => break e,
+            }
+        };
+
+        assert_eq!(data, b"1234567");
+        assert!(err.to_string().contains("limit of 7 bytes"), "{}", err);
+    }
+
+    #[test]
+    fn test_with_total_limit() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let body = || {
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"first\"\r\n\r\n",
+                b"12345",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"second\"\r\n\r\n",
+                b"12345",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"third\"\r\n\r\n",
+                b"\r\n--boundary--",
+            ])
+        };
+
+        // each field is under the per-field limit but together they exceed the total
+        let mut multipart = Multipart::with_body(body(), BOUNDARY)
+            .with_field_size_limit(5)
+            .with_total_limit(8);
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(
+            run_future_hot(field.data.read_to_string()).unwrap(),
+            "12345"
+        );
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let err = run_future_hot(field.data.read_to_string()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("total limit of 8 bytes (10 bytes read)"),
+            "{}",
+            err
+        );
+
+        // the count survives the transition to the next field
+        let err = run_future_hot(multipart.next_field()).unwrap_err();
+        assert!(err.to_string().contains("total limit"), "{}", err);
+
+        // exactly at the limit
+        let mut multipart = Multipart::with_body(body(), BOUNDARY).with_total_limit(10);
+
+        while let Some(field) = run_future_hot(multipart.next_field()).unwrap() {
+            run_future_hot(field.data.read_to_string()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_forbid_field_data() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        fn body(data: &'static [u8]) -> [&'static [u8]; 6] {
+            [
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"empty\"\r\n\r\n",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n",
+                data,
+                b"\r\n--boundary--",
+            ]
+        }
+
+        let empty = body(b"");
+        let with_data = body(b"data");
+
+        // empty fields pass whether or not their data is read
+        for &read_data in &[false, true] {
+            let mut multipart =
+                Multipart::with_body(mock_stream(&empty), BOUNDARY).forbid_field_data(true);
+
+            while let Some(field) = run_future_hot(multipart.next_field()).unwrap() {
+                if read_data {
+                    assert_eq!(run_future_hot(field.data.read_to_string()).unwrap(), "");
+                }
+            }
+        }
+
+        // reading the data
+        let mut multipart =
+            Multipart::with_body(mock_stream(&with_data), BOUNDARY).forbid_field_data(true);
+        run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let err = run_future_hot(field.data.read_to_string()).unwrap_err();
+        assert!(err.to_string().contains("not allowed"), "{}", err);
+
+        // skipping the data
+        let mut multipart =
+            Multipart::with_body(mock_stream(&with_data), BOUNDARY).forbid_field_data(true);
+        run_future_hot(multipart.next_field()).unwrap().unwrap();
+        run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let err = run_future_hot(multipart.next_field()).unwrap_err();
+        assert!(err.to_string().contains("not allowed"), "{}", err);
+
+        // skipping the headers as well; they aren't data
+        let multipart =
+            Multipart::with_body(mock_stream(&empty), BOUNDARY).forbid_field_data(true);
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+
+        let multipart =
+            Multipart::with_body(mock_stream(&with_data), BOUNDARY).forbid_field_data(true);
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let err = until_ready!(|cx| multipart.as_mut().poll_has_next_field(cx)).unwrap_err();
+        assert!(err.to_string().contains("not allowed"), "{}", err);
+    }
+
+    #[test]
+    fn test_expect_first_field() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        const BODY: &[&[u8]] = &[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"token\"\r\n\r\n",
+            b"secret",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n",
+            b"file data",
+            b"\r\n--boundary--",
+        ];
+
+        let mut multipart =
+            Multipart::with_body(mock_stream(BODY), BOUNDARY).expect_first_field("token");
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "token");
+        // only the first field is checked
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "file");
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+
+        let mut multipart =
+            Multipart::with_body(mock_stream(BODY), BOUNDARY).expect_first_field("file");
+
+        let err = run_future_hot(multipart.next_field()).unwrap_err();
+        assert!(err.is_client_error());
+        assert!(
+            err.to_string()
+                .contains("expected the first field to be \"file\" but found \"token\""),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_expect_first_field_after_charset() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        const BODY: &[&[u8]] = &[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"_charset_\"\r\n\r\n",
+            b"UTF-8",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"token\"\r\n\r\n",
+            b"secret",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\r\n",
+            b"file data",
+            b"\r\n--boundary--",
+        ];
+
+        let mut multipart = Multipart::with_body(mock_stream(BODY), BOUNDARY)
+            .with_charset_field(true)
+            .expect_first_field("token");
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "token");
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "file");
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+        assert_eq!(multipart.default_charset(), Some("UTF-8"));
+
+        // the field after the captured one is still checked
+        let mut multipart = Multipart::with_body(mock_stream(BODY), BOUNDARY)
+            .with_charset_field(true)
+            .expect_first_field("file");
+
+        let err = run_future_hot(multipart.next_field()).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("expected the first field to be \"file\" but found \"token\""),
+            "{}",
+            err
+        );
+
+        // without capturing, `_charset_` is an ordinary first field
+        let mut multipart =
+            Multipart::with_body(mock_stream(BODY), BOUNDARY).expect_first_field("token");
+        assert!(run_future_hot(multipart.next_field()).is_err());
+    }
+
+    #[test]
+    fn test_text_content_types() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        const BODY: &[&[u8]] = &[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"json\"\r\n",
+            b"Content-Type: application/json\r\n\r\n",
+            b"{\"foo\": 1}",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"text\"\r\n",
+            b"Content-Type: text/plain\r\n\r\n",
+            b"bar",
+            b"\r\n--boundary--",
+        ];
+
+        let mut multipart = Multipart::with_body(mock_stream(BODY), BOUNDARY);
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert!(!field.headers.is_text());
+        assert!(field.headers.is_file());
+
+        let mut multipart = Multipart::with_body(mock_stream(BODY), BOUNDARY)
+            .text_content_types(|ct| ct.type_() == mime::TEXT || ct.subtype() == mime::JSON);
+
+        match run_future_hot(multipart.next_text_field(1024))
+            .unwrap()
+            .unwrap()
+        {
+            TextOrFile::Text(headers, text) => {
+                assert!(headers.is_text_field());
+                assert_eq!(text, "{\"foo\": 1}");
+            }
+            TextOrFile::File(field) => panic!("expected text field: {:?}", field.headers),
+        }
+
+        match run_future_hot(multipart.next_text_field(1024))
+            .unwrap()
+            .unwrap()
+        {
+            TextOrFile::Text(headers, text) => {
+                assert_eq!(headers.name, "text");
+                assert_eq!(text, "bar");
+            }
+            TextOrFile::File(field) => panic!("expected text field: {:?}", field.headers),
+        }
+
+        // the predicate replaces the default rule rather than adding to it
+        let mut multipart = Multipart::with_body(mock_stream(BODY), BOUNDARY)
+            .text_content_types(|ct| ct.subtype() == mime::JSON);
+        let _ = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert!(!field.headers.is_text());
+    }
+
+    #[test]
+    fn test_with_charset_field() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let body = || {
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"_charset_\"\r\n\r\n",
+                b"windows",
+                b"-1252",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"text\"\r\n\r\n",
+                b"caf\xE9",
+                b"\r\n--boundary--",
+            ])
+        };
+
+        // not captured by default
+        let mut multipart = Multipart::with_body(body(), BOUNDARY);
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "_charset_");
+        assert_eq!(field.data.default_charset(), None);
+
+        let mut multipart = Multipart::with_body(body(), BOUNDARY).with_charset_field(true);
+        assert_eq!(multipart.default_charset(), None);
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "text");
+        assert_eq!(field.data.default_charset(), Some("windows-1252"));
+
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+        assert_eq!(multipart.default_charset(), Some("windows-1252"));
+    }
+
+    #[test]
+    fn test_headers_and_data_in_same_chunk() {
+        let _ = ::env_logger::try_init();
+
+        // (chunks, first data chunk)
+        let cases: &[(&[&[u8]], &[u8])] = &[
+            // the whole field in one chunk after the boundary
+            (
+                &[
+                    b"--boundary\r\n",
+                    b"Content-Disposition: form-data; name=\"foo\"\r\n\r\nfield data",
+                    b"\r\n--boundary--",
+                ],
+                b"field data",
+            ),
+            // the whole body in one chunk
+            (
+                &[b"--boundary\r\n\
+                    Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+                    field data\r\n--boundary--"],
+                b"field data",
+            ),
+            // headers accumulated from a previous chunk
+            (
+                &[
+                    b"--boundary\r\n",
+                    b"Content-Disposition: form-data;",
+                    b" name=\"foo\"\r\n\r\nfield",
+                    b" data\r\n--boundary--",
+                ],
+                b"field",
+            ),
+            // double-CRLF split between chunks
+            (
+                &[
+                    b"--boundary\r\n",
+                    b"Content-Disposition: form-data; name=\"foo\"\r\n\r",
+                    b"\nfield",
+                    b" data\r\n--boundary--",
+                ],
+                b"field",
+            ),
+        ];
+
+        for &(chunks, first) in cases {
+            let multipart = Multipart::with_body(mock_stream(chunks), BOUNDARY);
+            pin_mut!(multipart);
+
+            ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+
+            let headers = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+            assert_eq!(headers.name, "foo");
+
+            ready_assert_eq!(
+                |cx| multipart.as_mut().poll_field_chunk(cx),
+                Some(Ok(first))
+            );
+
+            let mut data = first.to_vec();
+
+            while let Some(chunk) = until_ready!(|cx| multipart.as_mut().poll_field_chunk(cx)) {
+                data.extend_from_slice(chunk.unwrap());
+            }
+
+            assert_eq!(data, b"field data", "chunks: {:?}", chunks);
+            ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+        }
+    }
+
+    #[test]
+    fn test_no_fields() {
+        let _ = ::env_logger::try_init();
+
+        let bodies: &[&[&[u8]]] = &[
+            &[b"--boundary--\r\n"],
+            &[b"--boundary--"],
+            &[b"--bound", b"ary--\r\n"],
+            &[b"--boundary", b"--", b"\r\n"],
+            &[b"--boundary-", b"-"],
+        ];
+
+        for body in bodies {
+            let multipart = Multipart::with_body(mock_stream(body), BOUNDARY);
+            pin_mut!(multipart);
+            ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+            ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+        }
+    }
+
+    #[test]
+    fn test_map_ok() {
+        use bytes::Bytes;
+        use futures::TryStreamExt;
+        use std::pin::Pin;
+
+        let _ = ::env_logger::try_init();
+
+        let body = mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\nfield ",
+            b"data\r",
+            b"\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+            b"bar data",
+            b"\r\n--boundary--",
+        ])
+        .map_ok(<[u8]>::to_vec);
+
+        let mut multipart = Multipart::with_body(body, BOUNDARY);
+
+        {
+            let mut multipart = Pin::new(&mut multipart);
+            ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+            until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+        }
+
+        // `field ` was pushed back after the headers
+        let mut multipart = multipart.map_ok(Bytes::from);
+        let mut multipart = Pin::new(&mut multipart);
+
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(Bytes::from_static(b"field ")))
+        );
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(Bytes::from_static(b"data")))
+        );
+        ready_assert_eq!(|cx| multipart.as_mut().poll_field_chunk(cx), None);
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_headers(cx),
+            Ok(FieldHeaders {
+                name: "bar".into(),
+                ..FieldHeaders::default()
+            })
+        );
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(Bytes::from_static(b"bar data")))
+        );
+        ready_assert_eq!(|cx| multipart.as_mut().poll_field_chunk(cx), None);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+    }
+
+    #[test]
+    fn test_next_text_field() {
+        use crate::server::TextOrFile;
+        use crate::test_util::run_future_hot;
+        use futures::TryStreamExt;
+
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"title\"\r\n\r\n",
+                b"My Upload",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n",
+                b"Content-Type: text/plain\r\n\r\n",
+                b"file contents",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"description\"\r\n",
+                b"Content-Type: text/plain; charset=utf-8\r\n\r\n",
+                b"some notes",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+
+        match run_future_hot(multipart.next_text_field(1024)) {
+            Ok(Some(TextOrFile::Text(headers, text))) => {
+                assert_eq!(headers.name, "title");
+                assert_eq!(text, "My Upload");
+            }
+            other => panic!("expected text field, got {:?}", other),
+        }
+
+        match run_future_hot(multipart.next_text_field(1024)) {
+            Ok(Some(TextOrFile::File(field))) => {
+                assert_eq!(field.headers.name, "file");
+                let data: Vec<&[u8]> = run_future_hot(field.data.try_collect()).unwrap();
+                assert_eq!(data.concat(), b"file contents");
+            }
+            other => panic!("expected file field, got {:?}", other),
+        }
+
+        match run_future_hot(multipart.next_text_field(1024)) {
+            Ok(Some(TextOrFile::Text(headers, text))) => {
+                assert_eq!(headers.name, "description");
+                assert_eq!(text, "some notes");
+            }
+            other => panic!("expected text field, got {:?}", other),
+        }
+
+        assert!(run_future_hot(multipart.next_text_field(1024))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_next_text_field_limit() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"title\"\r\n\r\n",
+                b"My ",
+                b"Upload",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+
+        let err = run_future_hot(multipart.next_text_field(4)).unwrap_err();
+        assert!(err.is_client_error());
+        assert!(
+            err.to_string()
+                .contains("field data exceeds the limit of 4 bytes"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_next_text_field_default_charset() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"_charset_\"\r\n\r\n",
+                b"windows-1252",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"title\"\r\n\r\n",
+                b"caf\xE9",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        )
+        .with_charset_field(true);
+
+        match run_future_hot(multipart.next_text_field(1024)) {
+            #[cfg(feature = "charset")]
+            Ok(Some(TextOrFile::Text(headers, text))) => {
+                assert_eq!(headers.name, "title");
+                assert_eq!(text, "caf\u{E9}");
+            }
+            #[cfg(not(feature = "charset"))]
+            Ok(Some(TextOrFile::File(field))) => assert_eq!(field.headers.name, "title"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_from_request_empty_boundary() {
+        let request = |content_type: &str| {
+            http::Request::post("/upload")
+                .header(http::header::CONTENT_TYPE, content_type)
+                .body(mock_stream(crate::test_util::TEST_SINGLE_FIELD))
+                .unwrap()
+        };
+
+        for content_type in &[
+            "multipart/form-data; boundary=",
+            "multipart/form-data; boundary=\"\"",
+            "multipart/form-data",
+        ] {
+            let req = Multipart::try_from_request(request(content_type))
+                .err()
+                .unwrap_or_else(|| panic!("accepted {:?}", content_type));
+            // the request is returned intact
+            assert_eq!(req.headers()[http::header::CONTENT_TYPE], *content_type);
+        }
+
+        let multipart =
+            Multipart::try_from_request(request("multipart/form-data; boundary=boundary"))
+                .unwrap_or_else(|_| panic!("rejected a valid boundary"));
+        assert_eq!(multipart.debug_state().boundary, "--boundary");
+    }
+
+    #[test]
+    fn test_try_from_request_any_method() {
+        let request = |method: http::Method| {
+            http::Request::builder()
+                .method(method)
+                .uri("/upload")
+                .header(
+                    http::header::CONTENT_TYPE,
+                    "multipart/form-data; boundary=boundary",
+                )
+                .body(mock_stream(crate::test_util::TEST_SINGLE_FIELD))
+                .unwrap()
+        };
+
+        for method in &[http::Method::PUT, http::Method::PATCH] {
+            let req = Multipart::try_from_request(request(method.clone()))
+                .err()
+                .unwrap_or_else(|| panic!("accepted {}", method));
+            assert_eq!(req.method(), method);
+
+            let multipart = Multipart::try_from_request_any_method(request(method.clone()))
+                .unwrap_or_else(|_| panic!("rejected {}", method));
+            assert_eq!(multipart.boundary(), "boundary");
+        }
+
+        let req = http::Request::put("/upload")
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .body(mock_stream(crate::test_util::TEST_SINGLE_FIELD))
+            .unwrap();
+        assert!(Multipart::try_from_request_any_method(req).is_err());
+    }
+
+    #[test]
+    fn test_try_from_request_not_form_data() {
+        let request = |method: &str, content_type: &str| {
+            http::Request::builder()
+                .method(method)
+                .uri("/upload")
+                .header(http::header::CONTENT_TYPE, content_type)
+                .body(mock_stream(crate::test_util::TEST_SINGLE_FIELD))
+                .unwrap()
+        };
+
+        // a boundary alone isn't enough
+        for content_type in &[
+            "multipart/mixed; boundary=boundary",
+            "text/plain; boundary=boundary",
+        ] {
+            assert!(Multipart::try_from_request(request("POST", content_type)).is_err());
+            assert!(Multipart::try_from_request_any_method(request("PUT", content_type)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_from_content_type() {
+        use super::NoBoundary;
+
+        let content_type: mime::Mime = "multipart/form-data; boundary=\"boundary\""
+            .parse()
+            .unwrap();
+        let multipart = Multipart::from_content_type(
+            mock_stream(crate::test_util::TEST_SINGLE_FIELD),
+            &content_type,
+        )
+        .unwrap();
+        assert_eq!(multipart.boundary(), "boundary");
+
+        for content_type in &["multipart/form-data", "multipart/form-data; boundary="] {
+            let content_type: mime::Mime = content_type.parse().unwrap();
+            let res = Multipart::from_content_type(
+                mock_stream(crate::test_util::TEST_SINGLE_FIELD),
+                &content_type,
+            );
+            assert_eq!(res.err(), Some(NoBoundary));
+        }
+    }
+
+    #[test]
+    fn test_into_single_field() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let body = || {
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"file\"; filename=\"upload.txt\"\r\n",
+                b"Content-Type: text/plain\r\n\r\n",
+                b"uploaded ",
+                b"file contents",
+                b"\r\n--boundary--",
+            ])
+        };
+
+        let field = run_future_hot(Multipart::with_body(body(), BOUNDARY).into_single_field(1024))
+            .unwrap()
+            .unwrap();
+        assert_eq!(field.headers.name, "file");
+        assert_eq!(field.headers.filename.as_deref(), Some("upload.txt"));
+        assert_eq!(field.headers.content_type, Some(mime::TEXT_PLAIN));
+        assert_eq!(field.data, b"uploaded file contents");
+
+        let err = run_future_hot(Multipart::with_body(body(), BOUNDARY).into_single_field(16))
+            .unwrap_err();
+        assert!(err.is_client_error());
+        assert!(err.to_string().contains("limit of 16 bytes"), "{}", err);
+
+        let empty = Multipart::with_body(mock_stream(&[b"--boundary--"]), BOUNDARY);
+        assert!(run_future_hot(empty.into_single_field(1024))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_with_header_limit() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let body = || {
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n",
+                b"X-Padding: ",
+                &[b'x'; 64],
+                b"\r\n",
+                b"\r\nfield data\r\n--boundary--",
+            ])
+        };
+
+        let mut multipart = Multipart::with_body(body(), BOUNDARY).with_header_limit(64);
+        let err = run_future_hot(multipart.next_field()).unwrap_err();
+        assert!(err.to_string().contains("limit of 64 bytes"), "{}", err);
+
+        let mut multipart = Multipart::with_body(body(), BOUNDARY).with_header_limit(256);
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "foo");
+    }
+
+    #[test]
+    fn test_is_consumed() {
+        use crate::test_util::run_future_hot;
+
+        let _ = ::env_logger::try_init();
+
+        let mut multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"field data",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        assert!(!multipart.is_consumed());
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(
+            run_future_hot(field.data.read_to_string()).unwrap(),
+            "field data"
+        );
+        assert!(!multipart.is_consumed());
+
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+        assert!(multipart.is_consumed());
+    }
+
+    #[test]
+    fn test_no_headers() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[b"--boundary", b"\r\n", b"\r\n", b"--boundary--"]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+    }
+
+    #[test]
+    fn test_boundary_in_headers() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n",
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+                b"field data",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_headers(cx),
+            Err(Error::BoundaryMismatch(
+                "boundary found inside header section: \
+                 \"Content-Disposition: form-data; name=\"foo\"\" (at byte offset 56)"
+                    .into()
+            ))
+        );
+
+        // the following field can still be read
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let headers = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+        assert_eq!(headers.name, "bar");
+
+        // the end of the stream is still reported as such
+        let multipart = Multipart::with_body(
+            mock_stream(&[b"--boundary\r\n", b"Content-Disposition: form-data\r\n"]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let err = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
+        assert!(err.is_truncation(), "{}", err);
+    }
+
+    #[test]
+    fn test_raw_field_headers() {
+        use bytes::Bytes;
+
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n",
+                b"Content-ID: <foo@example.com>\r",
+                b"\n\r\nfield data",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+                b"bar data",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+
+        // the first boundary is consumed automatically
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_raw_field_headers(cx),
+            Ok(Bytes::from_static(
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\
+                  Content-ID: <foo@example.com>\r\n\r\n"
+            ))
+        );
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"field data"[..]))
+        );
+        ready_assert_eq!(|cx| multipart.as_mut().poll_field_chunk(cx), None);
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let headers = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+        assert_eq!(headers.name, "bar");
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"bar data"[..]))
+        );
+    }
+
+    #[test]
+    fn test_single_field() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r",
+                b"\n",
+                b"Content-Disposition:",
+                b" form-data; name=",
+                b"\"foo\"",
+                b"\r\n\r\n",
+                b"field data",
+                b"\r",
+                b"\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_headers(cx),
+            Ok(FieldHeaders {
+                name: "foo".into(),
+                filename: None,
+                content_type: None,
+                ext_headers: Default::default(),
+                ..FieldHeaders::default()
+            })
+        );
+
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"field data"[..]))
+        );
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_field_chunk(cx), None);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+    }
+
+    #[test]
+    fn test_headers_before_has_next_field() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"field data",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_headers(cx),
+            Ok(FieldHeaders {
+                name: "foo".into(),
+                filename: None,
+                content_type: None,
+                ext_headers: Default::default(),
+                ..FieldHeaders::default()
+            })
+        );
+
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"field data"[..]))
+        );
+        ready_assert_eq!(|cx| multipart.as_mut().poll_field_chunk(cx), None);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+    }
+
+    #[test]
+    fn test_headers_before_has_next_field_empty() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(mock_stream(&[]), BOUNDARY);
+        pin_mut!(multipart);
+        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
+    }
+
+    #[test]
+    fn test_error_predicates() {
+        let _ = ::env_logger::try_init();
+
+        let multipart = Multipart::with_body(
+            mock_stream(&[b"--boundary", b"\r\n", b"\r\n", b"--boundary--"]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let malformed = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
+        assert!(malformed.is_client_error());
+        assert!(!malformed.is_truncation());
+        assert!(!malformed.is_io());
+
+        let multipart = Multipart::with_body(
+            mock_stream(&[b"--boundary\r\n", b"Content-Disposition: form-"]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let truncated = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
+        assert!(truncated.is_truncation());
+        assert!(!truncated.is_client_error());
+        assert!(!truncated.is_io());
+
+        let io = Error::Stream("connection reset");
+        assert!(io.is_io());
+        assert!(!io.is_client_error());
+        assert!(!io.is_truncation());
+    }
+
+    #[test]
+    fn test_debug_state() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"field data",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+
+        let state = multipart.debug_state();
+        assert_eq!(state.fields, 1);
+        assert_eq!(state.bytes_read, 68);
+        assert_eq!(state.boundary, "--boundary");
+        assert_eq!(state.headers_buffered, 0);
+        assert_eq!(state.chunk_pushed, None);
+
+        let formatted = format!("{:?}", state);
+        assert!(
+            formatted.contains("State::Watching, pending chunks: 1"),
+            "{}",
+            formatted
+        );
+    }
+
+    #[test]
+    fn test_next_field_undrained() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\nfield ",
+                b"data",
+                b"\r\n--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+                b"bar data",
+                b"\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        assert!(multipart.current_field_done());
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        assert!(!multipart.current_field_done());
+        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+        // `field ` is buffered after the headers
+        assert!(!multipart.current_field_done());
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_headers(cx),
+            Ok(FieldHeaders {
+                name: "bar".into(),
+                ..FieldHeaders::default()
+            })
+        );
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"bar data"[..]))
+        );
         ready_assert_eq!(|cx| multipart.as_mut().poll_field_chunk(cx), None);
+        assert!(multipart.current_field_done());
+
         ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(false));
+        assert!(multipart.current_field_done());
+    }
+
+    #[test]
+    fn test_current_field_done_overflow() {
+        let _ = ::env_logger::try_init();
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"123456789\r\n--boundary--",
+            ]),
+            BOUNDARY,
+        )
+        .with_field_size_limit(8);
+        pin_mut!(multipart);
+
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"12345678"[..]))
+        );
+        // the boundary has been found, but `9` is still held past the limit
+        assert!(!multipart.current_field_done());
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        let _ = ::env_logger::try_init();
+
+        let multipart = Multipart::with_body(
+            mock_stream(&[
+                b"--boundary\r\n",
+                b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+                b"field data",
+                b"\r\n--bou",
+            ]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap();
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Ok(&b"field data"[..]))
+        );
+        ready_assert_eq!(
+            |cx| multipart.as_mut().poll_field_chunk(cx),
+            Some(Err(Error::UnexpectedEof(
+                "unable to verify multipart boundary; expected: \"--boundary\" \
+                 found: \"\\r\\n--bou\" (at byte offset 68)"
+                    .into()
+            )))
+        );
+
+        let multipart = Multipart::with_body(
+            mock_stream(&[b"--boundary\r\n", b"Content-Disposition: form"]),
+            BOUNDARY,
+        );
+        pin_mut!(multipart);
+        ready_assert_eq!(|cx| multipart.as_mut().poll_has_next_field(cx), Ok(true));
+        let err = until_ready!(|cx| multipart.as_mut().poll_field_headers(cx)).unwrap_err();
+        assert!(err.is_truncation());
+        assert!(err.to_string().ends_with("(at byte offset 37)"), "{}", err);
     }
 
     #[test]
@@ -535,7 +2813,7 @@ mod test {
                 filename: None,
                 content_type: None,
                 ext_headers: Default::default(),
-                _backcompat: (),
+                ..FieldHeaders::default()
             })
         );
 
@@ -554,7 +2832,7 @@ mod test {
                 filename: Some("foo.txt".into()),
                 content_type: Some(mime::TEXT_PLAIN_UTF_8),
                 ext_headers: Default::default(),
-                _backcompat: (),
+                ..FieldHeaders::default()
             })
         );
 
@@ -0,0 +1,99 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::ptr;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use futures_core::{Future, Stream};
+
+use super::{BufferedField, Error, Multipart};
+
+/// Parse a complete `multipart/form-data` body held in memory, buffering the data of every field.
+///
+/// This runs the same parser as [`Multipart`](struct.Multipart.html) to completion without an
+/// executor, for CLI tools and tests. `boundary` is as passed to
+/// [`Multipart::with_body()`](struct.Multipart.html#method.with_body).
+///
+/// Returns an error if the body is malformed or ends before the closing boundary.
+pub fn parse_all(body: &[u8], boundary: &str) -> Result<Vec<BufferedField>, Error<Infallible>> {
+    let parse = async {
+        let mut multipart = Multipart::with_body(Once(Some(body)), boundary);
+        let mut fields = Vec::new();
+
+        while let Some(field) = multipart.next_field().await? {
+            let data = field.data.collect_vec().await?;
+
+            fields.push(BufferedField {
+                headers: field.headers,
+                data,
+            });
+        }
+
+        Ok(fields)
+    };
+
+    pin_mut!(parse);
+
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+
+    match parse.poll(&mut cx) {
+        Poll::Ready(res) => res,
+        // the body stream never returns `Pending` so neither does the parser
+        Poll::Pending => unreachable!("parsing an in-memory body returned `Pending`"),
+    }
+}
+
+/// Yields the whole body as a single chunk.
+struct Once<'a>(Option<&'a [u8]>);
+
+impl<'a> Stream for Once<'a> {
+    type Item = Result<&'a [u8], Infallible>;
+
+    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+        Poll::Ready(self.0.take().map(Ok))
+    }
+}
+
+fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(|_| raw_waker(), |_| (), |_| (), |_| ());
+
+    fn raw_waker() -> RawWaker {
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+
+    // SAFETY: the vtable functions do nothing and never dereference the data pointer
+    unsafe { Waker::from_raw(raw_waker()) }
+}
+
+#[test]
+fn test_parse_all() {
+    let _ = ::env_logger::try_init();
+
+    let body = b"--boundary\r\n\
+        Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+        field data\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"bar\"; filename=\"bar.bin\"\r\n\
+        Content-Type: application/octet-stream\r\n\r\n\
+        \x00\x01\r\n\x02\r\n\
+        --boundary--\r\n";
+
+    let fields = parse_all(body, "boundary").unwrap();
+    assert_eq!(fields.len(), 2);
+
+    assert_eq!(fields[0].headers.name, "foo");
+    assert_eq!(fields[0].data, b"field data");
+
+    assert_eq!(fields[1].headers.name, "bar");
+    assert_eq!(fields[1].headers.filename.as_deref(), Some("bar.bin"));
+    assert_eq!(fields[1].data, b"\x00\x01\r\n\x02");
+
+    let err = parse_all(&body[..body.len() / 2], "boundary").unwrap_err();
+    assert!(err.is_truncation(), "{}", err);
+}
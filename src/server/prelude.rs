@@ -0,0 +1,36 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! The types and traits needed to read most requests, for glob importing.
+//!
+//! ```rust
+//! use multipart_async::server::prelude::*;
+//!
+//! async fn print_fields(body: &[u8]) -> Result<(), Error<std::io::Error>> {
+//!     let mut multipart = Multipart::with_reader(body, "boundary");
+//!
+//!     while let Some(field) = multipart.next_field().await? {
+//!         if field.headers.is_text() {
+//!             println!("{}: {}", field.headers.name, field.data.read_to_string().await?);
+//!         } else {
+//!             let data = field.data.collect_vec().limit(1024 * 1024).await?;
+//!             println!("{}: {} bytes", field.headers.name, data.len());
+//!         }
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! `Result` is deliberately not exported as it would shadow the one in the standard prelude.
+pub use crate::BodyChunk;
+
+pub use super::{BufferedField, Error, Field, FieldData, FieldHeaders, Multipart, TextOrFile};
+
+#[cfg(feature = "hyper_014")]
+pub use super::hyper::HyperRequestExt;
+#[cfg(feature = "tide")]
+pub use super::tide::TideRequestExt;
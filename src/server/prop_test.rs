@@ -0,0 +1,117 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! Property tests feeding randomly generated, randomly chunked bodies through `Multipart`.
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+use futures_util::TryStreamExt;
+use memchr::memmem;
+
+use crate::server::Multipart;
+use crate::test_util::{mock_stream, run_future_hot};
+
+const BOUNDARY: &str = "boundary";
+
+/// Byte sequences that are likely to trip up the boundary finder when they appear in field data.
+const TRICKY_BYTES: &[&[u8]] = &[
+    b"\r",
+    b"\n",
+    b"\r\n",
+    b"-",
+    b"--",
+    b"--b",
+    b"--bound",
+    b"--boundar",
+    b"boundary",
+    b"\r\n--",
+    b"\r\n--bou",
+    b"\r\n\r\n",
+];
+
+/// The smallest chunk size generated; single-byte chunks exercise boundaries and headers
+/// spanning many chunks.
+const MIN_CHUNK_SIZE: usize = 1;
+const MAX_CHUNK_SIZE: usize = 64;
+
+fn field_data() -> impl Strategy<Value = Vec<u8>> {
+    let token = prop_oneof![
+        vec(any::<u8>(), 1..8),
+        proptest::sample::select(TRICKY_BYTES).prop_map(<[u8]>::to_vec),
+    ];
+
+    vec(token, 0..16)
+        .prop_map(|tokens| tokens.concat())
+        .prop_filter("field data must not contain the boundary", |data| {
+            memmem::find(data, b"--boundary").is_none()
+        })
+}
+
+fn fields() -> impl Strategy<Value = Vec<(String, Vec<u8>)>> {
+    vec(("[a-zA-Z0-9_-]{1,16}", field_data()), 0..6)
+}
+
+fn write_body(fields: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    for (name, data) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n",
+                BOUNDARY, name
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+
+    body.extend_from_slice(format!("--{}--\r\n", BOUNDARY).as_bytes());
+    body
+}
+
+/// Split `body` into chunks, cycling through `sizes` for the length of each.
+fn split_body<'a>(body: &'a [u8], sizes: &[usize]) -> Vec<&'a [u8]> {
+    let mut chunks = Vec::new();
+    let mut rem = body;
+
+    for &size in sizes.iter().cycle() {
+        if rem.is_empty() {
+            break;
+        }
+
+        let (chunk, rest) = rem.split_at(size.min(rem.len()));
+        chunks.push(chunk);
+        rem = rest;
+    }
+
+    chunks
+}
+
+fn parse_body(chunks: &[&[u8]]) -> Vec<(String, Vec<u8>)> {
+    let mut multipart = Multipart::with_body(mock_stream(chunks), BOUNDARY);
+    let mut parsed = Vec::new();
+
+    while let Some(field) = run_future_hot(multipart.next_field()).unwrap() {
+        let chunks: Vec<&[u8]> = run_future_hot(field.data.try_collect()).unwrap();
+        parsed.push((field.headers.name, chunks.concat()));
+    }
+
+    parsed
+}
+
+proptest! {
+    #[test]
+    fn test_random_chunking(
+        fields in fields(),
+        sizes in vec(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE, 1..8),
+    ) {
+        let body = write_body(&fields);
+        let chunks = split_body(&body, &sizes);
+
+        prop_assert_eq!(parse_body(&chunks), fields);
+    }
+}
@@ -0,0 +1,151 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::{fmt, io};
+
+use futures_core::Stream;
+use futures_io::AsyncRead;
+
+use super::Multipart;
+
+/// Configuration for reading a request body from an `AsyncRead`.
+///
+/// See [`Multipart::with_reader_config()`](struct.Multipart.html#method.with_reader_config).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AsyncReadConfig {
+    /// The maximum number of bytes requested from the reader at once, and so the maximum size
+    /// of each chunk yielded by the field data streams.
+    ///
+    /// A buffer of this size is allocated once and reused for every read, with only the bytes
+    /// read copied out into each chunk, so larger sizes use more memory per request while
+    /// smaller sizes need more calls to the reader (and so more syscalls, if it is a socket or
+    /// file) for the same body.
+    ///
+    /// Defaults to 16 KiB. Must not be zero.
+    pub buf_size: usize,
+}
+
+impl Default for AsyncReadConfig {
+    fn default() -> Self {
+        AsyncReadConfig {
+            buf_size: 16 * 1024,
+        }
+    }
+}
+
+/// A `Stream` of chunks read from an `AsyncRead`.
+///
+/// Constructed by [`Multipart::with_reader()`](struct.Multipart.html#method.with_reader).
+pub struct ReadStream<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: fmt::Debug> fmt::Debug for ReadStream<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReadStream")
+            .field("reader", &self.reader)
+            .field("buf_size", &self.buf.len())
+            .finish()
+    }
+}
+
+impl<R: AsyncRead + Unpin> ReadStream<R> {
+    fn new(reader: R, config: AsyncReadConfig) -> Self {
+        assert_ne!(
+            config.buf_size, 0,
+            "AsyncReadConfig::buf_size cannot be zero"
+        );
+
+        ReadStream {
+            reader,
+            buf: vec![0; config.buf_size],
+        }
+    }
+
+    /// Get a reference to the wrapped reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for ReadStream<R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        let read = ready!(Pin::new(&mut this.reader).poll_read(cx, &mut this.buf)?);
+
+        if read == 0 {
+            return Poll::Ready(None);
+        }
+
+        Poll::Ready(Some(Ok(this.buf[..read].to_vec())))
+    }
+}
+
+impl<R: AsyncRead + Unpin> Multipart<ReadStream<R>> {
+    /// Construct a new `Multipart` reading the request body from `reader`, with the default
+    /// [`AsyncReadConfig`](struct.AsyncReadConfig.html).
+    ///
+    /// See [`::with_body()`](#method.with_body) for the handling of `boundary`.
+    pub fn with_reader<B: Into<String>>(reader: R, boundary: B) -> Self {
+        Self::with_reader_config(reader, boundary, AsyncReadConfig::default())
+    }
+
+    /// Construct a new `Multipart` reading the request body from `reader` using the given
+    /// configuration.
+    ///
+    /// ### Panics
+    /// If `config.buf_size` is zero.
+    pub fn with_reader_config<B: Into<String>>(
+        reader: R,
+        boundary: B,
+        config: AsyncReadConfig,
+    ) -> Self {
+        Multipart::with_body(ReadStream::new(reader, config), boundary)
+    }
+}
+
+#[test]
+fn test_with_reader_buf_sizes() {
+    use crate::test_util::run_future_hot;
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    const BODY: &[u8] = b"--boundary\r\n\
+        Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+        field data\r\n\
+        --boundary\r\n\
+        Content-Disposition: form-data; name=\"bar\"; filename=\"bar.txt\"\r\n\r\n\
+        bar data\r\n\
+        --boundary--\r\n";
+
+    for &buf_size in &[1, 64 * 1024] {
+        let mut multipart =
+            Multipart::with_reader_config(BODY, "boundary", AsyncReadConfig { buf_size });
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "foo");
+        let chunks: Vec<Vec<u8>> = run_future_hot(field.data.try_collect()).unwrap();
+        assert!(chunks.iter().all(|chunk| chunk.len() <= buf_size));
+        assert_eq!(chunks.concat(), b"field data");
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "bar");
+        assert_eq!(field.headers.filename.as_deref(), Some("bar.txt"));
+        assert_eq!(
+            run_future_hot(field.data.read_to_string()).unwrap(),
+            "bar data"
+        );
+
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use bytes::Bytes;
+use futures_core::{Stream, TryStream};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::BodyChunk;
+
+use super::{Error, FieldHeaders, Multipart};
+
+/// A field saved to disk by
+/// [`Multipart::save_files_concurrently()`](struct.Multipart.html#method.save_files_concurrently).
+#[derive(Debug)]
+pub struct SavedFile {
+    /// The headers of the field.
+    pub headers: FieldHeaders,
+    /// The path the field was saved to.
+    pub path: PathBuf,
+    /// The number of bytes written.
+    pub size: u64,
+}
+
+type WriteTask = JoinHandle<io::Result<u64>>;
+
+impl<S> Multipart<S>
+where
+    Self: Unpin,
+    S: TryStream,
+    S::Ok: BodyChunk,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    Error<S::Error>: From<S::Error>,
+{
+    /// Save fields to disk, writing each on its own task so that reading the request can continue
+    /// while the previous files are still being written. Enabled with the `save` feature.
+    ///
+    /// `path_for` is called with the headers of each field and returns the path to save it to,
+    /// or `None` to skip the field. The filename sent by the client should not be used as-is
+    /// as it may contain path separators.
+    ///
+    /// Each write task is fed through a channel holding up to `channel_capacity` chunks; once
+    /// it is full, reading the request waits for the task to catch up. This bounds the memory
+    /// held per file when the disk is slower than the client.
+    ///
+    /// Returns after all files have been written. Errors from the request are converted to
+    /// `io::Error` with a kind of `Other`.
+    ///
+    /// ### Panics
+    /// If not called within a Tokio runtime, or if `channel_capacity` is zero.
+    pub async fn save_files_concurrently<F>(
+        &mut self,
+        channel_capacity: usize,
+        mut path_for: F,
+    ) -> io::Result<Vec<SavedFile>>
+    where
+        F: FnMut(&FieldHeaders) -> Option<PathBuf>,
+    {
+        let mut writing: Vec<(FieldHeaders, PathBuf, WriteTask)> = Vec::new();
+
+        while let Some(mut field) = self.next_field().await.map_err(io::Error::other)? {
+            let path = match path_for(&field.headers) {
+                Some(path) => path,
+                None => continue,
+            };
+
+            let (mut tx, rx) = mpsc::channel(channel_capacity);
+            let task = tokio::spawn(write_file(path.clone(), rx));
+
+            loop {
+                let chunk = tokio::future::poll_fn(|cx| Pin::new(&mut field.data).poll_next(cx))
+                    .await
+                    .transpose()
+                    .map_err(io::Error::other)?;
+
+                let chunk = match chunk {
+                    Some(chunk) => Bytes::copy_from_slice(chunk.as_slice()),
+                    None => break,
+                };
+
+                if tx.send(chunk).await.is_err() {
+                    // the task only stops receiving early if writing failed
+                    let res = task.await.map_err(io::Error::other)?;
+                    return Err(res
+                        .err()
+                        .unwrap_or_else(|| io::Error::other("file write task ended early")));
+                }
+            }
+
+            writing.push((field.headers, path, task));
+        }
+
+        let mut saved = Vec::with_capacity(writing.len());
+
+        for (headers, path, task) in writing {
+            let size = task.await.map_err(io::Error::other)??;
+            saved.push(SavedFile {
+                headers,
+                path,
+                size,
+            });
+        }
+
+        Ok(saved)
+    }
+}
+
+async fn write_file(path: PathBuf, mut rx: mpsc::Receiver<Bytes>) -> io::Result<u64> {
+    let mut file = File::create(&path).await?;
+    let mut size = 0;
+
+    while let Some(chunk) = rx.recv().await {
+        file.write_all(&chunk).await?;
+        size += chunk.len() as u64;
+    }
+
+    file.flush().await?;
+    Ok(size)
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_save_files_concurrently() {
+    use std::{env, fs};
+
+    use crate::test_util::mock_stream;
+
+    let _ = ::env_logger::try_init();
+
+    let dir = env::temp_dir().join("multipart-async-save-files");
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut multipart = Multipart::with_body(
+        mock_stream(&[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"first\"; filename=\"first.txt\"\r\n\r\n",
+            b"first ",
+            b"file",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"text\"\r\n\r\n",
+            b"not a file",
+            b"\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"second\"; filename=\"second.bin\"\r\n\r\n",
+            b"\x00\x01\x02",
+            b"\x03",
+            b"\r\n--boundary--",
+        ]),
+        "boundary",
+    );
+
+    let mut count = 0;
+    let saved = multipart
+        .save_files_concurrently(1, |headers| {
+            headers.filename.as_ref()?;
+            count += 1;
+            Some(dir.join(format!("upload-{}", count)))
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(saved.len(), 2);
+
+    assert_eq!(saved[0].headers.name, "first");
+    assert_eq!(saved[0].size, 10);
+    assert_eq!(fs::read(&saved[0].path).unwrap(), b"first file");
+
+    assert_eq!(saved[1].headers.name, "second");
+    assert_eq!(saved[1].size, 4);
+    assert_eq!(fs::read(&saved[1].path).unwrap(), b"\x00\x01\x02\x03");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
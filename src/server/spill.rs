@@ -0,0 +1,193 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::fmt;
+use std::io::{self, SeekFrom};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{Stream, TryStream};
+use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncSeekExt, AsyncWriteExt};
+
+use crate::BodyChunk;
+
+use super::{Error, Field, FieldHeaders};
+
+impl<'a, S> Field<'a, S>
+where
+    S: TryStream + 'a,
+    S::Ok: BodyChunk,
+    S::Error: std::error::Error + Send + Sync + 'static,
+    Error<S::Error>: From<S::Error>,
+{
+    /// Read the data of this field, keeping up to `threshold` bytes in memory and writing the
+    /// rest to an anonymous temporary file. Enabled with the `spill` feature.
+    ///
+    /// The temporary file is deleted by the OS once the returned `SpilledField` is dropped.
+    /// Errors from the request are converted to `io::Error` with a kind of `Other`.
+    ///
+    /// ### Panics
+    /// If not called within a Tokio runtime.
+    pub async fn spill_to_temp(self, threshold: usize) -> io::Result<SpilledField> {
+        let mut data = self.data;
+        let mut buffer = Vec::new();
+        let mut file: Option<File> = None;
+        let mut len = 0u64;
+
+        while let Some(chunk) = tokio::future::poll_fn(|cx| Pin::new(&mut data).poll_next(cx)).await
+        {
+            let chunk = chunk.map_err(io::Error::other)?;
+            let chunk = chunk.as_slice();
+            len += chunk.len() as u64;
+
+            if file.is_none() && buffer.len() + chunk.len() <= threshold {
+                buffer.extend_from_slice(chunk);
+                continue;
+            }
+
+            let file = match file {
+                Some(ref mut file) => file,
+                None => file.get_or_insert(create_temp().await?),
+            };
+
+            file.write_all(chunk).await?;
+        }
+
+        if let Some(ref mut file) = file {
+            file.flush().await?;
+            file.seek(SeekFrom::Start(0)).await?;
+        }
+
+        Ok(SpilledField {
+            headers: self.headers,
+            buffer,
+            pos: 0,
+            file,
+            len,
+        })
+    }
+}
+
+async fn create_temp() -> io::Result<File> {
+    let file = tokio::task::spawn_blocking(tempfile::tempfile)
+        .await
+        .map_err(io::Error::other)??;
+
+    Ok(File::from_std(file))
+}
+
+/// A field read by [`Field::spill_to_temp()`](struct.Field.html#method.spill_to_temp), held
+/// partly in memory and partly in a temporary file.
+///
+/// Implements Tokio's `AsyncRead` over the whole of the field's data.
+pub struct SpilledField {
+    /// The headers of the field.
+    pub headers: FieldHeaders,
+    buffer: Vec<u8>,
+    /// The position of the next byte to read from `buffer`
+    pos: usize,
+    file: Option<File>,
+    len: u64,
+}
+
+impl SpilledField {
+    /// The total length of the field's data.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns `true` if the field had no data.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the data was longer than the threshold and some of it was written to
+    /// a temporary file.
+    pub fn is_spilled(&self) -> bool {
+        self.file.is_some()
+    }
+}
+
+impl AsyncRead for SpilledField {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+
+        if this.pos < this.buffer.len() {
+            let read = (this.buffer.len() - this.pos).min(buf.len());
+            buf[..read].copy_from_slice(&this.buffer[this.pos..this.pos + read]);
+            this.pos += read;
+            return Poll::Ready(Ok(read));
+        }
+
+        match this.file {
+            Some(ref mut file) => Pin::new(file).poll_read(cx, buf),
+            None => Poll::Ready(Ok(0)),
+        }
+    }
+}
+
+impl fmt::Debug for SpilledField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SpilledField")
+            .field("headers", &self.headers)
+            .field("len", &self.len)
+            .field("buffered", &self.buffer.len())
+            .field("spilled", &self.is_spilled())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[tokio::test]
+async fn test_spill_to_temp() {
+    use tokio::io::AsyncReadExt;
+
+    use crate::server::Multipart;
+    use crate::test_util::mock_stream;
+
+    let _ = ::env_logger::try_init();
+
+    let large = b"0123456789".repeat(100);
+
+    let chunks: &[&[u8]] = &[
+        b"--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"small\"\r\n\r\n",
+        b"small data",
+        b"\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"large\"; filename=\"large.txt\"\r\n\r\n",
+        &large[..300],
+        &large[300..],
+        b"\r\n--boundary--",
+    ];
+
+    let mut multipart = Multipart::with_body(mock_stream(chunks), "boundary");
+
+    let field = multipart.next_field().await.unwrap().unwrap();
+    let mut small = field.spill_to_temp(512).await.unwrap();
+    assert_eq!(small.headers.name, "small");
+    assert!(!small.is_spilled());
+
+    let mut data = Vec::new();
+    small.read_to_end(&mut data).await.unwrap();
+    assert_eq!(data, b"small data");
+
+    let field = multipart.next_field().await.unwrap().unwrap();
+    let mut spilled = field.spill_to_temp(512).await.unwrap();
+    assert_eq!(spilled.headers.name, "large");
+    assert!(spilled.is_spilled());
+    assert_eq!(spilled.len(), large.len() as u64);
+
+    let mut data = Vec::new();
+    spilled.read_to_end(&mut data).await.unwrap();
+    assert_eq!(data, large);
+
+    assert!(multipart.next_field().await.unwrap().is_none());
+}
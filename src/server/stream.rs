@@ -0,0 +1,212 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+
+use futures_core::{Stream, TryStream};
+
+use crate::BodyChunk;
+
+use super::field::PollNextField;
+use super::{Error, FieldHeaders, Multipart};
+
+struct Shared<S: TryStream> {
+    multipart: Pin<Box<Multipart<S>>>,
+    /// Incremented when the next field is requested, invalidating the data of the previous one
+    field_id: usize,
+}
+
+fn lock<S: TryStream>(shared: &Mutex<Shared<S>>) -> MutexGuard<'_, Shared<S>> {
+    // a panic while locked can't leave the parser in a state that is unsafe to observe
+    shared.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl<S> Multipart<S>
+where
+    S: TryStream,
+    S::Ok: BodyChunk,
+{
+    /// Convert `self` into a `Stream` of fields which don't borrow it, e.g. to push them into
+    /// a `FuturesUnordered` or send them to another task.
+    ///
+    /// Fields must still be read in order: once the stream has been polled for the next field,
+    /// reading the data of the previous one returns an error.
+    pub fn into_stream(self) -> MultipartStream<S> {
+        MultipartStream {
+            shared: Arc::new(Mutex::new(Shared {
+                multipart: Box::pin(self),
+                field_id: 0,
+            })),
+            state: PollNextField::default(),
+            requested: false,
+            finished: false,
+        }
+    }
+}
+
+/// A `Stream` of the fields of a request, each owning a handle to the request body.
+///
+/// Returned by [`Multipart::into_stream()`](struct.Multipart.html#method.into_stream).
+pub struct MultipartStream<S: TryStream> {
+    shared: Arc<Mutex<Shared<S>>>,
+    state: PollNextField,
+    /// The previous field was invalidated and we're polling for the next one
+    requested: bool,
+    finished: bool,
+}
+
+impl<S> Stream for MultipartStream<S>
+where
+    S: TryStream,
+    S::Ok: BodyChunk,
+    Error<S::Error>: From<S::Error>,
+{
+    type Item = super::Result<OwnedField<S>, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        let mut shared = lock(&this.shared);
+
+        if !this.requested {
+            shared.field_id += 1;
+            this.requested = true;
+        }
+
+        let res = ready!(this.state.poll(shared.multipart.as_mut(), cx));
+        this.requested = false;
+
+        let headers = match res {
+            Ok(Some(headers)) => headers,
+            Ok(None) => {
+                this.finished = true;
+                return Poll::Ready(None);
+            }
+            Err(e) => {
+                this.finished = true;
+                return Poll::Ready(Some(Err(e)));
+            }
+        };
+
+        Poll::Ready(Some(Ok(OwnedField {
+            headers,
+            data: OwnedFieldData {
+                shared: this.shared.clone(),
+                field_id: shared.field_id,
+            },
+        })))
+    }
+}
+
+/// A field yielded by [`MultipartStream`](struct.MultipartStream.html).
+pub struct OwnedField<S: TryStream> {
+    /// The headers of this field, including the name, filename, and `Content-Type`, if provided.
+    pub headers: FieldHeaders,
+    /// The data of this field in the request, represented as a stream of chunks.
+    pub data: OwnedFieldData<S>,
+}
+
+impl<S: TryStream> fmt::Debug for OwnedField<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OwnedField")
+            .field("headers", &self.headers)
+            .field("data", &"<OwnedFieldData>")
+            .finish()
+    }
+}
+
+/// The data of an [`OwnedField`](struct.OwnedField.html), as a stream of chunks.
+///
+/// Yields an error if polled after the `MultipartStream` it came from was polled for the next
+/// field.
+pub struct OwnedFieldData<S: TryStream> {
+    shared: Arc<Mutex<Shared<S>>>,
+    field_id: usize,
+}
+
+impl<S> Stream for OwnedFieldData<S>
+where
+    S: TryStream,
+    S::Ok: BodyChunk,
+    Error<S::Error>: From<S::Error>,
+{
+    type Item = super::Result<S::Ok, S::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let mut shared = lock(&self.shared);
+
+        if shared.field_id != self.field_id {
+            return Poll::Ready(Some(fmt_err!(
+                "field data read after the next field was requested"
+            )));
+        }
+
+        shared.multipart.as_mut().poll_field_chunk(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::{StreamExt, TryStreamExt};
+
+    use crate::server::Multipart;
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    const BODY: &[&[u8]] = &[
+        b"--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+        b"foo ",
+        b"data",
+        b"\r\n--boundary\r\n",
+        b"Content-Disposition: form-data; name=\"bar\"\r\n\r\n",
+        b"bar data",
+        b"\r\n--boundary--",
+    ];
+
+    #[test]
+    fn test_into_stream() {
+        let _ = ::env_logger::try_init();
+
+        let mut stream = Multipart::with_body(mock_stream(BODY), "boundary").into_stream();
+
+        let foo = run_future_hot(stream.try_next()).unwrap().unwrap();
+        assert_eq!(foo.headers.name, "foo");
+        let data: Vec<&[u8]> = run_future_hot(foo.data.try_collect()).unwrap();
+        assert_eq!(data.concat(), b"foo data");
+
+        let bar = run_future_hot(stream.try_next()).unwrap().unwrap();
+        assert_eq!(bar.headers.name, "bar");
+        let data: Vec<&[u8]> = run_future_hot(bar.data.try_collect()).unwrap();
+        assert_eq!(data.concat(), b"bar data");
+
+        assert!(run_future_hot(stream.next()).is_none());
+        assert!(run_future_hot(stream.next()).is_none());
+    }
+
+    #[test]
+    fn test_into_stream_out_of_order() {
+        let _ = ::env_logger::try_init();
+
+        let stream = Multipart::with_body(mock_stream(BODY), "boundary").into_stream();
+        let fields: Vec<_> = run_future_hot(stream.try_collect()).unwrap();
+
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].headers.name, "foo");
+        assert_eq!(fields[1].headers.name, "bar");
+
+        for mut field in fields {
+            let err = run_future_hot(field.data.try_next()).unwrap_err();
+            assert!(err.to_string().contains("next field"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::{Stream, TryStream};
+
+use crate::BodyChunk;
+
+use super::{BufferedField, Error, Multipart};
+
+impl<S> Multipart<S>
+where
+    S: TryStream,
+    S::Ok: BodyChunk,
+{
+    /// Read the first `n` fields into memory, then stop parsing and return them along with the
+    /// rest of the body, e.g. to hand it off to another consumer.
+    ///
+    /// The returned stream first yields any chunks which were read from the underlying stream but
+    /// not yet consumed, then the remaining chunks of the underlying stream. If all `n` fields
+    /// were read, it starts with the boundary of the next field (without the preceding line
+    /// ending), so it can be wrapped in another `Multipart` with the same boundary.
+    ///
+    /// If the body has fewer than `n` fields, all of them are returned. If the data of any field
+    /// exceeds `field_limit` bytes, an error is returned instead.
+    pub async fn take_fields(
+        mut self,
+        n: usize,
+        field_limit: usize,
+    ) -> super::Result<(Vec<BufferedField>, RemainingBody<S>), S::Error>
+    where
+        Self: Unpin,
+        Error<S::Error>: From<S::Error>,
+    {
+        // `n` may be far larger than the number of fields actually sent
+        let mut fields = Vec::with_capacity(n.min(16));
+
+        while fields.len() < n {
+            let field = match self.next_field().await? {
+                Some(field) => field,
+                None => break,
+            };
+
+            let data = field.data.collect_vec().limit(field_limit).await?;
+
+            fields.push(BufferedField {
+                headers: field.headers,
+                data,
+            });
+        }
+
+        Ok((fields, self.into_remaining()))
+    }
+
+    fn into_remaining(self) -> RemainingBody<S> {
+        let (held, stream) = self.inner.stream.into_parts();
+
+        RemainingBody {
+            held: self
+                .inner
+                .pushed
+                .into_iter()
+                .chain(self.overflow)
+                .chain(held)
+                .collect(),
+            stream,
+        }
+    }
+}
+
+/// The rest of a request body, returned by
+/// [`Multipart::take_fields()`](struct.Multipart.html#method.take_fields).
+///
+/// Yields the chunks which were read from the underlying stream but not parsed, then the chunks
+/// of the underlying stream.
+pub struct RemainingBody<S: TryStream> {
+    held: VecDeque<S::Ok>,
+    stream: S,
+}
+
+impl<S: TryStream> RemainingBody<S> {
+    unsafe_pinned!(stream: S);
+    unsafe_unpinned!(held: VecDeque<S::Ok>);
+}
+
+impl<S: TryStream> Stream for RemainingBody<S> {
+    type Item = Result<S::Ok, S::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        if let Some(chunk) = self.as_mut().held().pop_front() {
+            return Poll::Ready(Some(Ok(chunk)));
+        }
+
+        self.stream().try_poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures_util::TryStreamExt;
+
+    use crate::server::Multipart;
+    use crate::test_util::{mock_stream, run_future_hot};
+
+    const BOUNDARY: &str = "boundary";
+
+    #[test]
+    fn test_take_fields() {
+        let _ = ::env_logger::try_init();
+
+        let rest: &[&[u8]] = &[
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"baz\"\r\n\r\n",
+            b"qux\r\n",
+            b"--boundary--",
+        ];
+
+        let mut body: Vec<&[u8]> = vec![
+            b"--boundary\r",
+            b"\n",
+            b"Content-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"bar\r\n",
+            b"--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"hello\"\r\n\r\n",
+            b"world\r\n",
+        ];
+        body.extend_from_slice(rest);
+
+        let multipart = Multipart::with_body(mock_stream(&body), BOUNDARY);
+        let (fields, remaining) = run_future_hot(multipart.take_fields(2, 5)).unwrap();
+
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].headers.name, "foo");
+        assert_eq!(fields[0].data, b"bar");
+        assert_eq!(fields[1].headers.name, "hello");
+        assert_eq!(fields[1].data, b"world");
+
+        let remaining = run_future_hot(remaining.map_ok(<[u8]>::to_vec).try_concat()).unwrap();
+        assert_eq!(remaining, rest.concat());
+
+        // the rest is a valid body on its own
+        let multipart = Multipart::with_body(mock_stream(rest), BOUNDARY);
+        let (fields, _) = run_future_hot(multipart.take_fields(2, 5)).unwrap();
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].headers.name, "baz");
+    }
+
+    #[test]
+    fn test_take_fields_split_chunk() {
+        let _ = ::env_logger::try_init();
+
+        let body: &[&[u8]] = &[
+            b"--boundary\r\nContent-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"bar\r\n--bound",
+            b"ary\r\nContent-Disposition: form-data; name=\"baz\"\r\n\r\nqux\r\n--boundary--",
+        ];
+
+        let multipart = Multipart::with_body(mock_stream(body), BOUNDARY);
+        let (fields, remaining) = run_future_hot(multipart.take_fields(1, 3)).unwrap();
+
+        assert_eq!(fields.len(), 1);
+        assert_eq!(fields[0].data, b"bar");
+
+        let remaining = run_future_hot(remaining.map_ok(<[u8]>::to_vec).try_concat()).unwrap();
+        assert_eq!(
+            remaining,
+            &b"--boundary\r\nContent-Disposition: form-data; name=\"baz\"\r\n\r\nqux\r\n--boundary--"[..]
+        );
+    }
+    #[test]
+    fn test_take_fields_limit() {
+        let _ = ::env_logger::try_init();
+
+        let body: &[&[u8]] = &[
+            b"--boundary\r\nContent-Disposition: form-data; name=\"foo\"\r\n\r\n",
+            b"bar\r\n--boundary\r\n",
+            b"Content-Disposition: form-data; name=\"hello\"\r\n\r\nworld\r\n--boundary--",
+        ];
+
+        let multipart = Multipart::with_body(mock_stream(body), BOUNDARY);
+        let err = match run_future_hot(multipart.take_fields(usize::MAX, 3)) {
+            Ok(_) => panic!("expected the limit to be exceeded"),
+            Err(err) => err,
+        };
+        assert!(
+            err.to_string().contains("exceeds the limit of 3 bytes"),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,45 @@
+// Copyright 2017-2019 `multipart-async` Crate Developers
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! Server-side integration with [Tide](https://github.com/http-rs/tide).
+//! Enabled with the `tide` feature.
+use mime::Mime;
+use tide::{Body, Request, StatusCode};
+
+use super::{Multipart, ReadStream};
+
+/// Extension trait for reading a `tide::Request` as a multipart request.
+pub trait TideRequestExt {
+    /// Take the body of this request and wrap it in a `Multipart`, reading it with the default
+    /// [`AsyncReadConfig`](../struct.AsyncReadConfig.html).
+    ///
+    /// Returns an error with status `415 Unsupported Media Type` if the request is not
+    /// `multipart/form-data`, or `400 Bad Request` if it has no `boundary` parameter.
+    /// The method of the request is not checked.
+    fn into_multipart(self) -> tide::Result<Multipart<ReadStream<Body>>>;
+}
+
+impl<State> TideRequestExt for Request<State> {
+    fn into_multipart(mut self) -> tide::Result<Multipart<ReadStream<Body>>> {
+        let content_type = self
+            .header(tide::http::headers::CONTENT_TYPE)
+            .and_then(|values| values.last().as_str().parse::<Mime>().ok())
+            .filter(|mime| mime.type_() == mime::MULTIPART && mime.subtype() == mime::FORM_DATA)
+            .ok_or_else(|| {
+                tide::Error::from_str(
+                    StatusCode::UnsupportedMediaType,
+                    "expected a multipart/form-data request",
+                )
+            })?;
+
+        let boundary = super::boundary_param(&content_type)
+            .ok_or_else(|| tide::Error::from_str(StatusCode::BadRequest, super::NoBoundary))?;
+
+        info!("multipart request received, boundary: {}", boundary);
+
+        Ok(Multipart::with_reader(self.take_body(), boundary))
+    }
+}
@@ -4,6 +4,8 @@
 // http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
+//! Helpers for testing with mock request bodies. Enabled with the `test-util` feature.
+//!
 //! ### Note: not stable APIS
 //! The items exported in this module are not considered part of this crate's public API
 //! and may receive breaking changes in semver-compatible versions.
@@ -18,6 +20,8 @@ use futures_core::task::Context;
 use futures_test::stream::StreamTestExt;
 use futures_test::task::noop_context;
 
+use bytes::Bytes;
+use futures_util::future::Either;
 use futures_util::stream::{self, StreamExt};
 use std::convert::Infallible;
 
@@ -43,6 +47,26 @@ pub fn mock_stream<'d>(
         .interleave_pending()
 }
 
+/// Yield `chunks` in order, e.g. to replay a request body captured with its exact chunking.
+///
+/// If `interleave_pending` is `true`, `Poll::Pending` is returned (with the task woken)
+/// before each chunk, which exercises the parser's handling of partial reads.
+///
+/// Unlike [`mock_stream()`](fn.mock_stream.html) the chunks are owned, so they can be
+/// loaded at runtime.
+pub fn replay_stream(
+    chunks: Vec<Bytes>,
+    interleave_pending: bool,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let stream = stream::iter(chunks).map(Ok);
+
+    if interleave_pending {
+        Either::Left(stream.interleave_pending())
+    } else {
+        Either::Right(stream)
+    }
+}
+
 macro_rules! until_ready(
     (|$cx:ident| $expr:expr) => {{
         use std::task::Poll::*;
@@ -97,3 +121,41 @@ where
 }
 
 pub fn assert_unpin<T: Unpin>() {}
+
+#[cfg(feature = "server")]
+#[test]
+fn test_replay_stream() {
+    use crate::server::Multipart;
+    use futures_util::TryStreamExt;
+
+    let _ = ::env_logger::try_init();
+
+    // a body as it was received from a client, one `Bytes` per read
+    let recorded = [
+        &b"--boundary\r\nContent-Disposition: form-data; name=\"foo\"\r\n"[..],
+        b"\r\nfield data\r\n--bound",
+        b"ary\r\nContent-Disposition: form-data; name=\"bar\"; filename=\"bar.bin\"\r\n\r\n",
+        b"\x00\x01\x02\r\n--boundary--\r\n",
+    ];
+
+    for &interleave_pending in &[false, true] {
+        let chunks = recorded.iter().map(|c| Bytes::copy_from_slice(c)).collect();
+        let mut multipart =
+            Multipart::with_body(replay_stream(chunks, interleave_pending), "boundary");
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "foo");
+        assert_eq!(
+            run_future_hot(field.data.read_to_string()).unwrap(),
+            "field data"
+        );
+
+        let field = run_future_hot(multipart.next_field()).unwrap().unwrap();
+        assert_eq!(field.headers.name, "bar");
+        assert_eq!(field.headers.filename.as_deref(), Some("bar.bin"));
+        let data: Vec<Bytes> = run_future_hot(field.data.try_collect()).unwrap();
+        assert_eq!(data.concat(), b"\x00\x01\x02");
+
+        assert!(run_future_hot(multipart.next_field()).unwrap().is_none());
+    }
+}
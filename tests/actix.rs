@@ -0,0 +1,91 @@
+//! Test extracting `server::actix::Multipart` from an actix-web request
+use actix_web::dev::Payload;
+use actix_web::error::PayloadError;
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::{FromRequest, HttpRequest, ResponseError};
+use futures::executor::block_on;
+
+use multipart_async::server::actix::Multipart;
+use multipart_async::server::Error;
+
+const BODY: &[u8] = b"--boundary\r\n\
+    Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+    foo data\r\n\
+    --boundary\r\n\
+    Content-Disposition: form-data; name=\"bar\"; filename=\"bar.txt\"\r\n\
+    Content-Type: text/plain\r\n\r\n\
+    bar data\r\n\
+    --boundary--";
+
+fn request(content_type: &str, body: &'static [u8]) -> (HttpRequest, Payload) {
+    TestRequest::post()
+        .uri("/upload")
+        .insert_header((CONTENT_TYPE, content_type))
+        .set_payload(body)
+        .to_http_parts()
+}
+
+fn extract(content_type: &str) -> actix_web::Result<Multipart> {
+    let (req, mut payload) = request(content_type, BODY);
+    block_on(Multipart::from_request(&req, &mut payload))
+}
+
+#[test]
+fn test_actix_extractor() {
+    let _ = env_logger::try_init();
+
+    let mut multipart = extract("multipart/form-data; boundary=boundary").unwrap();
+    assert_eq!(multipart.boundary(), "boundary");
+
+    let mut fields = Vec::new();
+
+    while let Some(field) = block_on(multipart.next_field()).unwrap() {
+        let name = field.headers.name.clone();
+        let text = block_on(field.data.read_to_string()).unwrap();
+        fields.push((name, text));
+    }
+
+    assert_eq!(
+        fields,
+        [
+            ("foo".to_string(), "foo data".to_string()),
+            ("bar".to_string(), "bar data".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_actix_rejections() {
+    let _ = env_logger::try_init();
+
+    for content_type in &[
+        "text/plain",
+        "multipart/form-data",
+        "multipart/mixed; boundary=boundary",
+    ] {
+        let err = extract(content_type)
+            .err()
+            .unwrap_or_else(|| panic!("accepted {:?}", content_type));
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
+
+#[test]
+fn test_actix_error_response() {
+    let _ = env_logger::try_init();
+
+    let mut multipart = extract("multipart/form-data; boundary=boundary").unwrap();
+    multipart.set_field_data_limit(Some(3));
+
+    let field = block_on(multipart.next_field()).unwrap().unwrap();
+    // as the `?` operator would convert it in a handler
+    let err: Error<PayloadError> = block_on(field.data.read_to_string()).unwrap_err().into();
+
+    assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(err.error_response().status(), StatusCode::BAD_REQUEST);
+}
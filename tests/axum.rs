@@ -0,0 +1,85 @@
+//! Test extracting `server::axum::Multipart` from an Axum request
+use axum::body::Body;
+use axum::extract::FromRequest;
+use axum::response::IntoResponse;
+use futures::executor::block_on;
+use http::{Request, StatusCode};
+
+use multipart_async::server::axum::Multipart;
+use multipart_async::server::Error;
+
+const BODY: &[u8] = b"--boundary\r\n\
+    Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+    foo data\r\n\
+    --boundary\r\n\
+    Content-Disposition: form-data; name=\"bar\"; filename=\"bar.txt\"\r\n\
+    Content-Type: text/plain\r\n\r\n\
+    bar data\r\n\
+    --boundary--";
+
+fn request(content_type: &str, body: &'static [u8]) -> Request<Body> {
+    Request::post("/upload")
+        .header(http::header::CONTENT_TYPE, content_type)
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn extract(req: Request<Body>) -> Result<Multipart, (StatusCode, String)> {
+    block_on(Multipart::from_request(req, &()))
+}
+
+#[test]
+fn test_axum_extractor() {
+    let _ = env_logger::try_init();
+
+    let mut multipart = extract(request("multipart/form-data; boundary=boundary", BODY))
+        .unwrap_or_else(|(status, msg)| panic!("rejected with {}: {}", status, msg));
+    assert_eq!(multipart.boundary(), "boundary");
+
+    let mut fields = Vec::new();
+
+    while let Some(field) = block_on(multipart.next_field()).unwrap() {
+        let name = field.headers.name.clone();
+        let text = block_on(field.data.read_to_string()).unwrap();
+        fields.push((name, text));
+    }
+
+    assert_eq!(
+        fields,
+        [
+            ("foo".to_string(), "foo data".to_string()),
+            ("bar".to_string(), "bar data".to_string())
+        ]
+    );
+}
+
+#[test]
+fn test_axum_rejections() {
+    let _ = env_logger::try_init();
+
+    for content_type in &[
+        "text/plain",
+        "multipart/form-data",
+        "multipart/mixed; boundary=boundary",
+    ] {
+        let (status, _) = extract(request(content_type, BODY))
+            .err()
+            .unwrap_or_else(|| panic!("accepted {:?}", content_type));
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
+
+#[test]
+fn test_axum_error_response() {
+    let _ = env_logger::try_init();
+
+    let mut multipart = extract(request("multipart/form-data; boundary=boundary", BODY))
+        .unwrap_or_else(|(status, msg)| panic!("rejected with {}: {}", status, msg));
+    multipart.set_field_data_limit(Some(3));
+
+    let field = block_on(multipart.next_field()).unwrap().unwrap();
+    // as the `?` operator would convert it in a handler
+    let err: Error<axum::Error> = block_on(field.data.read_to_string()).unwrap_err().into();
+
+    assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,291 @@
+//! Test the different shapes of form requests cURL can send against a Hyper endpoint
+use std::net::SocketAddr;
+use std::{env, fs, thread};
+
+use bytes::Bytes;
+use http::header::HeaderValue;
+use http::{Request, StatusCode};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Response, Server};
+
+use futures::{future, FutureExt, StreamExt, TryStreamExt};
+
+use curl::easy::{Easy2, Form, Handler, List, ReadError};
+use multipart_async::server::{FieldHeaders, Multipart};
+
+struct TestHandler;
+
+impl Handler for TestHandler {}
+
+/// Sends a prepared body in small pieces, like a field read from a pipe.
+struct ChunkedBody {
+    body: Vec<u8>,
+    pos: usize,
+    chunk_size: usize,
+}
+
+impl Handler for ChunkedBody {
+    fn read(&mut self, data: &mut [u8]) -> Result<usize, ReadError> {
+        let rem = &self.body[self.pos..];
+        let len = rem.len().min(self.chunk_size).min(data.len());
+        data[..len].copy_from_slice(&rem[..len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+/// Start a server, run `send` with its URL on another thread and return the fields it received.
+async fn receive_fields<F>(send: F) -> Vec<(FieldHeaders, Vec<u8>)>
+where
+    F: FnOnce(String) + Send + 'static,
+{
+    // set 0 for the port to have the OS pick one
+    let addr: SocketAddr = ([127, 0, 0, 1], 0).into();
+
+    let (tx, mut rx) = futures::channel::mpsc::channel(1);
+
+    let make_service = make_service_fn(move |_| {
+        let tx = tx.clone();
+
+        future::ok::<_, hyper::Error>(service_fn(move |req: Request<Body>| {
+            let mut tx = tx.clone();
+
+            async move {
+                assert_eq!(req.uri().path(), "/multipart-upload");
+
+                let mut multipart = Multipart::try_from_request(req).unwrap();
+                let mut fields = Vec::new();
+
+                while let Some(field) = multipart.next_field().await.unwrap() {
+                    let chunks: Vec<Bytes> = field.data.try_collect().await.unwrap();
+                    fields.push((field.headers, chunks.concat()));
+                }
+
+                tx.try_send(fields).unwrap();
+
+                Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Body::empty())
+            }
+        }))
+    });
+
+    let server = Server::bind(&addr).serve(make_service);
+    let url = format!("http://{}/multipart-upload", server.local_addr());
+
+    let client = thread::spawn(move || send(url));
+
+    let mut fields = None;
+
+    server
+        .with_graceful_shutdown(rx.next().map(|res| fields = res))
+        .await
+        .unwrap();
+
+    client.join().unwrap();
+
+    fields.expect("server did not receive a request")
+}
+
+fn send_form(url: String, form: Form) {
+    let mut easy = Easy2::new(TestHandler);
+    easy.url(&url).unwrap();
+    easy.post(true).unwrap();
+    easy.httppost(form).unwrap();
+    easy.perform().unwrap();
+}
+
+/// Send `body` with `Transfer-Encoding: chunked`, `chunk_size` bytes at a time.
+fn send_chunked(url: String, boundary: &str, body: Vec<u8>, chunk_size: usize) {
+    let mut easy = Easy2::new(ChunkedBody {
+        body,
+        pos: 0,
+        chunk_size,
+    });
+    easy.url(&url).unwrap();
+    easy.post(true).unwrap();
+
+    let mut headers = List::new();
+    headers
+        .append(&format!(
+            "Content-Type: multipart/form-data; boundary={}",
+            boundary
+        ))
+        .unwrap();
+    headers.append("Transfer-Encoding: chunked").unwrap();
+    // don't wait for `100 Continue`
+    headers.append("Expect:").unwrap();
+    easy.http_headers(headers).unwrap();
+
+    easy.perform().unwrap();
+}
+
+/// `curl -F "image=@image.png;type=image/png" -F "html=<p>hi</p>;type=text/html"`
+#[tokio::test]
+async fn test_curl_form_type() {
+    let fields = receive_fields(|url| {
+        let mut form = Form::new();
+        form.part("image")
+            .buffer("image.png", b"\x89PNG\r\n\x1a\n".to_vec())
+            .content_type("image/png")
+            .add()
+            .unwrap();
+        form.part("html")
+            .contents(b"<p>hi</p>")
+            .content_type("text/html")
+            .add()
+            .unwrap();
+
+        send_form(url, form);
+    })
+    .await;
+
+    assert_eq!(fields.len(), 2);
+
+    let (ref headers, ref data) = fields[0];
+    assert_eq!(headers.name, "image");
+    assert_eq!(headers.filename.as_deref(), Some("image.png"));
+    assert_eq!(headers.content_type, Some(mime::IMAGE_PNG));
+    assert!(!headers.is_text());
+    assert_eq!(data, b"\x89PNG\r\n\x1a\n");
+
+    let (ref headers, ref data) = fields[1];
+    assert_eq!(headers.name, "html");
+    assert_eq!(headers.filename, None);
+    assert_eq!(headers.content_type, Some(mime::TEXT_HTML));
+    assert!(headers.is_text());
+    assert_eq!(data, b"<p>hi</p>");
+}
+
+/// `curl -F "file=@<path>;filename=renamed.txt"`
+#[tokio::test]
+async fn test_curl_form_filename() {
+    let path = env::temp_dir().join("multipart-async-curl-form-filename.txt");
+    fs::write(&path, "Hello from a file on disk!").unwrap();
+
+    let send_path = path.clone();
+
+    let fields = receive_fields(move |url| {
+        let mut form = Form::new();
+        form.part("file")
+            .file(&send_path)
+            .filename("renamed.txt")
+            .add()
+            .unwrap();
+
+        send_form(url, form);
+    })
+    .await;
+
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(fields.len(), 1);
+
+    let (ref headers, ref data) = fields[0];
+    assert_eq!(headers.name, "file");
+    assert_eq!(headers.filename.as_deref(), Some("renamed.txt"));
+    assert_eq!(data, b"Hello from a file on disk!");
+}
+
+/// `curl -F "field=value;headers=\"X-Custom: custom value\""`
+#[tokio::test]
+async fn test_curl_form_headers() {
+    let fields = receive_fields(|url| {
+        let mut headers = List::new();
+        headers.append("X-Custom: custom value").unwrap();
+
+        let mut form = Form::new();
+        form.part("field")
+            .contents(b"value")
+            .content_header(headers)
+            .add()
+            .unwrap();
+
+        send_form(url, form);
+    })
+    .await;
+
+    assert_eq!(fields.len(), 1);
+
+    let (ref headers, ref data) = fields[0];
+    assert_eq!(headers.name, "field");
+    assert_eq!(
+        headers.ext_headers.get("x-custom"),
+        Some(&HeaderValue::from_static("custom value"))
+    );
+    assert_eq!(data, b"value");
+}
+
+/// `echo "..." | curl -F "stdin=<-"`, where the length of the body isn't known up front
+/// so it is sent with `Transfer-Encoding: chunked`
+#[tokio::test]
+async fn test_curl_form_stdin_chunked() {
+    const BOUNDARY: &str = "------------------------d74496d66958873e";
+
+    let contents = "line one\nline two\nline three\n".repeat(16);
+
+    let body = format!(
+        "--{0}\r\n\
+         Content-Disposition: form-data; name=\"stdin\"\r\n\
+         \r\n\
+         {1}\r\n\
+         --{0}--\r\n",
+        BOUNDARY, contents
+    );
+
+    let fields = receive_fields(move |url| send_chunked(url, BOUNDARY, body.into(), 7)).await;
+
+    assert_eq!(fields.len(), 1);
+
+    let (ref headers, ref data) = fields[0];
+    assert_eq!(headers.name, "stdin");
+    assert_eq!(headers.filename, None);
+    assert_eq!(data, contents.as_bytes());
+}
+
+/// A request as a Chromium-based browser sends it for a form with a text input, a file input
+/// and an empty file input.
+#[tokio::test]
+async fn test_browser_form() {
+    const BOUNDARY: &str = "----WebKitFormBoundary7MA4YWxkTrZu0gW";
+
+    const BODY: &[u8] = b"------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
+        Content-Disposition: form-data; name=\"title\"\r\n\
+        \r\n\
+        My Upload\r\n\
+        ------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
+        Content-Disposition: form-data; name=\"attachment\"; filename=\"notes.txt\"\r\n\
+        Content-Type: text/plain\r\n\
+        \r\n\
+        first line\r\n\
+        second line\r\n\
+        \r\n\
+        ------WebKitFormBoundary7MA4YWxkTrZu0gW\r\n\
+        Content-Disposition: form-data; name=\"empty\"; filename=\"\"\r\n\
+        Content-Type: application/octet-stream\r\n\
+        \r\n\
+        \r\n\
+        ------WebKitFormBoundary7MA4YWxkTrZu0gW--\r\n";
+
+    let fields = receive_fields(|url| send_chunked(url, BOUNDARY, BODY.to_vec(), 64)).await;
+
+    assert_eq!(fields.len(), 3);
+
+    let (ref headers, ref data) = fields[0];
+    assert_eq!(headers.name, "title");
+    assert_eq!(headers.filename, None);
+    assert_eq!(headers.content_type, None);
+    assert_eq!(data, b"My Upload");
+
+    let (ref headers, ref data) = fields[1];
+    assert_eq!(headers.name, "attachment");
+    assert_eq!(headers.filename.as_deref(), Some("notes.txt"));
+    assert_eq!(headers.content_type, Some(mime::TEXT_PLAIN));
+    assert_eq!(data, b"first line\r\nsecond line\r\n");
+
+    let (ref headers, ref data) = fields[2];
+    assert_eq!(headers.name, "empty");
+    assert_eq!(headers.filename.as_deref(), Some(""));
+    assert_eq!(headers.content_type, Some(mime::APPLICATION_OCTET_STREAM));
+    assert_eq!(data, b"");
+}
@@ -9,7 +9,7 @@ use hyper::{Body, Response, Server};
 use futures::{future, FutureExt, StreamExt, TryStreamExt};
 
 use curl::easy::{Easy2, Form, Handler, ReadError, WriteError};
-use multipart_async::server::Multipart;
+use multipart_async::server::prelude::*;
 use std::ops::Range;
 use std::thread;
 
@@ -48,6 +48,7 @@ async fn test_hyper_with_curl() {
                             normal_done = true;
 
                             assert!(field.headers.is_text());
+                            assert!(!field.headers.is_file());
                             assert_eq!(field.headers.filename, None);
                             assert_eq!(field.headers.content_type, None);
                             assert_eq!(field.headers.ext_headers, HeaderMap::new());
@@ -59,6 +60,7 @@ async fn test_hyper_with_curl() {
 
                             assert_eq!(field.headers.filename, Some("text-file.txt".to_string()));
                             assert!(field.headers.is_text());
+                            assert!(field.headers.is_file());
                             assert_eq!(field.headers.content_type, Some(mime::TEXT_PLAIN));
                             assert_eq!(field.headers.ext_headers, HeaderMap::new());
                             assert_eq!(
@@ -72,6 +74,7 @@ async fn test_hyper_with_curl() {
 
                             assert_eq!(field.headers.filename, Some("binary-file.bin".to_string()));
                             assert!(!field.headers.is_text());
+                            assert!(field.headers.is_file());
                             assert_eq!(
                                 field.headers.content_type,
                                 Some(mime::APPLICATION_OCTET_STREAM)
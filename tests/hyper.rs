@@ -0,0 +1,71 @@
+//! Test reading a `hyper::Request` with `server::hyper::HyperRequestExt`
+use hyper_014 as hyper;
+use hyper::{Body, Request};
+
+use multipart_async::server::prelude::*;
+
+const BODY: &[u8] = b"--boundary\r\n\
+    Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+    foo data\r\n\
+    --boundary\r\n\
+    Content-Disposition: form-data; name=\"bar\"; filename=\"bar.txt\"\r\n\
+    Content-Type: text/plain\r\n\r\n\
+    bar data\r\n\
+    --boundary--";
+
+fn request(content_type: &str) -> Request<Body> {
+    Request::put("/upload?id=1")
+        .header(http::header::CONTENT_TYPE, content_type)
+        .body(Body::from(BODY))
+        .unwrap()
+}
+
+/// Errors from the body and from parsing both convert with `?`
+async fn read_fields(mut multipart: Multipart<Body>) -> Result<Vec<String>, Error<hyper::Error>> {
+    let mut fields = Vec::new();
+
+    while let Some(field) = multipart.next_field().await? {
+        let name = field.headers.name.clone();
+        let text = field.data.read_to_string().await?;
+        fields.push(format!("{}={}", name, text));
+    }
+
+    Ok(fields)
+}
+
+#[tokio::test]
+async fn test_hyper_into_multipart() {
+    let _ = env_logger::try_init();
+
+    let (multipart, parts) = request("multipart/form-data; boundary=boundary")
+        .into_multipart()
+        .unwrap_or_else(|_| panic!("rejected a multipart request"));
+
+    // the rest of the request is kept
+    assert_eq!(parts.method, http::Method::PUT);
+    assert_eq!(parts.uri.query(), Some("id=1"));
+
+    assert_eq!(
+        read_fields(multipart).await.unwrap(),
+        ["foo=foo data", "bar=bar data"]
+    );
+}
+
+#[tokio::test]
+async fn test_hyper_not_multipart() {
+    let _ = env_logger::try_init();
+
+    for content_type in &[
+        "text/plain",
+        "multipart/form-data",
+        "multipart/mixed; boundary=boundary",
+    ] {
+        let req = request(content_type)
+            .into_multipart()
+            .err()
+            .unwrap_or_else(|| panic!("accepted {:?}", content_type));
+        // the request is returned intact
+        assert_eq!(req.headers()[http::header::CONTENT_TYPE], *content_type);
+        assert_eq!(req.uri().query(), Some("id=1"));
+    }
+}
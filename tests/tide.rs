@@ -0,0 +1,65 @@
+//! Test reading a request with `Multipart` from inside a Tide endpoint
+use futures::executor::block_on;
+use tide::http::{Method, Request, Response};
+use tide::StatusCode;
+
+use multipart_async::server::prelude::*;
+
+const BODY: &[u8] = b"--boundary\r\n\
+    Content-Disposition: form-data; name=\"foo\"\r\n\r\n\
+    foo data\r\n\
+    --boundary\r\n\
+    Content-Disposition: form-data; name=\"bar\"; filename=\"bar.txt\"\r\n\
+    Content-Type: text/plain\r\n\r\n\
+    bar data\r\n\
+    --boundary--";
+
+fn app() -> tide::Server<()> {
+    let mut app = tide::new();
+
+    app.at("/upload").post(|req: tide::Request<()>| async move {
+        let mut multipart = req.into_multipart()?;
+        let mut out = String::new();
+
+        while let Some(field) = multipart.next_field().await? {
+            let name = field.headers.name.clone();
+            let text = field.data.read_to_string().await?;
+            out.push_str(&format!("{}={};", name, text));
+        }
+
+        Ok(out)
+    });
+
+    app
+}
+
+fn upload(content_type: &str, body: &'static [u8]) -> Response {
+    let mut req = Request::new(Method::Post, "http://localhost/upload");
+    req.insert_header("Content-Type", content_type);
+    req.set_body(body);
+
+    block_on(app().respond(req)).unwrap()
+}
+
+#[test]
+fn test_tide_into_multipart() {
+    let _ = env_logger::try_init();
+
+    let mut res = upload("multipart/form-data; boundary=boundary", BODY);
+    assert_eq!(res.status(), StatusCode::Ok);
+    assert_eq!(
+        block_on(res.body_string()).unwrap(),
+        "foo=foo data;bar=bar data;"
+    );
+}
+
+#[test]
+fn test_tide_not_multipart() {
+    let _ = env_logger::try_init();
+
+    let res = upload("text/plain", b"foo data");
+    assert_eq!(res.status(), StatusCode::UnsupportedMediaType);
+
+    let res = upload("multipart/form-data", BODY);
+    assert_eq!(res.status(), StatusCode::BadRequest);
+}
@@ -0,0 +1,139 @@
+//! Test writing a request with `MultipartWriter` and parsing it back with `Multipart`
+use std::convert::Infallible;
+
+use futures::{stream, TryStreamExt};
+use http::header::CONTENT_TYPE;
+use http::Request;
+
+use multipart_async::client::prelude::*;
+use multipart_async::server::prelude::*;
+
+/// Odd-sized so that boundaries and headers are split between chunks
+const CHUNK_SIZE: usize = 7;
+
+struct Expected {
+    name: &'static str,
+    filename: Option<&'static str>,
+    content_type: Option<mime::Mime>,
+    data: Vec<u8>,
+}
+
+fn binary_data() -> Vec<u8> {
+    // every byte value, plus a near-miss of the boundary
+    let mut data: Vec<u8> = (0..=255).collect();
+    data.extend_from_slice(b"\r\n--boundary-ish\r\n--");
+    data
+}
+
+#[tokio::test]
+async fn test_writer_to_server_round_trip() {
+    let _ = env_logger::try_init();
+
+    let request = MultipartRequest::new();
+    let content_type = request.get_content_type();
+
+    let mut writer = request.wrap_writer(Vec::<u8>::new());
+
+    writer
+        .write_text("text", "hello, world!")
+        .await
+        .unwrap()
+        .write_field(
+            "file",
+            Some("file.txt"),
+            Some(&mime::TEXT_PLAIN_UTF_8),
+            &b"file contents\r\nover multiple lines\r\n"[..],
+        )
+        .await
+        .unwrap()
+        .write_bytes_field(
+            "binary",
+            Some("data.bin"),
+            Some(&mime::APPLICATION_OCTET_STREAM),
+            &binary_data(),
+        )
+        .await
+        .unwrap()
+        .write_stream(
+            "stream",
+            Some("stream.txt"),
+            None,
+            stream::iter(vec![Ok::<_, Infallible>("streamed "), Ok("contents")]),
+        )
+        .await
+        .unwrap()
+        .write_text("empty", "")
+        .await
+        .unwrap();
+
+    let body = writer.finish_into_inner().await.unwrap();
+
+    let expected = vec![
+        Expected {
+            name: "text",
+            filename: None,
+            content_type: None,
+            data: b"hello, world!".to_vec(),
+        },
+        Expected {
+            name: "file",
+            filename: Some("file.txt"),
+            content_type: Some(mime::TEXT_PLAIN_UTF_8),
+            data: b"file contents\r\nover multiple lines\r\n".to_vec(),
+        },
+        Expected {
+            name: "binary",
+            filename: Some("data.bin"),
+            content_type: Some(mime::APPLICATION_OCTET_STREAM),
+            data: binary_data(),
+        },
+        Expected {
+            name: "stream",
+            filename: Some("stream.txt"),
+            content_type: None,
+            data: b"streamed contents".to_vec(),
+        },
+        Expected {
+            name: "empty",
+            filename: None,
+            content_type: None,
+            data: Vec::new(),
+        },
+    ];
+
+    let chunks: Vec<_> = body.chunks(CHUNK_SIZE).map(Ok::<_, Infallible>).collect();
+
+    let req = Request::post("/")
+        .header(CONTENT_TYPE, content_type)
+        .body(stream::iter(chunks))
+        .unwrap();
+
+    let mut multipart =
+        Multipart::try_from_request(req).unwrap_or_else(|_| panic!("request should be multipart"));
+
+    for expected in &expected {
+        let field = multipart
+            .next_field()
+            .await
+            .unwrap()
+            .unwrap_or_else(|| panic!("missing field {:?}", expected.name));
+
+        assert_eq!(field.headers.name, expected.name);
+        assert_eq!(
+            field.headers.filename.as_deref(),
+            expected.filename,
+            "{}",
+            expected.name
+        );
+        assert_eq!(
+            field.headers.content_type, expected.content_type,
+            "{}",
+            expected.name
+        );
+
+        let data: Vec<&[u8]> = field.data.try_collect().await.unwrap();
+        assert_eq!(data.concat(), expected.data, "{}", expected.name);
+    }
+
+    assert!(multipart.next_field().await.unwrap().is_none());
+}